@@ -111,6 +111,8 @@ async fn test_backend(urls: Vec<(DBType, &str)>) -> Result<(), Error> {
                 let (get_db_type, _get_conn) = conn.backend_db().await?;
                 assert_eq!(DBType::SQLite.backend_name(), get_db_type.backend_name());
             }
+            #[cfg(feature = "mssql-compat")]
+            DBType::MSSQL => {}
         }
         test_adapter_query(url).await?;
     }
@@ -86,7 +86,7 @@ async fn simple_query() -> Result<(), Error> {
 #[template(
     source = r#"
     {%- let v="abc".to_string() %}
-    SELECT {{e(v)}} as v,t.* FROM table t
+    SELECT {{e(v)}} as v,t.* FROM my_table t
     WHERE arg1 = {{e(arg1)}}
       AND arg2 = {{e(arg2)}}
       AND arg3 = {{e(arg3)}}
@@ -109,7 +109,7 @@ async fn simple_query() -> Result<(), Error> {
 "#,
     print = "all"
 )]
-#[add_type(Option<&'a i64>,bool)]
+#[add_type(bool)]
 pub struct QueryData<'a, T>
 where
     T: Sized + Send + Sync,
@@ -119,6 +119,7 @@ where
     arg2: String,
     arg3: &'a str,
     #[ignore_type]
+    #[add_type(Option<&'a i64>)] // arg4.first()
     arg4: Vec<i64>,
     #[ignore_type]
     arg5: HashMap<i32, i64>,
@@ -153,6 +154,7 @@ pub struct ComplexQuery<'a> {
     min_age: Option<i32>,
     #[ignore_type]
     filter_names: Vec<&'a str>,
+    #[raw]
     order_field: &'a str,
     limit: i64,
 }
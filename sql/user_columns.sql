@@ -0,0 +1 @@
+select id, name, email from users
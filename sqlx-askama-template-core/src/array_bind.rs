@@ -0,0 +1,175 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    sync::{LazyLock, PoisonError, RwLock},
+};
+
+use sqlx_core::{
+    any::{AnyArguments, AnyTypeInfo, AnyTypeInfoKind, AnyValueKind},
+    arguments::Arguments,
+    database::Database,
+    encode::Encode,
+    error::BoxDynError,
+    types::Type,
+};
+
+/// Binds a whole list as one native array argument
+#[doc(hidden)]
+pub type ArrayBinder<DB, T> =
+    fn(&mut <DB as Database>::Arguments, Vec<T>) -> Result<(), BoxDynError>;
+
+/// [`ArrayBinder`]s by database and element type, boxed as `dyn Any`
+type ArrayBinders = HashMap<(TypeId, TypeId), Box<dyn Any + Send + Sync>>;
+
+static ARRAY_BINDERS: LazyLock<RwLock<ArrayBinders>> = LazyLock::new(Default::default);
+
+/// Lets `e_in()` bind lists of `T` as a single native array on `DB`, rendering
+/// `= ANY($1)` instead of an `IN (...)` list of one placeholder per item
+///
+/// Arrays are only bound when the rendering dialect is PostgreSQL, where
+/// `e_in()` fails for element types that were not registered. `sqlx::Any`,
+/// whose arguments have no array values, needs no registration: the list is
+/// bound as the text of an array literal, cast to the array type.
+///
+/// # Example
+/// ```
+/// use sqlx::Postgres;
+/// use sqlx_askama_template::register_array_type;
+///
+/// register_array_type::<Postgres, i64>();
+/// register_array_type::<Postgres, String>();
+/// ```
+pub fn register_array_type<DB, T>()
+where
+    DB: Database,
+    T: Send + 'static,
+    Vec<T>: for<'a> Encode<'a, DB> + Type<DB>,
+{
+    let binder: ArrayBinder<DB, T> = |arg, values| arg.add(values);
+    ARRAY_BINDERS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert((TypeId::of::<DB>(), TypeId::of::<T>()), Box::new(binder));
+}
+
+/// Binder registered for lists of `T` on `DB`
+#[doc(hidden)]
+pub fn array_binder<DB: Database, T: 'static>() -> Option<ArrayBinder<DB, T>> {
+    ARRAY_BINDERS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&(TypeId::of::<DB>(), TypeId::of::<T>()))
+        .and_then(|binder| binder.downcast_ref::<ArrayBinder<DB, T>>())
+        .copied()
+}
+
+/// Binds `values` as one PostgreSQL array through `sqlx::Any` arguments,
+/// which have no array values: the items are encoded as `Any` values and
+/// bound together as the text of an array literal
+///
+/// Returns the array type to cast the placeholder to, e.g. `int8[]`.
+pub(crate) fn bind_any_array<'t, DB, T>(
+    arguments: &mut DB::Arguments,
+    values: &'t [T],
+) -> Result<&'static str, BoxDynError>
+where
+    DB: Database,
+    &'t T: Encode<'t, DB> + Type<DB>,
+{
+    let type_info = <&T as Type<DB>>::type_info();
+    let array_type = match (&type_info as &dyn Any).downcast_ref::<AnyTypeInfo>() {
+        Some(AnyTypeInfo { kind }) => match kind {
+            AnyTypeInfoKind::Bool => "bool[]",
+            AnyTypeInfoKind::SmallInt => "int2[]",
+            AnyTypeInfoKind::Integer => "int4[]",
+            AnyTypeInfoKind::BigInt => "int8[]",
+            AnyTypeInfoKind::Real => "float4[]",
+            AnyTypeInfoKind::Double => "float8[]",
+            AnyTypeInfoKind::Text => "text[]",
+            AnyTypeInfoKind::Blob => "bytea[]",
+            AnyTypeInfoKind::Null => return Err(format!("no array type for `{type_info}`").into()),
+        },
+        None => return Err(format!("`{}` arguments are not `sqlx::Any` ones", DB::NAME).into()),
+    };
+    let mut items = DB::Arguments::default();
+    for value in values {
+        items.add(value)?;
+    }
+    let items = (&items as &dyn Any)
+        .downcast_ref::<AnyArguments>()
+        .ok_or("not `sqlx::Any` arguments")?;
+    let mut literal = String::from("{");
+    for (i, item) in items.values.0.iter().enumerate() {
+        if i > 0 {
+            literal.push(',');
+        }
+        push_array_item(item, &mut literal)?;
+    }
+    literal.push('}');
+    (arguments as &mut dyn Any)
+        .downcast_mut::<AnyArguments>()
+        .ok_or("not `sqlx::Any` arguments")?
+        .add(literal)?;
+    Ok(array_type)
+}
+
+/// Appends one item of a PostgreSQL array literal
+fn push_array_item(item: &AnyValueKind, out: &mut String) -> Result<(), BoxDynError> {
+    let float = |value: f64| match value {
+        _ if value.is_nan() => "NaN".to_string(),
+        f64::INFINITY => "Infinity".to_string(),
+        f64::NEG_INFINITY => "-Infinity".to_string(),
+        _ => value.to_string(),
+    };
+    let quoted = |text: &str, out: &mut String| {
+        out.push('"');
+        for c in text.chars() {
+            if c == '"' || c == '\\' {
+                out.push('\\');
+            }
+            out.push(c);
+        }
+        out.push('"');
+    };
+    match item {
+        AnyValueKind::Null(_) => out.push_str("NULL"),
+        AnyValueKind::Bool(value) => out.push(if *value { 't' } else { 'f' }),
+        AnyValueKind::SmallInt(value) => out.push_str(&value.to_string()),
+        AnyValueKind::Integer(value) => out.push_str(&value.to_string()),
+        AnyValueKind::BigInt(value) => out.push_str(&value.to_string()),
+        AnyValueKind::Real(value) => out.push_str(&float(f64::from(*value))),
+        AnyValueKind::Double(value) => out.push_str(&float(*value)),
+        AnyValueKind::Text(value) => quoted(value, out),
+        AnyValueKind::TextSlice(value) => quoted(value, out),
+        AnyValueKind::Blob(value) => {
+            let hex: String = value.iter().map(|b| format!("{b:02x}")).collect();
+            quoted(&format!("\\x{hex}"), out);
+        }
+        other => return Err(format!("`{other:?}` can not be an array item").into()),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx_core::any::Any as AnyDb;
+
+    #[test]
+    fn any_array_literal() {
+        let mut arguments = AnyArguments::default();
+        let values = ["a".to_string(), "b\"c\\".to_string()];
+        let array_type = bind_any_array::<AnyDb, String>(&mut arguments, &values).unwrap();
+        assert_eq!(array_type, "text[]");
+        let mut arguments2 = AnyArguments::default();
+        bind_any_array::<AnyDb, f64>(&mut arguments2, &[1.5, f64::NAN]).unwrap();
+        let literals: Vec<String> = [arguments, arguments2]
+            .iter()
+            .map(|arguments| match &arguments.values.0[..] {
+                [AnyValueKind::Text(text)] => text.to_string(),
+                other => panic!("unexpected arguments {other:?}"),
+            })
+            .collect();
+        assert_eq!(literals, [r#"{"a","b\"c\\"}"#, "{1.5,NaN}"]);
+    }
+}
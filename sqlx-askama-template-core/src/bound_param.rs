@@ -0,0 +1,55 @@
+use std::cell::RefCell;
+
+/// One parameter bound by a render, in placeholder order
+///
+/// Collected by `SqlTemplate::render_to_writer` for its parameter manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoundParam {
+    /// 1-based position among the arguments
+    pub index: usize,
+    /// Placeholder written into the SQL, e.g. `$1` or `?`
+    pub placeholder: String,
+    /// Label given with `e_named()`, `None` for `e()`, `el()` and the like
+    pub name: Option<String>,
+    /// Rust type of the bound value without references, e.g. `i64` or
+    /// `alloc::string::String`
+    pub type_name: &'static str,
+    /// Bound from a `#[param(secret)]` field or with `e_secret()`, so loggers
+    /// print `<redacted>` instead of its value
+    pub secret: bool,
+}
+
+thread_local! {
+    static BOUND: RefCell<Option<Vec<BoundParam>>> = const { RefCell::new(None) };
+}
+
+/// Restores the previous recording when the render ends, even by panic
+struct Restore(Option<Vec<BoundParam>>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        BOUND.with(|b| *b.borrow_mut() = previous);
+    }
+}
+
+/// Runs the (synchronous) render `f`, returning its result and the parameters
+/// it bound
+#[doc(hidden)]
+pub fn record_bound_params<R>(f: impl FnOnce() -> R) -> (R, Vec<BoundParam>) {
+    let previous = BOUND.with(|b| b.borrow_mut().replace(Vec::new()));
+    let restore = Restore(previous);
+    let result = f();
+    let params = BOUND.with(|b| b.borrow_mut().take()).unwrap_or_default();
+    drop(restore);
+    (result, params)
+}
+
+/// Adds a parameter to the running [`record_bound_params`], if any
+pub(crate) fn push_bound_param(param: impl FnOnce() -> BoundParam) {
+    BOUND.with(|b| {
+        if let Some(params) = b.borrow_mut().as_mut() {
+            params.push(param());
+        }
+    });
+}
@@ -0,0 +1,18 @@
+use std::fmt;
+
+/// Failed `build()` of a builder generated by `#[sql_template(builder)]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuilderError {
+    /// A field that is not an `Option` was never set
+    MissingField(&'static str),
+}
+
+impl fmt::Display for BuilderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingField(field) => write!(f, "field `{field}` was not set"),
+        }
+    }
+}
+
+impl std::error::Error for BuilderError {}
@@ -0,0 +1,82 @@
+use std::{cell::RefCell, collections::HashMap, sync::Arc};
+
+/// Values shared by all templates of a render, read in templates with `ctx()`
+///
+/// For cross-cutting values such as the application name or feature flags
+/// that should not live on every template struct. Attached with
+/// `DBAdapter::with_context` or `SqlTemplate::render_with_context`.
+///
+/// # Example
+/// ```no_run
+/// use sqlx::SqlitePool;
+/// use sqlx_askama_template::{SqlTemplate, TemplateContext};
+///
+/// #[derive(SqlTemplate)]
+/// #[template(source = r#"
+///     select * from users where app = {{ e(ctx("app")) }}
+///     {% if ctx_flag("soft_delete") %} and deleted_at is null {% endif %}
+/// "#)]
+/// #[add_type(String)]
+/// struct UserQuery;
+///
+/// # async fn run(pool: SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+/// let ctx = TemplateContext::new().with("app", "billing").with("soft_delete", "true");
+/// let users = UserQuery.adapter().with_context(ctx).fetch_all(&pool).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TemplateContext {
+    values: HashMap<String, String>,
+}
+
+impl TemplateContext {
+    /// Creates an empty context
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Adds a value
+    pub fn with(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.values.insert(key.into(), value.into());
+        self
+    }
+    /// Sets a value
+    pub fn set(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.values.insert(key.into(), value.into());
+    }
+    /// Returns a value
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Arc<TemplateContext>>> = const { RefCell::new(None) };
+}
+
+/// Restores the previous context when the render ends, even by panic
+struct Restore(Option<Arc<TemplateContext>>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        CURRENT.with(|c| *c.borrow_mut() = previous);
+    }
+}
+
+/// Makes `context` current for the duration of the (synchronous) render `f`
+#[doc(hidden)]
+pub fn with_context<R>(context: Option<&Arc<TemplateContext>>, f: impl FnOnce() -> R) -> R {
+    let Some(context) = context else {
+        return f();
+    };
+    let previous = CURRENT.with(|c| c.borrow_mut().replace(context.clone()));
+    let _restore = Restore(previous);
+    f()
+}
+
+/// Reads a value of the current context
+#[doc(hidden)]
+pub fn current_value<R>(key: &str, f: impl FnOnce(Option<&str>) -> R) -> R {
+    CURRENT.with(|c| f(c.borrow().as_ref().and_then(|ctx| ctx.get(key))))
+}
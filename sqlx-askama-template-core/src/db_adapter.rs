@@ -0,0 +1,1536 @@
+use std::{
+    any::Any,
+    fmt,
+    marker::PhantomData,
+    ops::Deref,
+    sync::{Arc, PoisonError, RwLock},
+    time::Duration,
+};
+
+use futures_util::TryStreamExt;
+
+#[cfg(feature = "mssql-compat")]
+use crate::statement::{has_top_level_order_by, top_level_order_by};
+use crate::{
+    Savepoint, SortSpec, SqlTemplateExecute, UsageError,
+    sort::write_sort,
+    statement::{
+        has_top_level_keyword, split_trailing, starts_with_keyword, top_level_order_by_keys,
+        top_level_words,
+    },
+};
+use sqlx_core::{
+    Either, Error,
+    any::{AnyConnection, AnyPool},
+    arguments::Arguments,
+    database::Database,
+    describe::Describe,
+    encode::Encode,
+    error::BoxDynError,
+    executor::{Execute, Executor},
+    pool::{Pool, PoolConnection},
+    sql_str::SqlStr,
+    types::Type,
+};
+/// Total row count column of [`DatabaseDialect::write_counted_pagination_sql`]
+pub const COUNTED_TOTAL_COLUMN: &str = "sqlx_askama_total";
+/// Row number column of [`DatabaseDialect::write_counted_pagination_sql`]
+pub const COUNTED_ROW_COLUMN: &str = "sqlx_askama_row";
+
+/// Abstracts SQL dialect differences across database systems
+///
+/// Provides a unified interface for handling database-specific SQL syntax variations,
+/// particularly for parameter binding, count queries, and pagination.
+pub trait DatabaseDialect: Send + Sync {
+    /// Returns the name of the database backend in use (e.g. PostgreSQL, MySQL, SQLite, etc.)
+    fn backend_name(&self) -> &str;
+    /// Gets placeholder generation function for parameter binding
+    ///
+    /// Database-specific placeholder formats:
+    /// - PostgreSQL: $1, $2...
+    /// - MySQL/SQLite: ?
+    ///
+    /// # Returns
+    /// Option<fn(usize, &mut String)> placeholder generation function
+    fn placeholder_fn(&self) -> Option<fn(usize, &mut String)>;
+    /// Wraps SQL in count query
+    ///
+    /// # Arguments
+    /// * `sql` - Original SQL to modify
+    fn write_count_sql(&self, sql: &mut String);
+    /// Wraps SQL in a query counting the distinct non-null values of `column`
+    ///
+    /// Defaults to `select count(distinct column) from (...) t`.
+    fn write_count_distinct_sql(&self, sql: &mut String, column: &str) {
+        let trailing = split_trailing(sql);
+        *sql = format!("select count(distinct {column}) from ({sql}) t{trailing}")
+    }
+    /// Generates pagination SQL clause
+    ///
+    /// # Arguments
+    /// * `sql` - Original SQL statement to modify
+    /// * `pagination_size` - Items per pagination
+    /// * `pagination_no` - Pagination number (auto-corrected to >=1)
+    /// * `arg` - SQL arguments container
+    ///
+    /// # Note
+    /// Automatically handles invalid pagination numbers; a `pagination_size`
+    /// of 0 or less leaves the SQL unpaginated, and the offset saturates
+    /// instead of overflowing
+    fn write_pagination_sql<'q, DB>(
+        &self,
+        sql: &mut String,
+        pagination_size: i64,
+        pagination_no: i64,
+        arg: &mut DB::Arguments,
+    ) -> Result<(), Error>
+    where
+        DB: Database,
+        i64: Encode<'q, DB> + Type<DB>;
+    /// Turns `sql` into one statement returning the requested pagination
+    /// together with the total row count, see
+    /// `DBAdapter::fetch_counted_pagination_as`
+    ///
+    /// This is a single joined statement, not a pipelined batch of the count
+    /// and pagination statements. `sql` must have a top-level `ORDER BY`,
+    /// which orders the returned rows.
+    ///
+    /// Each row gets two trailing columns: [`COUNTED_ROW_COLUMN`], `NULL` on
+    /// the single row returned for an empty pagination, and
+    /// [`COUNTED_TOTAL_COLUMN`]. Both must decode as `i64`.
+    ///
+    /// Defaults to `Ok(false)` (unsupported).
+    fn write_counted_pagination_sql<'q, DB>(
+        &self,
+        _sql: &mut String,
+        _pagination_size: i64,
+        _pagination_no: i64,
+        _arg: &mut DB::Arguments,
+    ) -> Result<bool, Error>
+    where
+        DB: Database,
+        i64: Encode<'q, DB> + Type<DB>,
+    {
+        Ok(false)
+    }
+    /// Statements applying a database-side `timeout` around a query on a
+    /// dedicated connection: `(before, after)`
+    ///
+    /// Without `after` statements the setting is considered permanent, and the
+    /// connection is closed after the query instead of returned to the pool.
+    ///
+    /// Defaults to `None`, for dialects that set the timeout in the SQL itself
+    /// (see [`Self::write_timeout_sql`]) or do not support one.
+    fn timeout_statements(&self, _timeout: Duration) -> Option<(Vec<String>, Vec<String>)> {
+        None
+    }
+    /// Applies a database-side `timeout` to the SQL itself, e.g. as an optimizer hint
+    fn write_timeout_sql(&self, _sql: &mut String, _timeout: Duration) {}
+    /// Appends a row `limit` to a `SELECT` statement without a top-level
+    /// `LIMIT`/`FETCH`, see `set_safety_limit`
+    fn write_safety_limit(&self, sql: &mut String, limit: u64) {
+        limit_unbounded_select(sql, limit);
+    }
+    /// Appends ` ORDER BY ...` for `sort`, with validated column names
+    ///
+    /// Defaults to the standard `NULLS FIRST`/`NULLS LAST` syntax.
+    fn write_sort_sql(&self, sql: &mut String, sort: &[SortSpec]) {
+        write_sort(sql, sort, true);
+    }
+    /// Statement setting a configuration parameter for the current transaction
+    /// only, e.g. for row-level security policies; the parameter name and its
+    /// value are bound as the two arguments of the statement
+    ///
+    /// Defaults to `None` (unsupported).
+    fn local_setting_statement(&self) -> Option<String> {
+        None
+    }
+    /// Statement cancelling, from another connection, the running query whose
+    /// SQL starts with the comment `/* {tag} */`, see
+    /// `DBAdapter::with_cancellation`
+    ///
+    /// Defaults to `None`: the query is only abandoned on the client side.
+    fn cancel_statement(&self, _tag: &str) -> Option<String> {
+        None
+    }
+    /// Maximum number of bind parameters in one statement (default 65535)
+    fn max_bind_params(&self) -> usize {
+        65535
+    }
+    /// Statements creating, rolling back to and releasing the savepoint `name`
+    ///
+    /// Defaults to the standard `SAVEPOINT`, `ROLLBACK TO SAVEPOINT` and
+    /// `RELEASE SAVEPOINT`.
+    fn savepoint(&self, name: &str) -> Savepoint {
+        let mut ident = String::new();
+        self.quote_identifier(name, &mut ident);
+        Savepoint {
+            create: format!("SAVEPOINT {ident}"),
+            rollback: format!("ROLLBACK TO SAVEPOINT {ident}"),
+            release: Some(format!("RELEASE SAVEPOINT {ident}")),
+        }
+    }
+    /// Appends `ident` as a quoted identifier
+    ///
+    /// Defaults to ANSI double quotes, doubling embedded quotes.
+    fn quote_identifier(&self, ident: &str, out: &mut String) {
+        quote_identifier_with(ident, '"', '"', out);
+    }
+    /// Replaces every `{schema}` token outside string literals with the quoted `schema`
+    ///
+    /// # Example
+    /// ```
+    /// use sqlx_askama_template::{DBType, DatabaseDialect};
+    ///
+    /// let mut sql = "select * from {schema}.users where note = '{schema}'".to_string();
+    /// DBType::PostgreSQL.write_schema(&mut sql, "tenant_42");
+    /// assert_eq!(sql, r#"select * from "tenant_42".users where note = '{schema}'"#);
+    /// ```
+    fn write_schema(&self, sql: &mut String, schema: &str) {
+        const TOKEN: &str = "{schema}";
+        if !sql.contains(TOKEN) {
+            return;
+        }
+        let mut out = String::with_capacity(sql.len());
+        let mut in_string = false;
+        let mut rest = sql.as_str();
+        while let Some(c) = rest.chars().next() {
+            if !in_string && rest.starts_with(TOKEN) {
+                self.quote_identifier(schema, &mut out);
+                rest = &rest[TOKEN.len()..];
+                continue;
+            }
+            if c == '\'' {
+                in_string = !in_string;
+            }
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+        *sql = out;
+    }
+}
+
+/// Appends `ident` between `open` and `close`, doubling embedded `close` characters
+pub(crate) fn quote_identifier_with(ident: &str, open: char, close: char, out: &mut String) {
+    out.push(open);
+    for c in ident.chars() {
+        if c == close {
+            out.push(close);
+        }
+        out.push(c);
+    }
+    out.push(close);
+}
+
+/// Database type enumeration supporting major database systems
+#[derive(Debug, PartialEq)]
+pub enum DBType {
+    /// PostgreSQL database
+    PostgreSQL,
+    /// MySQL database
+    MySQL,
+    /// SQLite database
+    SQLite,
+    /// Microsoft SQL Server database
+    #[cfg(feature = "mssql-compat")]
+    MSSQL,
+}
+impl DBType {
+    /// Creates a DBType instance from database name
+    ///
+    /// # Arguments
+    /// * `db_name` - Database identifier ("PostgreSQL"|"MySQL"|"SQLite"|"MSSQL")
+    ///
+    /// # Errors
+    /// Returns [`UsageError::Unsupported`] for unsupported database types
+    ///
+    /// # Example
+    /// ```
+    /// use sqlx_askama_template::DBType;
+    ///
+    /// let db_type = DBType::new("PostgreSQL")?;
+    /// assert_eq!(db_type, DBType::PostgreSQL);
+    /// assert!(DBType::new("Oracle").is_err());
+    /// # Ok::<(), sqlx::Error>(())
+    /// ```
+    /// Unknown names are passed to the resolver installed with
+    /// [`set_dialect_resolver`] before failing. libSQL and Turso backends
+    /// ([`LIBSQL_BACKEND_NAMES`]) are SQLite unless the resolver maps them.
+    pub fn new(db_name: &str) -> Result<Self, Error> {
+        match db_name {
+            "PostgreSQL" => Ok(Self::PostgreSQL),
+            "MySQL" => Ok(Self::MySQL),
+            "SQLite" => Ok(Self::SQLite),
+            #[cfg(feature = "mssql-compat")]
+            "MSSQL" | "Microsoft SQL Server" => Ok(Self::MSSQL),
+            _ => {
+                let resolver = *DIALECT_RESOLVER
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner);
+                resolver
+                    .and_then(|r| r(db_name))
+                    .or_else(|| {
+                        LIBSQL_BACKEND_NAMES
+                            .iter()
+                            .any(|name| name.eq_ignore_ascii_case(db_name))
+                            .then_some(Self::SQLite)
+                    })
+                    .ok_or_else(|| {
+                        Error::from(UsageError::Unsupported(format!(
+                            "unsupported db `{db_name}`; compiled-in dialects: {}; \
+                             map other backends with `set_dialect_resolver`",
+                            Self::supported().join(", ")
+                        )))
+                    })
+            }
+        }
+    }
+    /// Names of the dialects compiled into this build
+    pub fn supported() -> &'static [&'static str] {
+        &[
+            "PostgreSQL",
+            "MySQL",
+            "SQLite",
+            #[cfg(feature = "mssql-compat")]
+            "MSSQL",
+        ]
+    }
+}
+
+/// Backend names of libSQL / Turso drivers, recognized (ignoring ASCII case)
+/// by [`DBType::new`] as [`DBType::SQLite`]
+pub const LIBSQL_BACKEND_NAMES: &[&str] = &["libSQL", "Turso"];
+
+/// Maps a backend name unknown to [`DBType::new`] to a known dialect
+pub type DialectResolver = fn(&str) -> Option<DBType>;
+
+static DIALECT_RESOLVER: RwLock<Option<DialectResolver>> = RwLock::new(None);
+
+/// Installs a global [`DialectResolver`], replacing any previous one
+///
+/// # Example
+/// ```standalone_crate
+/// use sqlx_askama_template::{DBType, set_dialect_resolver};
+///
+/// // treat wire-compatible backends as their known dialect
+/// set_dialect_resolver(|name| match name {
+///     "CockroachDB" => Some(DBType::PostgreSQL),
+///     "MariaDB" => Some(DBType::MySQL),
+///     _ => None,
+/// });
+/// assert_eq!(DBType::new("CockroachDB")?, DBType::PostgreSQL);
+/// # Ok::<(), sqlx::Error>(())
+/// ```
+pub fn set_dialect_resolver(resolver: DialectResolver) {
+    *DIALECT_RESOLVER
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = Some(resolver);
+}
+
+impl DatabaseDialect for DBType {
+    fn backend_name(&self) -> &str {
+        match self {
+            Self::PostgreSQL => "PostgreSQL",
+            Self::MySQL => "MySQL",
+            Self::SQLite => "SQLite",
+            #[cfg(feature = "mssql-compat")]
+            Self::MSSQL => "MSSQL",
+        }
+    }
+    /// Gets placeholder generation function for parameter binding
+    ///
+    /// Database-specific placeholder formats:
+    /// - PostgreSQL: $1, $2...
+    /// - MySQL/SQLite: ?
+    /// - MSSQL: @p1, @p2...
+    ///
+    /// # Returns
+    /// Option<fn(usize, &mut String)> placeholder generation function
+    fn placeholder_fn(&self) -> Option<fn(usize, &mut String)> {
+        match self {
+            Self::PostgreSQL => Some(|i: usize, s: &mut String| s.push_str(&format!("${i}"))),
+            Self::MySQL | Self::SQLite => Some(|_: usize, s: &mut String| s.push('?')),
+            #[cfg(feature = "mssql-compat")]
+            Self::MSSQL => Some(|i: usize, s: &mut String| s.push_str(&format!("@p{i}"))),
+        }
+    }
+    /// Wraps SQL in count query
+    ///
+    /// # Arguments
+    /// * `sql` - Original SQL to modify
+    fn write_count_sql(&self, sql: &mut String) {
+        match self {
+            Self::PostgreSQL | DBType::MySQL | DBType::SQLite => {
+                pg_mysql_sqlite_count_sql(sql);
+            }
+            #[cfg(feature = "mssql-compat")]
+            Self::MSSQL => mssql_count_sql(sql, "count(1)"),
+        }
+    }
+    fn write_count_distinct_sql(&self, sql: &mut String, column: &str) {
+        match self {
+            Self::PostgreSQL | DBType::MySQL | DBType::SQLite => {
+                let trailing = split_trailing(sql);
+                *sql = format!("select count(distinct {column}) from ({sql}) t{trailing}")
+            }
+            #[cfg(feature = "mssql-compat")]
+            Self::MSSQL => mssql_count_sql(sql, &format!("count(distinct {column})")),
+        }
+    }
+    /// Generates pagination SQL clause
+    ///
+    /// # Arguments
+    /// * `sql` - Original SQL statement to modify
+    /// * `pagination_size` - Items per pagination
+    /// * `pagination_no` - Pagination number (auto-corrected to >=1)
+    /// * `arg` - SQL arguments container
+    ///
+    /// # Note
+    /// Automatically handles invalid pagination numbers; a `pagination_size`
+    /// of 0 or less leaves the SQL unpaginated, and the offset saturates
+    /// instead of overflowing
+    fn write_pagination_sql<'q, DB>(
+        &self,
+        sql: &mut String,
+        pagination_size: i64,
+        pagination_no: i64,
+
+        arg: &mut DB::Arguments,
+    ) -> Result<(), Error>
+    where
+        DB: Database,
+        i64: Encode<'q, DB> + Type<DB>,
+    {
+        let f = self.placeholder_fn();
+        match self {
+            Self::PostgreSQL | DBType::MySQL | DBType::SQLite => {
+                pg_mysql_sqlite_pagination_sql(sql, pagination_size, pagination_no, f, arg)?;
+                Ok(())
+            }
+            #[cfg(feature = "mssql-compat")]
+            Self::MSSQL => mssql_pagination_sql(sql, pagination_size, pagination_no, f, arg),
+        }
+    }
+    /// PostgreSQL only: the template SQL appears twice in the statement, which
+    /// needs numbered placeholders to share its arguments
+    fn write_counted_pagination_sql<'q, DB>(
+        &self,
+        sql: &mut String,
+        pagination_size: i64,
+        pagination_no: i64,
+        arg: &mut DB::Arguments,
+    ) -> Result<bool, Error>
+    where
+        DB: Database,
+        i64: Encode<'q, DB> + Type<DB>,
+    {
+        match self {
+            Self::PostgreSQL => {
+                pg_counted_pagination_sql(sql, pagination_size, pagination_no, arg)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+    /// PostgreSQL: `SET LOCAL statement_timeout` in a transaction committed
+    /// after the query; SQLite: `PRAGMA busy_timeout`, the connection is then
+    /// closed after the query
+    fn timeout_statements(&self, timeout: Duration) -> Option<(Vec<String>, Vec<String>)> {
+        let ms = timeout.as_millis();
+        match self {
+            Self::PostgreSQL => Some((
+                vec![
+                    "BEGIN".to_string(),
+                    format!("SET LOCAL statement_timeout = {ms}"),
+                ],
+                vec!["COMMIT".to_string()],
+            )),
+            Self::SQLite => Some((vec![format!("PRAGMA busy_timeout = {ms}")], Vec::new())),
+            _ => None,
+        }
+    }
+    /// `LIMIT n` suffix; MSSQL: `TOP (n)` after `SELECT [DISTINCT]`
+    fn write_safety_limit(&self, sql: &mut String, limit: u64) {
+        match self {
+            #[cfg(feature = "mssql-compat")]
+            Self::MSSQL => mssql_limit_unbounded_select(sql, limit),
+            _ => limit_unbounded_select(sql, limit),
+        }
+    }
+    /// MySQL and MSSQL emulate `NULLS FIRST`/`NULLS LAST` with a `CASE` key
+    fn write_sort_sql(&self, sql: &mut String, sort: &[SortSpec]) {
+        match self {
+            Self::PostgreSQL | Self::SQLite => write_sort(sql, sort, true),
+            _ => write_sort(sql, sort, false),
+        }
+    }
+    /// PostgreSQL: `SELECT set_config($1, $2::text, true)`
+    fn local_setting_statement(&self) -> Option<String> {
+        (*self == Self::PostgreSQL).then(|| "SELECT set_config($1, $2::text, true)".to_string())
+    }
+    /// MySQL: `/*+ MAX_EXECUTION_TIME(ms) */` hint after the leading `SELECT`,
+    /// or the main `SELECT` of a `WITH` statement, skipping leading comments
+    ///
+    /// MySQL only honors the hint on `SELECT` statements, other statements are
+    /// left without timeout.
+    fn write_timeout_sql(&self, sql: &mut String, timeout: Duration) {
+        if *self != Self::MySQL {
+            return;
+        }
+        let end = {
+            let mut words = top_level_words(sql);
+            let select = match words.next() {
+                Some(word) if word.eq_ignore_ascii_case("select") => Some(word),
+                Some(word) if word.eq_ignore_ascii_case("with") => {
+                    words.find(|word| word.eq_ignore_ascii_case("select"))
+                }
+                _ => None,
+            };
+            select.map(|word| word.as_ptr() as usize - sql.as_ptr() as usize + word.len())
+        };
+        if let Some(end) = end {
+            let hint = format!(" /*+ MAX_EXECUTION_TIME({}) */", timeout.as_millis());
+            sql.insert_str(end, &hint);
+        }
+    }
+    /// PostgreSQL: `pg_cancel_backend` on the backend running the tagged query
+    fn cancel_statement(&self, tag: &str) -> Option<String> {
+        match self {
+            Self::PostgreSQL => Some(format!(
+                "SELECT pg_cancel_backend(pid) FROM pg_stat_activity \
+                 WHERE pid <> pg_backend_pid() AND query LIKE '/* {tag} */%'"
+            )),
+            _ => None,
+        }
+    }
+    /// 65535 for PostgreSQL and MySQL, 32766 for SQLite, 2100 for MSSQL
+    fn max_bind_params(&self) -> usize {
+        match self {
+            Self::SQLite => 32766,
+            #[cfg(feature = "mssql-compat")]
+            Self::MSSQL => 2100,
+            _ => 65535,
+        }
+    }
+    /// MSSQL: `SAVE TRANSACTION`/`ROLLBACK TRANSACTION`, which has no release;
+    /// standard `SAVEPOINT` statements otherwise
+    fn savepoint(&self, name: &str) -> Savepoint {
+        let mut ident = String::new();
+        self.quote_identifier(name, &mut ident);
+        match self {
+            #[cfg(feature = "mssql-compat")]
+            Self::MSSQL => Savepoint {
+                create: format!("SAVE TRANSACTION {ident}"),
+                rollback: format!("ROLLBACK TRANSACTION {ident}"),
+                release: None,
+            },
+            _ => Savepoint {
+                create: format!("SAVEPOINT {ident}"),
+                rollback: format!("ROLLBACK TO SAVEPOINT {ident}"),
+                release: Some(format!("RELEASE SAVEPOINT {ident}")),
+            },
+        }
+    }
+    /// Appends a quoted identifier: backticks for MySQL, brackets for MSSQL,
+    /// double quotes otherwise
+    fn quote_identifier(&self, ident: &str, out: &mut String) {
+        match self {
+            Self::PostgreSQL | Self::SQLite => quote_identifier_with(ident, '"', '"', out),
+            Self::MySQL => quote_identifier_with(ident, '`', '`', out),
+            #[cfg(feature = "mssql-compat")]
+            Self::MSSQL => quote_identifier_with(ident, '[', ']', out),
+        }
+    }
+}
+
+/// Appends `limit n` to a `SELECT` without a top-level `LIMIT`/`FETCH`
+fn limit_unbounded_select(sql: &mut String, limit: u64) {
+    if !starts_with_keyword(sql, "select") || has_top_level_keyword(sql, &["limit", "fetch"]) {
+        return;
+    }
+    let trailing = split_trailing(sql);
+    sql.push_str(&format!(" limit {limit}{trailing}"));
+}
+
+/// Inserts `top (n)` into a `SELECT` without a top-level `TOP`/`FETCH`
+#[cfg(feature = "mssql-compat")]
+fn mssql_limit_unbounded_select(sql: &mut String, limit: u64) {
+    if !starts_with_keyword(sql, "select") || has_top_level_keyword(sql, &["top", "fetch"]) {
+        return;
+    }
+    let mut at = sql.len() - sql.trim_start().len() + "select".len();
+    for modifier in ["distinct", "all"] {
+        if starts_with_keyword(&sql[at..], modifier) {
+            let skipped = sql[at..].len() - sql[at..].trim_start().len();
+            at += skipped + modifier.len();
+            break;
+        }
+    }
+    sql.insert_str(at, &format!(" top ({limit})"));
+}
+
+/// Generates count SQL query wrapping the original SQL for PostgreSQL/MySQL/SQLite databases
+fn pg_mysql_sqlite_count_sql(sql: &mut String) {
+    let trailing = split_trailing(sql);
+    *sql = format!("select count(1) from ({sql}) t{trailing}")
+}
+/// Generates count SQL query (`select {count} from (...) t`) wrapping the
+/// original SQL for SQL Server
+///
+/// SQL Server rejects an `ORDER BY` in a derived table unless `TOP`,
+/// `OFFSET` or `FOR XML`/`FOR JSON` is also given, so a top-level `ORDER BY`
+/// is dropped before wrapping when the query has none of them; the order does
+/// not change the count.
+#[cfg(feature = "mssql-compat")]
+fn mssql_count_sql(sql: &mut String, count: &str) {
+    let trailing = split_trailing(sql);
+    let limited = {
+        let mut words = top_level_words(sql).peekable();
+        let mut limited = false;
+        while let Some(word) = words.next() {
+            limited |= word.eq_ignore_ascii_case("top")
+                || word.eq_ignore_ascii_case("offset")
+                || (word.eq_ignore_ascii_case("for")
+                    && words.peek().is_some_and(|w| {
+                        w.eq_ignore_ascii_case("xml") || w.eq_ignore_ascii_case("json")
+                    }));
+        }
+        limited
+    };
+    if let (false, Some(at)) = (limited, top_level_order_by(sql)) {
+        sql.truncate(at);
+        sql.truncate(sql.trim_end().len());
+    }
+    *sql = format!("select {count} from ({sql}) t{trailing}")
+}
+/// Generates pagination SQL clause for PostgreSQL/MySQL/SQLite databases
+fn pg_mysql_sqlite_pagination_sql<'q, DB>(
+    sql: &mut String,
+    pagination_size: i64,
+    pagination_no: i64,
+    f: Option<fn(usize, &mut String)>,
+    arg: &mut DB::Arguments,
+) -> Result<(), Error>
+where
+    DB: Database,
+    i64: Encode<'q, DB> + Type<DB>,
+{
+    if pagination_size < 1 {
+        return Ok(());
+    }
+    // pages past i64::MAX rows are empty
+    let offset = (pagination_no.max(1) - 1).saturating_mul(pagination_size);
+    let trailing = split_trailing(sql);
+    if let Some(f) = f {
+        sql.push_str(" limit ");
+        arg.add(pagination_size).map_err(Error::Encode)?;
+        f(arg.len(), sql);
+        sql.push_str(" offset ");
+        arg.add(offset).map_err(Error::Encode)?;
+        f(arg.len(), sql);
+    } else {
+        sql.push_str(" limit ");
+        arg.add(pagination_size).map_err(Error::Encode)?;
+        arg.format_placeholder(sql)
+            .map_err(|e| Error::Encode(Box::new(e)))?;
+
+        sql.push_str(" offset ");
+        arg.add(offset).map_err(Error::Encode)?;
+        arg.format_placeholder(sql)
+            .map_err(|e| Error::Encode(Box::new(e)))?;
+    }
+    sql.push_str(&trailing);
+    Ok(())
+}
+
+/// Generates the count-and-pagination statement of PostgreSQL
+///
+/// One joined statement, not a pipelined batch: the count is left joined to
+/// the pagination, so an empty pagination still returns the total on one row
+/// whose [`COUNTED_ROW_COLUMN`] is `NULL`.
+///
+/// The query must have a top-level `ORDER BY`. It is paginated at its own
+/// level, so the `ORDER BY` and `LIMIT`/`OFFSET` pick the rows of the page;
+/// [`COUNTED_ROW_COLUMN`] then numbers them with
+/// `row_number() over (order by ...)` on the same keys, read as output
+/// columns of the page, and the joined statement is ordered by it.
+fn pg_counted_pagination_sql<'q, DB>(
+    sql: &mut String,
+    pagination_size: i64,
+    pagination_no: i64,
+    arg: &mut DB::Arguments,
+) -> Result<(), Error>
+where
+    DB: Database,
+    i64: Encode<'q, DB> + Type<DB>,
+{
+    let trailing = split_trailing(sql);
+    let Some(keys) = top_level_order_by_keys(sql) else {
+        return Err(Error::from(UsageError::Unsupported(
+            "counting and paginating in one statement needs a top-level ORDER BY".to_string(),
+        )));
+    };
+    let order = keys
+        .into_iter()
+        .map(output_column_key)
+        .collect::<Result<Vec<_>, _>>()?
+        .join(", ");
+    let mut page = sql.clone();
+    pg_mysql_sqlite_pagination_sql(
+        &mut page,
+        pagination_size,
+        pagination_no,
+        DBType::PostgreSQL.placeholder_fn(),
+        arg,
+    )?;
+    *sql = format!(
+        "select p.*, c.{COUNTED_TOTAL_COLUMN} from (select count(1) as {COUNTED_TOTAL_COLUMN} from ({sql}) t) c \
+         left join (select q.*, row_number() over (order by {order}) as {COUNTED_ROW_COLUMN} from ({page}) q) p \
+         on true order by p.{COUNTED_ROW_COLUMN}{trailing}"
+    );
+    Ok(())
+}
+
+/// Rewrites an `ORDER BY` key to sort the output columns of the query:
+/// `u.name desc` becomes `name desc`; positions are kept
+///
+/// Fails for expressions, which are not columns of the wrapped query.
+fn output_column_key(key: &str) -> Result<String, Error> {
+    let bytes = key.as_bytes();
+    let (mut i, mut column, mut is_column) = (0, 0, !key.is_empty());
+    while i < bytes.len() && !bytes[i].is_ascii_whitespace() {
+        match bytes[i] {
+            b'"' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    i += 1;
+                }
+            }
+            b'.' => column = i + 1,
+            c => is_column &= c.is_ascii_alphanumeric() || c == b'_',
+        }
+        i += 1;
+    }
+    let i = i.min(key.len());
+    let modifiers = &key[i..];
+    is_column &= modifiers.split_whitespace().all(|w| {
+        ["asc", "desc", "nulls", "first", "last"]
+            .iter()
+            .any(|kw| w.eq_ignore_ascii_case(kw))
+    });
+    if !is_column {
+        return Err(Error::from(UsageError::Unsupported(format!(
+            "counting and paginating in one statement needs ORDER BY keys that are output columns, not `{key}`"
+        ))));
+    }
+    Ok(format!("{}{modifiers}", &key[column..i]))
+}
+
+/// Generates pagination SQL clause for SQL Server (`OFFSET ... ROWS FETCH NEXT ... ROWS ONLY`)
+///
+/// SQL Server requires an `ORDER BY` before `OFFSET`, so `order by (select null)`
+/// is appended when the statement has no top-level one (an `ORDER BY` in a
+/// subquery, an `OVER (...)` window, a literal or a comment does not count).
+#[cfg(feature = "mssql-compat")]
+fn mssql_pagination_sql<'q, DB>(
+    sql: &mut String,
+    pagination_size: i64,
+    pagination_no: i64,
+    f: Option<fn(usize, &mut String)>,
+    arg: &mut DB::Arguments,
+) -> Result<(), Error>
+where
+    DB: Database,
+    i64: Encode<'q, DB> + Type<DB>,
+{
+    if pagination_size < 1 {
+        return Ok(());
+    }
+    // pages past i64::MAX rows are empty
+    let offset = (pagination_no.max(1) - 1).saturating_mul(pagination_size);
+    let trailing = split_trailing(sql);
+    if !has_top_level_order_by(sql) {
+        sql.push_str(" order by (select null)");
+    }
+    let f = f.unwrap_or(|i: usize, s: &mut String| s.push_str(&format!("@p{i}")));
+    sql.push_str(" offset ");
+    arg.add(offset).map_err(Error::Encode)?;
+    f(arg.len(), sql);
+    sql.push_str(" rows fetch next ");
+    arg.add(pagination_size).map_err(Error::Encode)?;
+    f(arg.len(), sql);
+    sql.push_str(" rows only");
+    sql.push_str(&trailing);
+    Ok(())
+}
+
+/// Trait for database connections/pools that can detect their backend type
+///
+/// # Type Parameters
+/// - `'c`: Connection lifetime
+/// - `DB`: Database type implementing [`sqlx::Database`]
+///
+/// # Required Implementations
+/// Automatically implemented for types that:
+/// - Implement [`Executor`] for database operations
+/// - Implement [`Deref`] to an [`Any`] type
+///
+/// # Provided Methods
+/// [`backend_db`]: Default implementation using the module-level function
+pub trait BackendDB<'c, DB>: Send
+where
+    DB: Database,
+{
+    type Executor: Executor<'c, Database = DB> + 'c;
+    type DatabaseDialect: DatabaseDialect;
+    fn backend_db(
+        self,
+    ) -> impl std::future::Future<
+        Output = Result<(Self::DatabaseDialect, Self::Executor), crate::Error>,
+    > + Send;
+    /// Like [`Self::backend_db`], with a hint whether the query reads or writes
+    ///
+    /// Adapters routing to several databases (see `ReadWriteAdapter`)
+    /// override this; the default ignores the hint.
+    fn backend_db_for(
+        self,
+        _access: QueryAccess,
+    ) -> impl std::future::Future<
+        Output = Result<(Self::DatabaseDialect, Self::Executor), crate::Error>,
+    > + Send
+    where
+        Self: Sized,
+    {
+        self.backend_db()
+    }
+    /// Like [`Self::backend_db_for`], first preparing the session of the
+    /// connection that will execute the query
+    ///
+    /// Pools hand out a dedicated connection for this. The default fails with
+    /// [`crate::Error::Dialect`].
+    fn backend_db_with_session(
+        self,
+        _access: QueryAccess,
+        _session: &SessionSetup<DB>,
+    ) -> impl std::future::Future<
+        Output = Result<(Self::DatabaseDialect, Self::Executor), crate::Error>,
+    > + Send
+    where
+        Self: Sized,
+    {
+        async {
+            Err(crate::Error::Dialect(
+                Error::from(UsageError::Unsupported(
+                    "session setup is not supported by this adapter".into(),
+                ))
+                .into(),
+            ))
+        }
+    }
+
+    /// Pool on which the cancellation of a query with the given access can be
+    /// issued, see `DBAdapter::with_cancellation`
+    ///
+    /// Defaults to `None`.
+    fn cancel_pool(&self, _access: QueryAccess) -> Option<Pool<DB>> {
+        None
+    }
+
+    /// Dialect that [`Self::backend_db_for`] would return for the given
+    /// access, when it is known without acquiring a connection
+    ///
+    /// Lets `DBAdapter::count` consult its count cache before touching the
+    /// database. Defaults to `None`.
+    fn known_dialect(&self, _access: QueryAccess) -> Option<Self::DatabaseDialect> {
+        None
+    }
+
+    /// Like [`Self::backend_db_with_session`], returning a connection pinned for
+    /// several statements instead of a single-use executor
+    ///
+    /// Only pools support this. The default fails with [`crate::Error::Dialect`].
+    fn backend_db_pinned(
+        self,
+        _access: QueryAccess,
+        _session: &SessionSetup<DB>,
+    ) -> impl std::future::Future<
+        Output = Result<(Self::DatabaseDialect, PinnedConnection<DB>), crate::Error>,
+    > + Send
+    where
+        Self: Sized,
+    {
+        async {
+            Err(crate::Error::Dialect(
+                Error::from(UsageError::Unsupported(
+                    "pinned connections are not supported by this adapter".into(),
+                ))
+                .into(),
+            ))
+        }
+    }
+}
+/// Binds the name and the value of a transaction-local configuration
+/// parameter as the arguments of [`DatabaseDialect::local_setting_statement`]
+pub type LocalSettingBind<DB> =
+    Arc<dyn Fn(&mut <DB as Database>::Arguments) -> Result<(), BoxDynError> + Send + Sync>;
+
+/// Session preparation of the connection running a query, see
+/// [`BackendDB::backend_db_with_session`]
+pub struct SessionSetup<DB: Database> {
+    /// Statements run before the query
+    pub statements: Vec<String>,
+    /// Database-side timeout, applied through
+    /// [`DatabaseDialect::timeout_statements`]
+    pub timeout: Option<Duration>,
+    /// Transaction-local configuration parameters (name, bind), applied
+    /// through [`DatabaseDialect::local_setting_statement`] in a transaction
+    /// around the query
+    pub local_settings: Vec<(String, LocalSettingBind<DB>)>,
+}
+
+impl<DB: Database> Default for SessionSetup<DB> {
+    fn default() -> Self {
+        Self {
+            statements: Vec::new(),
+            timeout: None,
+            local_settings: Vec::new(),
+        }
+    }
+}
+
+impl<DB: Database> Clone for SessionSetup<DB> {
+    fn clone(&self) -> Self {
+        Self {
+            statements: self.statements.clone(),
+            timeout: self.timeout,
+            local_settings: self.local_settings.clone(),
+        }
+    }
+}
+
+/// Setting values are left out
+impl<DB: Database> fmt::Debug for SessionSetup<DB> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = self
+            .local_settings
+            .iter()
+            .map(|(n, _)| n.as_str())
+            .collect();
+        f.debug_struct("SessionSetup")
+            .field("statements", &self.statements)
+            .field("timeout", &self.timeout)
+            .field("local_settings", &names)
+            .finish()
+    }
+}
+
+impl<DB: Database> SessionSetup<DB> {
+    /// Whether nothing has to be prepared
+    pub fn is_empty(&self) -> bool {
+        self.statements.is_empty() && self.timeout.is_none() && self.local_settings.is_empty()
+    }
+}
+/// Whether a query issued through [`BackendDB`] reads or writes data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryAccess {
+    /// `fetch*` and `count` queries
+    Read,
+    /// `execute*` queries
+    Write,
+}
+impl<'c, DB, C, C1> BackendDB<'c, DB> for C
+where
+    DB: Database,
+    C: Executor<'c, Database = DB> + 'c + Deref<Target = C1>,
+    C1: Any,
+    for<'c1> &'c1 mut DB::Connection: Executor<'c1, Database = DB>,
+{
+    type DatabaseDialect = DBType;
+    type Executor = AdapterExecutor<'c, DB, C>;
+    async fn backend_db(self) -> Result<(Self::DatabaseDialect, Self::Executor), crate::Error> {
+        detect_backend_db(self).await
+    }
+    async fn backend_db_with_session(
+        self,
+        _access: QueryAccess,
+        session: &SessionSetup<DB>,
+    ) -> Result<(Self::DatabaseDialect, Self::Executor), crate::Error> {
+        let (db_type, mut executor) = detect_backend_db(self).await?;
+        let (mut statements, mut teardown) = session
+            .timeout
+            .and_then(|timeout| db_type.timeout_statements(timeout))
+            .unwrap_or_default();
+        // a timeout setting without teardown outlives the query
+        let permanent_timeout = !statements.is_empty() && teardown.is_empty();
+        // no teardown means the timeout did not already open a transaction
+        if !session.local_settings.is_empty() && teardown.is_empty() {
+            statements.insert(0, "BEGIN".to_string());
+            teardown.push("COMMIT".to_string());
+        }
+        let mut setup: Vec<_> = statements
+            .into_iter()
+            .map(|sql| SqlTemplateExecute::<DB>::new(sql, None))
+            .collect();
+        for (_, bind) in &session.local_settings {
+            let Some(sql) = db_type.local_setting_statement() else {
+                return Err(crate::Error::Dialect(
+                    Error::from(UsageError::Unsupported(format!(
+                        "transaction-local settings are not supported by {}",
+                        db_type.backend_name()
+                    )))
+                    .into(),
+                ));
+            };
+            let mut args = DB::Arguments::default();
+            bind(&mut args).map_err(|e| crate::Error::Encode(Error::Encode(e).into()))?;
+            setup.push(SqlTemplateExecute::new(sql, Some(args)));
+        }
+        setup.extend(
+            session
+                .statements
+                .iter()
+                .map(|sql| SqlTemplateExecute::new(sql.clone(), None)),
+        );
+        // arbitrary session statements (`SET ROLE`, `SET search_path`) cannot
+        // be undone reliably, the connection must not go back to the pool
+        let close = permanent_timeout || !session.statements.is_empty();
+        executor.setup_session(setup, teardown, close).await?;
+        Ok((db_type, executor))
+    }
+    fn cancel_pool(&self, _access: QueryAccess) -> Option<Pool<DB>> {
+        (self.deref() as &dyn Any)
+            .downcast_ref::<Pool<DB>>()
+            .cloned()
+    }
+    fn known_dialect(&self, _access: QueryAccess) -> Option<Self::DatabaseDialect> {
+        known_backend_db::<DB>(self.deref() as &dyn Any)
+    }
+    async fn backend_db_pinned(
+        self,
+        access: QueryAccess,
+        session: &SessionSetup<DB>,
+    ) -> Result<(Self::DatabaseDialect, PinnedConnection<DB>), crate::Error> {
+        let (db_type, executor) = self.backend_db_with_session(access, session).await?;
+        let pinned = executor
+            .into_pinned("a pinned connection needs a pool executor")
+            .await?;
+        Ok((db_type, pinned))
+    }
+}
+
+#[derive(Debug)]
+pub struct AdapterExecutor<'c, DB: Database, C: Executor<'c, Database = DB>> {
+    executor: Either<C, PoolConnection<DB>>,
+    teardown: Vec<String>,
+    close: bool,
+    _m: PhantomData<&'c ()>,
+}
+impl<'c, DB, C> AdapterExecutor<'c, DB, C>
+where
+    DB: Database,
+    C: Executor<'c, Database = DB>,
+{
+    fn new(executor: Either<C, PoolConnection<DB>>) -> Self {
+        Self {
+            executor,
+            teardown: Vec::new(),
+            close: false,
+            _m: PhantomData,
+        }
+    }
+}
+
+impl<'c, DB, C, C1> AdapterExecutor<'c, DB, C>
+where
+    DB: Database,
+    C: Executor<'c, Database = DB> + Deref<Target = C1>,
+    C1: Any,
+    for<'c1> &'c1 mut DB::Connection: Executor<'c1, Database = DB>,
+{
+    /// Pins a connection and runs the session `setup` statements on it;
+    /// `teardown` statements run after the query on the same connection
+    ///
+    /// With `close`, the connection is closed after the query instead of
+    /// returned to the pool, for session state the teardown cannot reset.
+    ///
+    /// A pool executor is replaced by a connection acquired from it, so the
+    /// query runs in the same session. Other executors (connections,
+    /// transactions) are rejected with [`crate::Error::Dialect`]: their session
+    /// is the caller's to set up.
+    #[doc(hidden)]
+    pub async fn setup_session(
+        &mut self,
+        setup: Vec<SqlTemplateExecute<DB>>,
+        teardown: Vec<String>,
+        close: bool,
+    ) -> Result<(), crate::Error> {
+        if setup.is_empty() && teardown.is_empty() {
+            return Ok(());
+        }
+        let conn = self
+            .pin("session setup needs a pool executor; run it on the connection instead")
+            .await?;
+        for statement in setup {
+            statement.execute(&mut **conn).await?;
+        }
+        self.teardown = teardown;
+        self.close = close;
+        Ok(())
+    }
+    /// Pins a connection for several statements, see [`Self::setup_session`]
+    #[doc(hidden)]
+    pub async fn into_pinned(
+        mut self,
+        unsupported: &str,
+    ) -> Result<PinnedConnection<DB>, crate::Error> {
+        self.pin(unsupported).await?;
+        match self.executor {
+            Either::Right(conn) => Ok(PinnedConnection {
+                conn,
+                teardown: self.teardown,
+                close: self.close,
+            }),
+            Either::Left(_) => unreachable!("pinned executor"),
+        }
+    }
+    /// Replaces a pool executor by a connection acquired from it; other
+    /// executors fail with an `unsupported` [`crate::Error::Dialect`]
+    async fn pin(&mut self, unsupported: &str) -> Result<&mut PoolConnection<DB>, crate::Error> {
+        if let Either::Left(c) = &self.executor {
+            let pool = (c.deref() as &dyn Any).downcast_ref::<Pool<DB>>().cloned();
+            let Some(pool) = pool else {
+                return Err(crate::Error::Dialect(
+                    Error::from(UsageError::Unsupported(unsupported.to_string())).into(),
+                ));
+            };
+            self.executor = Either::Right(pool.acquire().await?);
+        }
+        match &mut self.executor {
+            Either::Right(conn) => Ok(conn),
+            Either::Left(_) => unreachable!("pinned executor"),
+        }
+    }
+}
+
+/// Pool connection dedicated to the statements of one query, with session
+/// teardown statements to run after them, see [`BackendDB::backend_db_pinned`]
+///
+/// If the query is abandoned before the teardown ran, or the session setup
+/// cannot be undone, the connection is closed instead of returned to the pool,
+/// so no session state leaks.
+pub struct PinnedConnection<DB: Database> {
+    #[doc(hidden)]
+    pub conn: PoolConnection<DB>,
+    teardown: Vec<String>,
+    close: bool,
+}
+
+impl<DB: Database> fmt::Debug for PinnedConnection<DB> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PinnedConnection")
+            .field("teardown", &self.teardown)
+            .field("close", &self.close)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<DB: Database> PinnedConnection<DB>
+where
+    for<'c1> &'c1 mut DB::Connection: Executor<'c1, Database = DB>,
+{
+    #[doc(hidden)]
+    pub async fn finish(&mut self) -> Result<(), Error> {
+        for sql in std::mem::take(&mut self.teardown) {
+            let result = SqlTemplateExecute::<DB>::new(sql, None)
+                .execute(&mut *self.conn)
+                .await;
+            if let Err(e) = result {
+                self.conn.close_on_drop();
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<DB: Database> Drop for PinnedConnection<DB> {
+    fn drop(&mut self) {
+        if self.close || !self.teardown.is_empty() {
+            self.conn.close_on_drop();
+        }
+    }
+}
+
+impl<'c, DB, C> Executor<'c> for AdapterExecutor<'c, DB, C>
+where
+    DB: Database,
+    C: Executor<'c, Database = DB>,
+    for<'c1> &'c1 mut DB::Connection: Executor<'c1, Database = DB>,
+{
+    type Database = DB;
+
+    fn fetch_many<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> futures_core::stream::BoxStream<
+        'e,
+        Result<
+            Either<<Self::Database as Database>::QueryResult, <Self::Database as Database>::Row>,
+            Error,
+        >,
+    >
+    where
+        'c: 'e,
+        E: 'q + Execute<'q, Self::Database>,
+    {
+        match self.executor {
+            Either::Left(executor) => executor.fetch_many(query),
+            Either::Right(conn) => {
+                let mut pinned = PinnedConnection {
+                    conn,
+                    teardown: self.teardown,
+                    close: self.close,
+                };
+                Box::pin(async_stream::try_stream! {
+                    let mut s = pinned.conn.fetch_many(query);
+                    let mut failed = None;
+                    loop {
+                        match s.try_next().await {
+                            Ok(Some(v)) => yield v,
+                            Ok(None) => break,
+                            Err(e) => {
+                                failed = Some(e);
+                                break;
+                            }
+                        }
+                    }
+                    drop(s);
+                    pinned.finish().await?;
+                    if let Some(e) = failed {
+                        Err(e)?;
+                    }
+                })
+            }
+        }
+    }
+
+    fn fetch_optional<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> futures_core::future::BoxFuture<'e, Result<Option<<Self::Database as Database>::Row>, Error>>
+    where
+        'c: 'e,
+        E: 'q + Execute<'q, Self::Database>,
+    {
+        match self.executor {
+            Either::Left(executor) => executor.fetch_optional(query),
+            Either::Right(conn) => {
+                let mut pinned = PinnedConnection {
+                    conn,
+                    teardown: self.teardown,
+                    close: self.close,
+                };
+                Box::pin(async move {
+                    let result = pinned.conn.fetch_optional(query).await;
+                    pinned.finish().await?;
+                    result
+                })
+            }
+        }
+    }
+
+    fn prepare_with<'e>(
+        self,
+        sql: SqlStr,
+        parameters: &'e [<Self::Database as Database>::TypeInfo],
+    ) -> futures_core::future::BoxFuture<'e, Result<<Self::Database as Database>::Statement, Error>>
+    where
+        'c: 'e,
+    {
+        match self.executor {
+            Either::Left(executor) => executor.prepare_with(sql, parameters),
+            Either::Right(mut conn) => {
+                Box::pin(async move { conn.prepare_with(sql, parameters).await })
+            }
+        }
+    }
+
+    fn describe<'e>(
+        self,
+        sql: SqlStr,
+    ) -> futures_core::future::BoxFuture<'e, Result<Describe<Self::Database>, Error>>
+    where
+        'c: 'e,
+    {
+        match self.executor {
+            Either::Left(executor) => executor.describe(sql),
+            Either::Right(mut conn) => Box::pin(async move { conn.describe(sql).await }),
+        }
+    }
+}
+/// Backend of an executor, when it is known without acquiring a connection:
+/// the typed database, the backend of an `AnyConnection`, or the URL scheme
+/// of an `AnyPool`
+fn known_backend_db<DB: Database>(executor: &dyn Any) -> Option<DBType> {
+    if DB::NAME != sqlx_core::any::Any::NAME {
+        return DBType::new(DB::NAME).ok();
+    }
+    if let Some(conn) = executor.downcast_ref::<AnyConnection>() {
+        return DBType::new(conn.backend_name()).ok();
+    }
+    let pool = executor.downcast_ref::<AnyPool>()?;
+    match pool.connect_options().database_url.scheme() {
+        "postgres" | "postgresql" => Some(DBType::PostgreSQL),
+        "mysql" | "mariadb" => Some(DBType::MySQL),
+        "sqlite" => Some(DBType::SQLite),
+        _ => None,
+    }
+}
+
+/// Detect the real database type from the executor.
+/// params
+///  - c: The executor.
+///
+/// returns
+///  - (DBType, AdapterExecutor<'c, DB, C>): The database type and the adapter executor.
+///
+/// errors
+///  - [`crate::Error::Dialect`] for unsupported databases
+///  - [`crate::Error::Execute`] when no connection could be acquired
+pub async fn detect_backend_db<'c, DB, C, C1>(
+    c: C,
+) -> Result<(DBType, AdapterExecutor<'c, DB, C>), crate::Error>
+where
+    DB: Database,
+    C: Executor<'c, Database = DB> + 'c + Deref<Target = C1>,
+    C1: Any,
+{
+    let dialect_err = |e: Error| crate::Error::Dialect(e.into());
+    if DB::NAME != sqlx_core::any::Any::NAME {
+        return Ok((
+            DBType::new(DB::NAME).map_err(dialect_err)?,
+            AdapterExecutor::new(Either::Left(c)),
+        ));
+    }
+
+    let any_ref = c.deref() as &dyn Any;
+    //处理 AnyConnection
+    if let Some(conn) = any_ref.downcast_ref::<AnyConnection>() {
+        return Ok((
+            DBType::new(conn.backend_name()).map_err(dialect_err)?,
+            AdapterExecutor::new(Either::Left(c)),
+        ));
+    }
+
+    //处理 AnyPool：先克隆连接池，不把 `&dyn Any`（非 `Sync`）保留到 await 之后，
+    //以保证返回的 future 是 `Send`
+    if let Some(pool) = any_ref.downcast_ref::<AnyPool>().cloned() {
+        let conn = pool.acquire().await?;
+
+        let db_type = DBType::new(conn.backend_name()).map_err(dialect_err)?;
+        let db_con: Box<dyn Any> = Box::new(conn);
+        let return_con = db_con.downcast::<PoolConnection<DB>>().map_err(|_| {
+            dialect_err(Error::from(UsageError::Unsupported(format!(
+                "unsupported db `{}`",
+                DB::NAME
+            ))))
+        })?;
+
+        return Ok((db_type, AdapterExecutor::new(Either::Right(*return_con))));
+    }
+    Err(dialect_err(Error::from(UsageError::Unsupported(format!(
+        "unsupported db `{}`",
+        DB::NAME
+    )))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx_core::any::{Any, AnyArguments, AnyValueKind};
+
+    fn mysql_timeout(sql: &str) -> String {
+        let mut sql = sql.to_string();
+        DBType::MySQL.write_timeout_sql(&mut sql, Duration::from_millis(1500));
+        sql
+    }
+
+    #[test]
+    fn mysql_timeout_hint() {
+        let hint = "/*+ MAX_EXECUTION_TIME(1500) */";
+        assert_eq!(
+            mysql_timeout("  SELECT * from t"),
+            format!("  SELECT {hint} * from t")
+        );
+        assert_eq!(
+            mysql_timeout("/* tag */ -- note\nselect 1"),
+            format!("/* tag */ -- note\nselect {hint} 1")
+        );
+        assert_eq!(
+            mysql_timeout("with a as (select 1) select * from a"),
+            format!("with a as (select 1) select {hint} * from a")
+        );
+        for sql in ["selector()", "update t set a = 1", "(select 1)", ""] {
+            assert_eq!(mysql_timeout(sql), sql);
+        }
+        let mut sql = "select 1".to_string();
+        DBType::PostgreSQL.write_timeout_sql(&mut sql, Duration::from_secs(1));
+        assert_eq!(sql, "select 1");
+    }
+
+    #[test]
+    fn pg_counted_pagination() {
+        let mut sql = "select id from t order by id -- note\n".to_string();
+        let mut args = AnyArguments::default();
+        pg_counted_pagination_sql::<Any>(&mut sql, 10, 3, &mut args).unwrap();
+        assert_eq!(args.len(), 2);
+        assert_eq!(
+            sql,
+            format!(
+                "select p.*, c.{COUNTED_TOTAL_COLUMN} from (select count(1) as {COUNTED_TOTAL_COLUMN} \
+                 from (select id from t order by id) t) c left join (select q.*, row_number() over (order by id) \
+                 as {COUNTED_ROW_COLUMN} from (select id from t order by id limit $1 offset $2) q) p \
+                 on true order by p.{COUNTED_ROW_COLUMN} -- note\n"
+            )
+        );
+        let mut sql =
+            "select u.id, u.\"Full Name\" from u order by u.\"Full Name\" desc nulls last, 1"
+                .to_string();
+        pg_counted_pagination_sql::<Any>(&mut sql, 10, 1, &mut args).unwrap();
+        assert!(sql.contains("row_number() over (order by \"Full Name\" desc nulls last, 1)"));
+        let mut expression = "select id from t order by lower(name)".to_string();
+        assert!(pg_counted_pagination_sql::<Any>(&mut expression, 10, 1, &mut args).is_err());
+        let mut unordered = "select id from (select id from t order by id) s".to_string();
+        assert!(pg_counted_pagination_sql::<Any>(&mut unordered, 10, 1, &mut args).is_err());
+    }
+
+    fn page(db_type: DBType, sql: &str, size: i64, no: i64) -> (String, Vec<i64>) {
+        let mut sql = sql.to_string();
+        let mut args = AnyArguments::default();
+        db_type
+            .write_pagination_sql::<Any>(&mut sql, size, no, &mut args)
+            .unwrap();
+        let values = args
+            .values
+            .0
+            .iter()
+            .map(|value| match value {
+                AnyValueKind::BigInt(value) => *value,
+                other => panic!("unexpected argument {other:?}"),
+            })
+            .collect();
+        (sql, values)
+    }
+
+    #[test]
+    fn pagination() {
+        assert_eq!(
+            page(DBType::PostgreSQL, "select * from t -- all\n", 10, 3),
+            (
+                "select * from t limit $1 offset $2 -- all\n".to_string(),
+                vec![10, 20]
+            )
+        );
+        assert_eq!(
+            page(DBType::MySQL, "select * from t;", 10, 0),
+            ("select * from t limit ? offset ?;".to_string(), vec![10, 0])
+        );
+        assert_eq!(
+            page(DBType::SQLite, "select * from t", 10, i64::MAX).1,
+            vec![10, i64::MAX]
+        );
+        assert_eq!(
+            page(DBType::SQLite, "select * from t", 0, 2),
+            ("select * from t".to_string(), Vec::new())
+        );
+    }
+
+    #[test]
+    fn count_sql() {
+        let mut sql = "select * from t; -- all".to_string();
+        DBType::PostgreSQL.write_count_sql(&mut sql);
+        assert_eq!(sql, "select count(1) from (select * from t) t; -- all");
+        let mut sql = "select * from t".to_string();
+        DBType::MySQL.write_count_distinct_sql(&mut sql, "a");
+        assert_eq!(sql, "select count(distinct a) from (select * from t) t");
+    }
+
+    #[test]
+    fn sort_sql_by_dialect() {
+        let sort = [SortSpec::asc("a").nulls_last()];
+        let mut sql = "select * from t".to_string();
+        DBType::PostgreSQL.write_sort_sql(&mut sql, &sort);
+        assert_eq!(sql, "select * from t ORDER BY a ASC NULLS LAST");
+        let mut sql = "select * from t".to_string();
+        DBType::MySQL.write_sort_sql(&mut sql, &sort);
+        assert_eq!(
+            sql,
+            "select * from t ORDER BY CASE WHEN a IS NULL THEN 1 ELSE 0 END, a ASC"
+        );
+    }
+
+    #[cfg(feature = "mssql-compat")]
+    fn mssql_page(sql: &str) -> String {
+        let mut sql = sql.to_string();
+        let mut args = AnyArguments::default();
+        mssql_pagination_sql::<Any>(&mut sql, 10, 3, None, &mut args).unwrap();
+        assert_eq!(args.len(), 2);
+        sql
+    }
+
+    #[cfg(feature = "mssql-compat")]
+    #[test]
+    fn mssql_pagination_needs_top_level_order_by() {
+        let page = " offset @p1 rows fetch next @p2 rows only";
+        assert_eq!(
+            mssql_page("select * from t order by id"),
+            format!("select * from t order by id{page}")
+        );
+        assert_eq!(
+            mssql_page("select * from t ORDER  BY id;"),
+            format!("select * from t ORDER  BY id{page};")
+        );
+        for sql in [
+            "select * from (select top 5 * from t order by id) s",
+            "select row_number() over (order by id) from t",
+            "select 'order by' from t",
+            "select * from t -- order by id\n",
+            "select * from t /* order by id */",
+        ] {
+            let mut trailing = sql.to_string();
+            let tail = split_trailing(&mut trailing);
+            assert_eq!(
+                mssql_page(sql),
+                format!("{trailing} order by (select null){page}{tail}")
+            );
+        }
+    }
+
+    #[cfg(feature = "mssql-compat")]
+    #[test]
+    fn mssql_count_drops_top_level_order_by() {
+        let count = |sql: &str| {
+            let mut sql = sql.to_string();
+            DBType::MSSQL.write_count_sql(&mut sql);
+            sql
+        };
+        assert_eq!(
+            count("select * from t where a = @p1 order by id desc; -- all"),
+            "select count(1) from (select * from t where a = @p1) t; -- all"
+        );
+        assert_eq!(
+            count("select row_number() over (order by id) n from t"),
+            "select count(1) from (select row_number() over (order by id) n from t) t"
+        );
+        for sql in [
+            "select top (5) * from t order by id",
+            "select * from t order by id offset 5 rows",
+            "select * from t order by id for json path",
+        ] {
+            assert_eq!(count(sql), format!("select count(1) from ({sql}) t"));
+        }
+        let mut sql = "select a from t order by a".to_string();
+        DBType::MSSQL.write_count_distinct_sql(&mut sql, "a");
+        assert_eq!(sql, "select count(distinct a) from (select a from t) t");
+    }
+}
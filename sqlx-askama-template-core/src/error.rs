@@ -0,0 +1,387 @@
+use std::{
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        PoisonError, RwLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use sqlx_core::{Error as SqlxError, error::BoxDynError};
+
+use crate::{query_meta::QueryMeta, statement::StatementKind};
+
+static ERROR_SQL_LIMIT: AtomicUsize = AtomicUsize::new(1024);
+
+/// Sets how many characters of the rendered SQL are shown when an [`Error`]
+/// is displayed (default 1024); `0` leaves the SQL out of errors entirely
+pub fn set_error_sql_limit(limit: usize) {
+    ERROR_SQL_LIMIT.store(limit, Ordering::Relaxed);
+}
+
+/// Current limit set by [`set_error_sql_limit`]
+pub fn error_sql_limit() -> usize {
+    ERROR_SQL_LIMIT.load(Ordering::Relaxed)
+}
+
+/// Crate error, one variant per stage a query can fail in
+///
+/// Each variant wraps the underlying sqlx error together with the template
+/// type name, the database backend and the rendered SQL, as far as they were
+/// known when the query failed. Converts from and back into
+/// [`sqlx_core::Error`], so `?` works in either direction.
+#[derive(Debug)]
+pub enum Error {
+    /// The askama template failed to render
+    Render(ErrorInfo),
+    /// A template value could not be encoded as a query argument
+    Encode(ErrorInfo),
+    /// The backend is unsupported or the SQL could not be adapted to it
+    /// (count or pagination rewriting)
+    Dialect(ErrorInfo),
+    /// The database connection or the query itself failed
+    Execute(ErrorInfo),
+}
+
+/// Details shared by all [`Error`] variants
+#[derive(Debug)]
+pub struct ErrorInfo {
+    source: SqlxError,
+    template: Option<&'static str>,
+    backend: Option<String>,
+    sql: Option<String>,
+}
+
+impl From<SqlxError> for ErrorInfo {
+    fn from(source: SqlxError) -> Self {
+        Self {
+            source,
+            template: None,
+            backend: None,
+            sql: None,
+        }
+    }
+}
+
+impl Error {
+    /// Details of the error
+    pub fn info(&self) -> &ErrorInfo {
+        match self {
+            Self::Render(info) | Self::Encode(info) | Self::Dialect(info) | Self::Execute(info) => {
+                info
+            }
+        }
+    }
+    fn info_mut(&mut self) -> &mut ErrorInfo {
+        match self {
+            Self::Render(info) | Self::Encode(info) | Self::Dialect(info) | Self::Execute(info) => {
+                info
+            }
+        }
+    }
+    fn into_info(self) -> ErrorInfo {
+        match self {
+            Self::Render(info) | Self::Encode(info) | Self::Dialect(info) | Self::Execute(info) => {
+                info
+            }
+        }
+    }
+    /// The underlying sqlx error
+    pub fn inner(&self) -> &SqlxError {
+        &self.info().source
+    }
+    /// Consumes the error, returning the underlying sqlx error
+    pub fn into_inner(self) -> SqlxError {
+        self.into_info().source
+    }
+    /// Template type name
+    pub fn template(&self) -> Option<&'static str> {
+        self.info().template
+    }
+    /// Database backend name
+    pub fn backend(&self) -> Option<&str> {
+        self.info().backend.as_deref()
+    }
+    /// Rendered SQL (not truncated); `None` when rendering had not finished or
+    /// [`set_error_sql_limit`] is `0`
+    pub fn sql(&self) -> Option<&str> {
+        self.info().sql.as_deref()
+    }
+    /// Failing parameter of an encoding error raised by the template helpers
+    pub fn param(&self) -> Option<&ParamEncodeError> {
+        match self.inner() {
+            SqlxError::Encode(e) => e.downcast_ref(),
+            _ => None,
+        }
+    }
+    /// Misuse of the template or of the adapter configuration, detected
+    /// before the query was sent
+    pub fn usage(&self) -> Option<&UsageError> {
+        match self.inner() {
+            SqlxError::Configuration(e) => e.downcast_ref(),
+            _ => None,
+        }
+    }
+    fn stage(&self) -> &'static str {
+        match self {
+            Self::Render(_) => "render",
+            Self::Encode(_) => "encode",
+            Self::Dialect(_) => "dialect",
+            Self::Execute(_) => "execute",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let info = self.info();
+        write!(f, "{} error: {}", self.stage(), info.source)?;
+        if let Some(template) = info.template {
+            write!(f, " [template `{template}`")?;
+            if let Some(backend) = &info.backend {
+                write!(f, ", {backend}")?;
+            }
+            f.write_str("]")?;
+        }
+        let limit = error_sql_limit();
+        if let Some(sql) = &info.sql
+            && limit > 0
+        {
+            match sql.char_indices().nth(limit) {
+                Some((end, _)) => write!(f, " sql: {}...", &sql[..end])?,
+                None => write!(f, " sql: {sql}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.inner())
+    }
+}
+
+/// Encoding errors become [`Error::Encode`], everything else [`Error::Execute`]
+impl From<SqlxError> for Error {
+    fn from(source: SqlxError) -> Self {
+        match source {
+            SqlxError::Encode(_) => Self::Encode(source.into()),
+            _ => Self::Execute(source.into()),
+        }
+    }
+}
+
+impl From<Error> for SqlxError {
+    fn from(e: Error) -> Self {
+        e.into_inner()
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        SqlxError::Io(e).into()
+    }
+}
+
+/// Misuse of a template or of the adapter configuration, the source of the
+/// [`Error`]s raised before a query is sent, see [`Error::usage`]
+///
+/// Carried as a [`sqlx_core::Error::Configuration`], so it survives the
+/// conversion into a sqlx error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UsageError {
+    /// The template has no `{% block count %}` for its count query
+    MissingCountBlock,
+    /// The rendered statement is not of the kind required with
+    /// `DBAdapter::expect_statement`
+    StatementKind {
+        /// Required kind
+        expected: StatementKind,
+        /// Kind of the rendered statement, `None` for other statements
+        found: Option<StatementKind>,
+    },
+    /// A sort column outside the `order_by` list of the template
+    SortColumn(String),
+    /// The argument of `count_distinct` is not a plain column name
+    NotAColumn(String),
+    /// `fetch_after_as` without sort keys
+    MissingSortKeys,
+    /// The keyset cursor does not fit the query: it does not match the sort
+    /// keys, or a sort key of the last row is `NULL`
+    Keyset(String),
+    /// The query can not be adapted as requested, e.g. a feature the backend
+    /// lacks or a query shape the rewriting does not handle
+    Unsupported(String),
+    /// Strings interpolated without binding, see `set_interpolation_lint`
+    Interpolation(String),
+    /// A SQL fragment or included template whose binds do not fit the
+    /// enclosing template
+    Fragment(String),
+}
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingCountBlock => f.write_str("the template has no `{% block count %}`"),
+            Self::StatementKind { expected, found } => match found {
+                Some(found) => write!(f, "expected {expected} statement, rendered {found}"),
+                None => write!(
+                    f,
+                    "expected {expected} statement, rendered another statement"
+                ),
+            },
+            Self::SortColumn(column) => write!(
+                f,
+                "sort column `{column}` is not in the `order_by` list of the template"
+            ),
+            Self::NotAColumn(column) => write!(f, "`{column}` is not a column name"),
+            Self::MissingSortKeys => f.write_str("fetch_after_as needs sort keys, see `with_sort`"),
+            Self::Keyset(message)
+            | Self::Unsupported(message)
+            | Self::Interpolation(message)
+            | Self::Fragment(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for UsageError {}
+
+impl From<UsageError> for SqlxError {
+    fn from(e: UsageError) -> Self {
+        SqlxError::Configuration(Box::new(e))
+    }
+}
+
+/// Encoding failure of one template parameter, the source of
+/// [`Error::Encode`] errors raised by the template helpers
+#[derive(Debug)]
+pub struct ParamEncodeError {
+    /// 1-based position of the parameter in the query arguments
+    pub index: usize,
+    /// Label passed to [`crate::TemplateArg::e_named`], if any
+    pub label: Option<String>,
+    /// The encoder error
+    pub source: BoxDynError,
+}
+
+impl fmt::Display for ParamEncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "parameter {}", self.index)?;
+        if let Some(label) = &self.label {
+            write!(f, " (`{label}`)")?;
+        }
+        write!(f, ": {}", self.source)
+    }
+}
+
+impl std::error::Error for ParamEncodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&*self.source)
+    }
+}
+
+/// Runs a template render, turning a panic (e.g. in a placeholder function or
+/// a template helper) into an [`Error::Render`] naming the template
+///
+/// The buffer is restored to its length before the render. Used by the derive.
+#[doc(hidden)]
+pub fn render_guarded<T>(
+    template: &'static str,
+    sql_buffer: &mut String,
+    render: impl FnOnce(&mut String) -> Result<T, Error>,
+) -> Result<T, Error> {
+    let len = sql_buffer.len();
+    let template = template.trim_start_matches('&');
+    match panic::catch_unwind(AssertUnwindSafe(|| render(&mut *sql_buffer))) {
+        Ok(result) => result,
+        Err(payload) => {
+            sql_buffer.truncate(len);
+            let message = payload
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("unknown panic");
+            let mut info = ErrorInfo::from(SqlxError::Protocol(format!(
+                "panicked while rendering: {message}"
+            )));
+            info.template = Some(template);
+            Err(Error::Render(info))
+        }
+    }
+}
+
+type ErrorHook = fn(&Error, &QueryMeta);
+
+static ERROR_HOOK: RwLock<Option<ErrorHook>> = RwLock::new(None);
+
+/// Installs a global hook called with every error surfaced by `DBAdapter`
+/// queries and `SqlTemplate::render`, e.g. for centralized alerting
+///
+/// Errors that occur before the backend is known have an empty
+/// [`QueryMeta::backend`]. Replaces any previous hook.
+pub fn set_error_hook(hook: fn(&Error, &QueryMeta)) {
+    *ERROR_HOOK.write().unwrap_or_else(PoisonError::into_inner) = Some(hook);
+}
+
+/// Removes the hook installed by [`set_error_hook`]
+pub fn clear_error_hook() {
+    *ERROR_HOOK.write().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+/// Calls the error hook, if any
+#[doc(hidden)]
+pub fn report_error(e: &Error, meta: &QueryMeta) {
+    let hook = *ERROR_HOOK.read().unwrap_or_else(PoisonError::into_inner);
+    if let Some(hook) = hook {
+        hook(e, meta);
+    }
+}
+
+/// What is known about a query so far, attached to its errors
+#[derive(Debug, Clone)]
+#[doc(hidden)]
+pub struct ErrorContext {
+    meta: QueryMeta,
+    sql: Option<String>,
+}
+
+impl ErrorContext {
+    #[doc(hidden)]
+    pub fn new(meta: QueryMeta) -> Self {
+        Self { meta, sql: None }
+    }
+    #[doc(hidden)]
+    pub fn meta(&self) -> &QueryMeta {
+        &self.meta
+    }
+    #[doc(hidden)]
+    pub fn set_backend(&mut self, backend: &str) {
+        self.meta.backend = backend.to_string();
+    }
+    /// Keeps a copy of the SQL unless errors leave it out
+    #[doc(hidden)]
+    pub fn set_sql(&mut self, sql: &str) {
+        if error_sql_limit() > 0 {
+            self.sql = Some(sql.to_string());
+        }
+    }
+    /// Attaches the context to an error, keeping its stage, and reports it to
+    /// the error hook
+    #[doc(hidden)]
+    pub fn wrap(&self, e: impl Into<Error>) -> Error {
+        let mut e = e.into();
+        let info = e.info_mut();
+        info.template = Some(self.meta.template);
+        info.backend = Some(self.meta.backend.clone()).filter(|b| !b.is_empty());
+        info.sql.clone_from(&self.sql);
+        report_error(&e, &self.meta);
+        e
+    }
+    /// Attaches the context to a dialect error
+    #[doc(hidden)]
+    pub fn dialect(&self, e: SqlxError) -> Error {
+        self.wrap(Error::Dialect(e.into()))
+    }
+}
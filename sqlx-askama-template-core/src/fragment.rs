@@ -0,0 +1,207 @@
+/// Pre-built SQL fragment with `?` bind markers and their values
+///
+/// Included in a template with `{{ sql_include(fragment) }}`: each `?` outside
+/// quotes is replaced by a placeholder of the target database and the matching
+/// value is bound in order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SqlFragment<V> {
+    /// SQL text using `?` as bind marker
+    pub sql: String,
+    /// Bind values, one per marker
+    pub values: Vec<V>,
+}
+
+impl<V> SqlFragment<V> {
+    /// Creates a fragment from SQL with `?` markers and its values
+    pub fn new(sql: impl Into<String>, values: Vec<V>) -> Self {
+        Self {
+            sql: sql.into(),
+            values,
+        }
+    }
+
+    /// Splits the SQL at each `?` marker outside quoted strings and identifiers
+    #[doc(hidden)]
+    pub fn segments(&self) -> Vec<&str> {
+        let mut segments = Vec::new();
+        let mut quote = None;
+        let mut start = 0;
+        for (i, c) in self.sql.char_indices() {
+            match (quote, c) {
+                (None, '\'' | '"' | '`') => quote = Some(c),
+                (Some(q), c) if q == c => quote = None,
+                (None, '?') => {
+                    segments.push(&self.sql[start..i]);
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        segments.push(&self.sql[start..]);
+        segments
+    }
+}
+
+#[cfg(feature = "sea-query")]
+mod sea {
+    use sea_query::{
+        Condition, Expr, OptionEnum, Query, QueryBuilder, SelectStatement, SqlWriterValues, Value,
+    };
+    use sqlx_core::{
+        database::Database,
+        encode::{Encode, IsNull},
+        error::BoxDynError,
+        types::Type,
+    };
+
+    use super::SqlFragment;
+
+    /// A sea-query value converted to a thread-safe, sqlx-bindable form
+    ///
+    /// Unsigned and tiny integers are widened to the next signed type, chars and
+    /// enums are bound as strings. Other value kinds fail when encoded.
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum SeaQueryValue {
+        /// Boolean value
+        Bool(Option<bool>),
+        /// 16-bit integer value
+        SmallInt(Option<i16>),
+        /// 32-bit integer value
+        Int(Option<i32>),
+        /// 64-bit integer value
+        BigInt(Option<i64>),
+        /// 32-bit float value
+        Float(Option<f32>),
+        /// 64-bit float value
+        Double(Option<f64>),
+        /// Text value
+        String(Option<String>),
+        /// Binary value
+        Bytes(Option<Vec<u8>>),
+        /// Value kind without a sqlx mapping, kept as its debug text
+        Unsupported(String),
+    }
+
+    impl From<Value> for SeaQueryValue {
+        fn from(value: Value) -> Self {
+            #[allow(unreachable_patterns)]
+            match value {
+                Value::Bool(v) => Self::Bool(v),
+                Value::TinyInt(v) => Self::SmallInt(v.map(i16::from)),
+                Value::SmallInt(v) => Self::SmallInt(v),
+                Value::Int(v) => Self::Int(v),
+                Value::BigInt(v) => Self::BigInt(v),
+                Value::TinyUnsigned(v) => Self::SmallInt(v.map(i16::from)),
+                Value::SmallUnsigned(v) => Self::Int(v.map(i32::from)),
+                Value::Unsigned(v) => Self::BigInt(v.map(i64::from)),
+                Value::BigUnsigned(v) => match v.map(i64::try_from).transpose() {
+                    Ok(v) => Self::BigInt(v),
+                    Err(_) => Self::Unsupported(format!("{value:?}")),
+                },
+                Value::Float(v) => Self::Float(v),
+                Value::Double(v) => Self::Double(v),
+                Value::String(v) => Self::String(v),
+                Value::Char(v) => Self::String(v.map(String::from)),
+                Value::Enum(OptionEnum::Some(e)) => Self::String(Some(e.value.to_string())),
+                Value::Enum(OptionEnum::None(_)) => Self::String(None),
+                Value::Bytes(v) => Self::Bytes(v),
+                v => Self::Unsupported(format!("{v:?}")),
+            }
+        }
+    }
+
+    impl SqlFragment<SeaQueryValue> {
+        /// Renders a sea-query select statement into a fragment
+        ///
+        /// Identifiers are quoted by `builder`; bind markers are always `?`.
+        pub fn from_select<B: QueryBuilder>(select: &SelectStatement, builder: B) -> Self {
+            let mut writer = SqlWriterValues::new("?", false);
+            select.build_collect_into(builder, &mut writer);
+            let (sql, values) = writer.into_parts();
+            Self::new(sql, values.0.into_iter().map(SeaQueryValue::from).collect())
+        }
+        /// Renders a sea-query condition (without the `WHERE` keyword) into a fragment
+        pub fn from_condition<B: QueryBuilder>(condition: Condition, builder: B) -> Self {
+            let select = Query::select()
+                .expr(Expr::cust("1"))
+                .cond_where(condition)
+                .to_owned();
+            let mut fragment = Self::from_select(&select, builder);
+            if let Some(i) = fragment.sql.find(" WHERE ") {
+                fragment.sql.drain(..i + " WHERE ".len());
+            }
+            fragment
+        }
+    }
+
+    fn encode_opt<'q, DB, T>(
+        v: &Option<T>,
+        buf: &mut <DB as Database>::ArgumentBuffer,
+    ) -> Result<IsNull, BoxDynError>
+    where
+        DB: Database,
+        T: Encode<'q, DB>,
+    {
+        match v {
+            Some(v) => v.encode_by_ref(buf),
+            None => Ok(IsNull::Yes),
+        }
+    }
+
+    impl<'q, DB> Encode<'q, DB> for SeaQueryValue
+    where
+        DB: Database,
+        bool: Encode<'q, DB> + Type<DB>,
+        i16: Encode<'q, DB> + Type<DB>,
+        i32: Encode<'q, DB> + Type<DB>,
+        i64: Encode<'q, DB> + Type<DB>,
+        f32: Encode<'q, DB> + Type<DB>,
+        f64: Encode<'q, DB> + Type<DB>,
+        String: Encode<'q, DB> + Type<DB>,
+        Vec<u8>: Encode<'q, DB> + Type<DB>,
+    {
+        fn encode_by_ref(
+            &self,
+            buf: &mut <DB as Database>::ArgumentBuffer,
+        ) -> Result<IsNull, BoxDynError> {
+            match self {
+                Self::Bool(v) => encode_opt::<DB, _>(v, buf),
+                Self::SmallInt(v) => encode_opt::<DB, _>(v, buf),
+                Self::Int(v) => encode_opt::<DB, _>(v, buf),
+                Self::BigInt(v) => encode_opt::<DB, _>(v, buf),
+                Self::Float(v) => encode_opt::<DB, _>(v, buf),
+                Self::Double(v) => encode_opt::<DB, _>(v, buf),
+                Self::String(v) => encode_opt::<DB, _>(v, buf),
+                Self::Bytes(v) => encode_opt::<DB, _>(v, buf),
+                Self::Unsupported(v) => Err(format!("unsupported sea-query value `{v}`").into()),
+            }
+        }
+
+        fn produces(&self) -> Option<DB::TypeInfo> {
+            let ty = match self {
+                Self::Bool(_) => bool::type_info(),
+                Self::SmallInt(_) => i16::type_info(),
+                Self::Int(_) => i32::type_info(),
+                Self::BigInt(_) => i64::type_info(),
+                Self::Float(_) => f32::type_info(),
+                Self::Double(_) => f64::type_info(),
+                Self::Bytes(_) => <Vec<u8>>::type_info(),
+                Self::String(_) | Self::Unsupported(_) => String::type_info(),
+            };
+            Some(ty)
+        }
+    }
+
+    impl<DB> Type<DB> for SeaQueryValue
+    where
+        DB: Database,
+        String: Type<DB>,
+    {
+        fn type_info() -> DB::TypeInfo {
+            String::type_info()
+        }
+    }
+}
+
+#[cfg(feature = "sea-query")]
+pub use sea::SeaQueryValue;
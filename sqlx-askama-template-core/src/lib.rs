@@ -0,0 +1,48 @@
+//! Parameter binding and SQL dialect layer of `sqlx-askama-template`
+//!
+//! [`TemplateArg`] encodes values into sqlx arguments and placeholders,
+//! [`DatabaseDialect`] / [`DBType`] rewrite SQL for counting, pagination and
+//! sorting per backend, and [`SqlTemplateExecute`] runs the result. None of it
+//! depends on askama or the derive macro, so SQL generated by other engines or
+//! by hand can reuse it. `sqlx-askama-template` re-exports everything.
+
+mod array_bind;
+mod bound_param;
+mod builder;
+mod context;
+mod db_adapter;
+mod error;
+mod fragment;
+mod list_split;
+mod naming;
+mod page_request;
+mod placeholder;
+mod pretty;
+mod query_meta;
+mod savepoint;
+mod sort;
+mod sql_template_execute;
+mod statement;
+mod template_arg;
+
+pub use array_bind::register_array_type;
+#[doc(hidden)]
+pub use array_bind::{ArrayBinder, array_binder};
+pub use bound_param::*;
+pub use builder::*;
+pub use context::*;
+pub use db_adapter::*;
+pub use error::*;
+pub use fragment::*;
+#[doc(hidden)]
+pub use list_split::*;
+pub use naming::*;
+pub use page_request::*;
+pub use placeholder::*;
+pub use pretty::*;
+pub use query_meta::*;
+pub use savepoint::*;
+pub use sort::*;
+pub use sql_template_execute::*;
+pub use statement::*;
+pub use template_arg::*;
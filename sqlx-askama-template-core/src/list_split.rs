@@ -0,0 +1,87 @@
+use std::cell::{Cell, RefCell};
+
+/// Part of an oversized `el()` list rendered in one execution
+#[derive(Debug, Clone, Copy)]
+#[doc(hidden)]
+pub struct ListWindow {
+    /// Items per execution
+    #[doc(hidden)]
+    pub size: usize,
+    /// Execution index
+    #[doc(hidden)]
+    pub index: usize,
+}
+
+thread_local! {
+    static WINDOW: Cell<Option<ListWindow>> = const { Cell::new(None) };
+    static LENGTHS: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Restores the previous window and lengths when the render ends, even by panic
+struct Restore(Option<ListWindow>, Vec<usize>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        WINDOW.with(|w| w.set(self.0));
+        let previous = std::mem::take(&mut self.1);
+        LENGTHS.with(|l| *l.borrow_mut() = previous);
+    }
+}
+
+/// Runs the (synchronous) render `f` with `window` applied to oversized lists,
+/// returning its result and the lengths of all `el()` lists it rendered
+#[doc(hidden)]
+pub fn with_list_window<R>(window: Option<ListWindow>, f: impl FnOnce() -> R) -> (R, Vec<usize>) {
+    let previous = WINDOW.with(|w| w.replace(window));
+    let lengths = LENGTHS.with(|l| std::mem::take(&mut *l.borrow_mut()));
+    let restore = Restore(previous, lengths);
+    let result = f();
+    let lengths = LENGTHS.with(|l| std::mem::take(&mut *l.borrow_mut()));
+    drop(restore);
+    (result, lengths)
+}
+
+/// Records the length of an `el()` list and keeps the items of the current
+/// window if the list is longer than it
+#[doc(hidden)]
+pub fn window_list<T>(items: Vec<T>) -> Vec<T> {
+    LENGTHS.with(|l| l.borrow_mut().push(items.len()));
+    match WINDOW.with(Cell::get) {
+        Some(window) if items.len() > window.size => items
+            .into_iter()
+            .skip(window.index * window.size)
+            .take(window.size)
+            .collect(),
+        _ => items,
+    }
+}
+
+/// Items per execution and number of executions needed so that `bind_count`
+/// arguments, including lists of `lengths`, stay within `limit`
+///
+/// `None` if no split is needed. Only the longest list is split, so all other
+/// arguments must fit next to a single of its items.
+#[doc(hidden)]
+pub fn split_plan(
+    bind_count: usize,
+    lengths: &[usize],
+    limit: usize,
+) -> Result<Option<(usize, usize)>, String> {
+    if bind_count <= limit {
+        return Ok(None);
+    }
+    let longest = lengths.iter().copied().max().unwrap_or_default();
+    let others = bind_count - longest;
+    if longest == 0 || others >= limit {
+        return Err(format!(
+            "{bind_count} parameters exceed the limit of {limit} and no single list can be split to fit"
+        ));
+    }
+    let size = limit - others;
+    if lengths.iter().filter(|&&len| len > size).count() > 1 {
+        return Err(format!(
+            "{bind_count} parameters exceed the limit of {limit}; only one list can be split"
+        ));
+    }
+    Ok(Some((size, longest.div_ceil(size))))
+}
@@ -0,0 +1,206 @@
+use std::{
+    cell::Cell,
+    sync::{PoisonError, RwLock},
+};
+
+use sqlx_core::database::Database;
+
+use crate::{DBType, DatabaseDialect, db_adapter::quote_identifier_with};
+
+/// Mapping from the names used in templates to database identifiers, applied
+/// by the `ident()` and `columns()` template helpers
+///
+/// Words are split at `_`, `-`, spaces and case changes, so `userId`,
+/// `user_id` and `UserID` all become `user_id` in [`NamingConvention::SnakeCase`].
+/// Installed globally with [`set_naming_convention`].
+#[derive(Debug, Clone, Copy, Default)]
+pub enum NamingConvention {
+    /// Names are used unchanged
+    #[default]
+    AsIs,
+    /// `user_id`
+    SnakeCase,
+    /// `USER_ID`
+    ScreamingSnakeCase,
+    /// `userId`
+    CamelCase,
+    /// `UserId`
+    PascalCase,
+    /// Any other mapping
+    Custom(fn(&str) -> String),
+}
+
+impl NamingConvention {
+    /// Maps one identifier, without its table or schema qualifier
+    pub fn apply(&self, name: &str) -> String {
+        let words = || split_words(name).into_iter();
+        match self {
+            Self::AsIs => name.to_string(),
+            Self::SnakeCase => words()
+                .map(|w| w.to_lowercase())
+                .collect::<Vec<_>>()
+                .join("_"),
+            Self::ScreamingSnakeCase => words()
+                .map(|w| w.to_uppercase())
+                .collect::<Vec<_>>()
+                .join("_"),
+            Self::CamelCase => words()
+                .enumerate()
+                .map(|(i, w)| match i {
+                    0 => w.to_lowercase(),
+                    _ => capitalize(w),
+                })
+                .collect(),
+            Self::PascalCase => words().map(capitalize).collect(),
+            Self::Custom(f) => f(name),
+        }
+    }
+}
+
+/// Words of `name`; an uppercase run ends before its last letter when a
+/// lowercase one follows, so `HTTPServer` is `HTTP` and `Server`
+fn split_words(name: &str) -> Vec<&str> {
+    let chars: Vec<(usize, char)> = name.char_indices().collect();
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, &(pos, c)) in chars.iter().enumerate() {
+        if matches!(c, '_' | '-' | ' ') {
+            if let Some(s) = start.take() {
+                words.push(&name[s..pos]);
+            }
+            continue;
+        }
+        if let Some(s) = start
+            && c.is_uppercase()
+        {
+            let prev = chars[i - 1].1;
+            let next_lower = chars.get(i + 1).is_some_and(|&(_, n)| n.is_lowercase());
+            if !prev.is_uppercase() || next_lower {
+                words.push(&name[s..pos]);
+                start = None;
+            }
+        }
+        start.get_or_insert(pos);
+    }
+    if let Some(s) = start {
+        words.push(&name[s..]);
+    }
+    words
+}
+
+/// `word` with its first letter uppercase and the others lowercase
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars.next().map_or_else(String::new, |first| {
+        first
+            .to_uppercase()
+            .chain(chars.flat_map(char::to_lowercase))
+            .collect()
+    })
+}
+
+static NAMING_CONVENTION: RwLock<NamingConvention> = RwLock::new(NamingConvention::AsIs);
+
+/// Installs the global [`NamingConvention`], replacing the previous one
+///
+/// # Example
+/// ```standalone_crate
+/// use sqlx::Postgres;
+/// use sqlx_askama_template::{NamingConvention, SqlTemplate, set_naming_convention};
+///
+/// #[derive(SqlTemplate)]
+/// #[template(source = r#"select {{ columns(["userId", "createdAt"]) }} from users"#)]
+/// struct UserQuery;
+///
+/// // struct fields in camelCase, columns in snake_case
+/// set_naming_convention(NamingConvention::SnakeCase);
+/// let (sql, _) = SqlTemplate::<Postgres>::render(&UserQuery)?;
+/// assert_eq!(sql, r#"select "user_id", "created_at" from users"#);
+/// # Ok::<(), sqlx_askama_template::Error>(())
+/// ```
+pub fn set_naming_convention(convention: NamingConvention) {
+    *NAMING_CONVENTION
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = convention;
+}
+
+/// The global [`NamingConvention`], [`NamingConvention::AsIs`] unless set
+pub fn naming_convention() -> NamingConvention {
+    *NAMING_CONVENTION
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Dialect of the render running on this thread, see [`with_identifier_quoting`]
+#[derive(Clone, Copy)]
+struct RenderDialect {
+    quotes: (char, char),
+    postgres: bool,
+}
+
+thread_local! {
+    static DIALECT: Cell<Option<RenderDialect>> = const { Cell::new(None) };
+}
+
+/// Restores the previous dialect when the render ends, even by panic
+struct Restore(Option<RenderDialect>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        DIALECT.with(|d| d.set(self.0));
+    }
+}
+
+/// Opening and closing characters of [`DatabaseDialect::quote_identifier`]
+fn quotes(dialect: &impl DatabaseDialect) -> (char, char) {
+    let mut quoted = String::new();
+    dialect.quote_identifier("", &mut quoted);
+    let mut chars = quoted.chars();
+    match (chars.next(), chars.next_back()) {
+        (Some(open), Some(close)) => (open, close),
+        _ => ('"', '"'),
+    }
+}
+
+/// Quotes the identifiers of the (synchronous) render `f` like `dialect`, and
+/// lets `e_in()` pick the list form of `dialect`
+///
+/// Outside of it the quoting follows the database of the template, with ANSI
+/// double quotes for `Any`.
+#[doc(hidden)]
+pub fn with_identifier_quoting<R>(dialect: &impl DatabaseDialect, f: impl FnOnce() -> R) -> R {
+    let render = RenderDialect {
+        quotes: quotes(dialect),
+        postgres: DBType::new(dialect.backend_name()).is_ok_and(|db| db == DBType::PostgreSQL),
+    };
+    let previous = DIALECT.with(|d| d.replace(Some(render)));
+    let _restore = Restore(previous);
+    f()
+}
+
+/// Whether the render runs for PostgreSQL: the dialect of
+/// [`with_identifier_quoting`], else the database of the template
+pub(crate) fn renders_postgres<DB: Database>() -> bool {
+    DIALECT
+        .with(Cell::get)
+        .map_or(DB::NAME == "PostgreSQL", |d| d.postgres)
+}
+
+/// Appends `name`, mapped by the [`naming_convention`] and quoted, each part
+/// of a qualified `table.column` separately
+pub(crate) fn write_identifier<DB: Database>(name: &str, out: &mut String) {
+    let (open, close) = DIALECT
+        .with(Cell::get)
+        .map(|d| d.quotes)
+        .unwrap_or_else(|| {
+            // ANSI double quotes, as for PostgreSQL, when the backend is unknown
+            quotes(&DBType::new(DB::NAME).unwrap_or(DBType::PostgreSQL))
+        });
+    let convention = naming_convention();
+    for (i, part) in name.split('.').enumerate() {
+        if i > 0 {
+            out.push('.');
+        }
+        quote_identifier_with(&convention.apply(part), open, close, out);
+    }
+}
@@ -0,0 +1,71 @@
+use std::fmt;
+
+/// Unsigned pagination request, validated by `DBAdapter::set_page`
+///
+/// Unlike `DBAdapter::set_pagination`, which silently corrects
+/// out-of-range values, invalid requests are reported as a
+/// [`PageRequestError`], e.g. to answer a client with a bad request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PageRequest {
+    /// Records per pagination, at least 1
+    pub pagination_size: u64,
+    /// 1-based pagination number
+    pub pagination_no: u64,
+}
+
+impl PageRequest {
+    /// Creates a request from any unsigned integers (`u8` to `u64`)
+    pub fn new(pagination_size: impl Into<u64>, pagination_no: impl Into<u64>) -> Self {
+        Self {
+            pagination_size: pagination_size.into(),
+            pagination_no: pagination_no.into(),
+        }
+    }
+    /// Checks the request, returning `(pagination_size, pagination_no)` as
+    /// accepted by `DBAdapter::set_pagination`
+    pub fn validate(&self) -> Result<(i64, i64), PageRequestError> {
+        if self.pagination_size == 0 {
+            return Err(PageRequestError::ZeroSize);
+        }
+        if self.pagination_no == 0 {
+            return Err(PageRequestError::ZeroNumber);
+        }
+        let size = i64::try_from(self.pagination_size)
+            .map_err(|_| PageRequestError::SizeOutOfRange(self.pagination_size))?;
+        let no = i64::try_from(self.pagination_no)
+            .map_err(|_| PageRequestError::NumberOutOfRange(self.pagination_no))?;
+        if (no - 1).checked_mul(size).is_none() {
+            return Err(PageRequestError::OffsetOverflow);
+        }
+        Ok((size, no))
+    }
+}
+
+/// Invalid [`PageRequest`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageRequestError {
+    /// The pagination size is 0
+    ZeroSize,
+    /// The pagination number is 0; numbers start at 1
+    ZeroNumber,
+    /// The pagination size does not fit in an `i64`
+    SizeOutOfRange(u64),
+    /// The pagination number does not fit in an `i64`
+    NumberOutOfRange(u64),
+    /// The row offset of the pagination does not fit in an `i64`
+    OffsetOverflow,
+}
+
+impl fmt::Display for PageRequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ZeroSize => f.write_str("pagination size must be at least 1"),
+            Self::ZeroNumber => f.write_str("pagination number must be at least 1"),
+            Self::SizeOutOfRange(size) => write!(f, "pagination size {size} is too large"),
+            Self::NumberOutOfRange(no) => write!(f, "pagination number {no} is too large"),
+            Self::OffsetOverflow => f.write_str("pagination offset is too large"),
+        }
+    }
+}
+
+impl std::error::Error for PageRequestError {}
@@ -0,0 +1,183 @@
+use std::fmt::Write;
+
+use sqlx_core::Error as SqlxError;
+
+use crate::{
+    DBType,
+    error::{Error, UsageError},
+};
+
+/// Bind parameter placeholder syntax
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PlaceholderStyle {
+    /// `$1`, `$2`... (PostgreSQL)
+    Dollar,
+    /// `?` (MySQL, SQLite)
+    Question,
+    /// `@p1`, `@p2`... (SQL Server)
+    AtP,
+}
+
+impl PlaceholderStyle {
+    /// Whether placeholders carry the 1-based parameter number
+    pub fn is_numbered(self) -> bool {
+        !matches!(self, Self::Question)
+    }
+    /// Placeholder function writing this style, for
+    /// `SqlTemplate::render_with_placeholder`
+    pub fn placeholder_fn(self) -> fn(usize, &mut String) {
+        match self {
+            Self::Dollar => |n, out| Self::Dollar.write(n, out),
+            Self::Question => |n, out| Self::Question.write(n, out),
+            Self::AtP => |n, out| Self::AtP.write(n, out),
+        }
+    }
+    fn write(self, n: usize, out: &mut String) {
+        let _ = match self {
+            Self::Dollar => write!(out, "${n}"),
+            Self::Question => write!(out, "?"),
+            Self::AtP => write!(out, "@p{n}"),
+        };
+    }
+}
+
+impl DBType {
+    /// Placeholder syntax of the backend, see [`crate::DatabaseDialect::placeholder_fn`]
+    pub fn placeholder_style(&self) -> PlaceholderStyle {
+        match self {
+            Self::PostgreSQL => PlaceholderStyle::Dollar,
+            Self::MySQL | Self::SQLite => PlaceholderStyle::Question,
+            #[cfg(feature = "mssql-compat")]
+            Self::MSSQL => PlaceholderStyle::AtP,
+        }
+    }
+}
+
+/// Parses the placeholder of style `style` starting at `bytes[i]`, returning
+/// its number (0 for `?`) and length
+fn placeholder_at(bytes: &[u8], i: usize, style: PlaceholderStyle) -> Option<(usize, usize)> {
+    let after_word = i > 0 && (bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'_');
+    let prefix = match style {
+        PlaceholderStyle::Question => return (bytes[i] == b'?').then_some((0, 1)),
+        PlaceholderStyle::Dollar => &b"$"[..],
+        PlaceholderStyle::AtP => &b"@p"[..],
+    };
+    if after_word || !bytes[i..].starts_with(prefix) {
+        return None;
+    }
+    let digits = bytes[i + prefix.len()..]
+        .iter()
+        .take_while(|b| b.is_ascii_digit())
+        .count();
+    let end = i + prefix.len() + digits;
+    let followed_by_word = bytes
+        .get(end)
+        .is_some_and(|b| b.is_ascii_alphanumeric() || *b == b'_');
+    if digits == 0 || followed_by_word {
+        return None;
+    }
+    let n = std::str::from_utf8(&bytes[i + prefix.len()..end])
+        .ok()?
+        .parse()
+        .ok()?;
+    Some((n, end - i))
+}
+
+/// Rewrites the `from` placeholders of `sql` in the `to` style, e.g. to run
+/// SQL rendered for PostgreSQL on SQLite, or to hand it to an external tool
+///
+/// String literals, quoted identifiers, comments and PostgreSQL dollar-quoted
+/// strings are left untouched. `?` placeholders are numbered in order;
+/// numbered placeholders keep their number, and can only become `?` when they
+/// appear exactly once each, in order (`$1`, `$2`...), failing with
+/// [`Error::Dialect`] otherwise.
+///
+/// # Example
+/// ```
+/// use sqlx_askama_template_core::{PlaceholderStyle, rewrite_placeholders};
+///
+/// let sql = "select '?' as q, id from users where id = ? and name = ?";
+/// assert_eq!(
+///     rewrite_placeholders(sql, PlaceholderStyle::Question, PlaceholderStyle::Dollar).unwrap(),
+///     "select '?' as q, id from users where id = $1 and name = $2"
+/// );
+/// ```
+pub fn rewrite_placeholders(
+    sql: &str,
+    from: PlaceholderStyle,
+    to: PlaceholderStyle,
+) -> Result<String, Error> {
+    if from == to {
+        return Ok(sql.to_string());
+    }
+    let bytes = sql.as_bytes();
+    let mut out = String::with_capacity(sql.len());
+    let mut copied = 0;
+    let mut count = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        let skip_to = match c {
+            b'\'' | b'"' | b'`' => {
+                let mut j = i + 1;
+                while j < bytes.len() && bytes[j] != c {
+                    j += 1;
+                }
+                Some(j + 1)
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                let mut j = i;
+                while j < bytes.len() && bytes[j] != b'\n' {
+                    j += 1;
+                }
+                Some(j)
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => Some(
+                sql[i + 2..]
+                    .find("*/")
+                    .map_or(bytes.len(), |end| i + 2 + end + 2),
+            ),
+            // dollar-quoted string: $$...$$ or $tag$...$tag$
+            b'$' if !bytes.get(i + 1).is_some_and(u8::is_ascii_digit) => {
+                let tag_len = bytes[i + 1..]
+                    .iter()
+                    .position(|b| !(b.is_ascii_alphanumeric() || *b == b'_'))
+                    .filter(|&len| bytes[i + 1 + len] == b'$');
+                tag_len.map(|len| {
+                    let tag = &sql[i..i + len + 2];
+                    let body = i + tag.len();
+                    sql[body..]
+                        .find(tag)
+                        .map_or(bytes.len(), |end| body + end + tag.len())
+                })
+            }
+            _ => None,
+        };
+        if let Some(end) = skip_to {
+            i = end.min(bytes.len());
+            continue;
+        }
+        let Some((n, len)) = placeholder_at(bytes, i, from) else {
+            i += 1;
+            continue;
+        };
+        count += 1;
+        let n = if from.is_numbered() { n } else { count };
+        if !to.is_numbered() && n != count {
+            return Err(Error::Dialect(
+                SqlxError::from(UsageError::Unsupported(format!(
+                    "placeholder {} can not be rewritten as `?`: numbered placeholders must \
+                     appear once each, in order",
+                    &sql[i..i + len]
+                )))
+                .into(),
+            ));
+        }
+        out.push_str(&sql[copied..i]);
+        to.write(n, &mut out);
+        i += len;
+        copied = i;
+    }
+    out.push_str(&sql[copied..]);
+    Ok(out)
+}
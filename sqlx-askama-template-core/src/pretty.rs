@@ -0,0 +1,218 @@
+/// Clause keywords starting a new line
+const CLAUSES: &[&str] = &[
+    "select",
+    "from",
+    "where",
+    "group",
+    "having",
+    "order",
+    "limit",
+    "offset",
+    "fetch",
+    "union",
+    "intersect",
+    "except",
+    "join",
+    "left",
+    "right",
+    "inner",
+    "full",
+    "cross",
+    "natural",
+    "values",
+    "set",
+    "returning",
+    "window",
+];
+/// Keywords that may open a clause of two words, like `left join`
+const CLAUSE_PREFIXES: &[&str] = &[
+    "left", "right", "inner", "full", "cross", "natural", "union", "group", "order",
+];
+/// Boolean operators starting an indented line
+const CONDITIONS: &[&str] = &["and", "or"];
+
+enum Token<'a> {
+    Word(&'a str),
+    /// String literal, quoted identifier or any other character run kept as is
+    Text(&'a str),
+    LineComment(&'a str),
+    BlockComment(&'a str),
+    Open,
+    Close,
+    Comma,
+    Space,
+}
+
+fn tokens(sql: &str) -> Vec<Token<'_>> {
+    let bytes = sql.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let c = bytes[i];
+        let token = match c {
+            b'\'' | b'"' | b'`' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != c {
+                    i += 1;
+                }
+                i = (i + 1).min(bytes.len());
+                Token::Text(&sql[start..i])
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                Token::LineComment(sql[start..i].trim_end())
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i += 2;
+                while i < bytes.len() && !(bytes[i] == b'*' && bytes.get(i + 1) == Some(&b'/')) {
+                    i += 1;
+                }
+                i = (i + 2).min(bytes.len());
+                Token::BlockComment(&sql[start..i])
+            }
+            b'(' => {
+                i += 1;
+                Token::Open
+            }
+            b')' => {
+                i += 1;
+                Token::Close
+            }
+            b',' => {
+                i += 1;
+                Token::Comma
+            }
+            _ if c.is_ascii_whitespace() => {
+                while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+                    i += 1;
+                }
+                Token::Space
+            }
+            _ if c.is_ascii_alphanumeric() || c == b'_' => {
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                    i += 1;
+                }
+                Token::Word(&sql[start..i])
+            }
+            _ => {
+                // one UTF-8 character
+                i += sql[i..].chars().next().map_or(1, char::len_utf8);
+                Token::Text(&sql[start..i])
+            }
+        };
+        tokens.push(token);
+    }
+    tokens
+}
+
+/// Formats SQL for display: one clause per line, `AND`/`OR` conditions and
+/// subqueries indented, whitespace runs (such as the blank lines left by
+/// template control blocks) collapsed
+///
+/// Only layout changes: words keep their case, and string literals, quoted
+/// identifiers and comments are kept verbatim. The result is meant for
+/// humans (admin UIs, review bots), not for execution.
+///
+/// # Example
+/// ```
+/// use sqlx_askama_template_core::format_sql;
+///
+/// let sql = "select id,\n\n  name from users where a = $1 and b = $2 order by id";
+/// assert_eq!(
+///     format_sql(sql),
+///     "select id, name\nfrom users\nwhere a = $1\n  and b = $2\norder by id"
+/// );
+/// ```
+pub fn format_sql(sql: &str) -> String {
+    let tokens = tokens(sql);
+    let next_word = |from: usize| {
+        tokens[from..].iter().find_map(|token| match token {
+            Token::Space => None,
+            Token::Word(word) => Some(Some(*word)),
+            _ => Some(None),
+        })?
+    };
+    let mut out = String::with_capacity(sql.len());
+    // for each open parenthesis, whether it holds a subquery
+    let mut parens: Vec<bool> = Vec::new();
+    let mut pending_space = false;
+    let mut previous_word: Option<&str> = None;
+    let mut between = false;
+    let newline = |out: &mut String, parens: &[bool], extra: usize| {
+        let trimmed = out.trim_end_matches(' ').len();
+        out.truncate(trimmed);
+        if !out.is_empty() && !out.ends_with('\n') {
+            out.push('\n');
+        }
+        let depth = parens.iter().filter(|subquery| **subquery).count();
+        out.push_str(&"  ".repeat(depth + extra));
+    };
+    for (i, token) in tokens.iter().enumerate() {
+        let breaking = parens.last().is_none_or(|subquery| *subquery);
+        match token {
+            Token::Space => {
+                pending_space = true;
+                continue;
+            }
+            Token::Word(word) => {
+                let lower = word.to_ascii_lowercase();
+                let joins_previous = previous_word.is_some_and(|previous| {
+                    CLAUSE_PREFIXES
+                        .iter()
+                        .any(|p| previous.eq_ignore_ascii_case(p))
+                });
+                let function = matches!(tokens.get(i + 1), Some(Token::Open));
+                if breaking && !joins_previous && !function && CLAUSES.contains(&lower.as_str()) {
+                    newline(&mut out, &parens, 0);
+                } else if breaking && CONDITIONS.contains(&lower.as_str()) && !between {
+                    newline(&mut out, &parens, 1);
+                } else if pending_space && !out.is_empty() && !out.ends_with([' ', '(', '\n']) {
+                    out.push(' ');
+                }
+                // the `and` of `between x and y` is no condition
+                between = lower == "between" || (between && lower != "and");
+                out.push_str(word);
+                previous_word = Some(word);
+                pending_space = false;
+                continue;
+            }
+            Token::Open => {
+                if pending_space && !out.ends_with([' ', '(', '\n']) && !out.is_empty() {
+                    out.push(' ');
+                }
+                out.push('(');
+                parens.push(next_word(i + 1).is_some_and(|word| {
+                    word.eq_ignore_ascii_case("select") || word.eq_ignore_ascii_case("with")
+                }));
+            }
+            Token::Close => {
+                if parens.pop() == Some(true) {
+                    newline(&mut out, &parens, 0);
+                }
+                out.push(')');
+            }
+            Token::Comma => out.push(','),
+            Token::LineComment(comment) => {
+                if pending_space && !out.is_empty() && !out.ends_with([' ', '\n']) {
+                    out.push(' ');
+                }
+                out.push_str(comment);
+                newline(&mut out, &parens, 0);
+            }
+            Token::Text(text) | Token::BlockComment(text) => {
+                if pending_space && !out.is_empty() && !out.ends_with([' ', '(', '\n']) {
+                    out.push(' ');
+                }
+                out.push_str(text);
+            }
+        }
+        previous_word = None;
+        pending_space = false;
+    }
+    let trimmed = out.trim_end().len();
+    out.truncate(trimmed);
+    out
+}
@@ -0,0 +1,27 @@
+/// Metadata describing a query executed through `DBAdapter`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryMeta {
+    /// Template name, `SqlTemplate::QUERY_TAG` or else the type name
+    pub template: &'static str,
+    /// `SqlTemplate::TEMPLATE_FINGERPRINT` of the template, 0 if unknown
+    pub template_fingerprint: u64,
+    /// Database backend name (e.g. PostgreSQL, MySQL, SQLite)
+    pub backend: String,
+    /// Operation (`"count"`, `"fetch"`, `"execute"` or `"render"`)
+    pub operation: &'static str,
+    /// Pagination size and number, if set
+    pub pagination: Option<(i64, i64)>,
+}
+
+impl QueryMeta {
+    /// Creates query metadata for the template type `T`
+    pub fn new<T: ?Sized>(backend: &str, operation: &'static str) -> Self {
+        Self {
+            template: std::any::type_name::<T>().trim_start_matches('&'),
+            template_fingerprint: 0,
+            backend: backend.to_string(),
+            operation,
+            pagination: None,
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use futures_core::future::BoxFuture;
+use sqlx_core::{connection::Connection, database::Database, executor::Executor};
+
+use crate::{
+    db_adapter::{DatabaseDialect, detect_backend_db},
+    error::Error,
+    sql_template_execute::SqlTemplateExecute,
+};
+
+/// Statements managing a savepoint, see [`DatabaseDialect::savepoint`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Savepoint {
+    /// Creates the savepoint
+    pub create: String,
+    /// Rolls back to the savepoint
+    pub rollback: String,
+    /// Releases the savepoint, if the dialect has such a statement
+    pub release: Option<String>,
+}
+
+/// Runs `f` inside a savepoint of the transaction on `conn` (e.g. `&mut *tx`)
+///
+/// The savepoint is released when `f` succeeds and rolled back to when it
+/// fails, so a failed optional step does not abort the whole transaction. The
+/// error of `f` is returned as is.
+///
+/// # Example
+/// ```no_run
+/// use sqlx::SqlitePool;
+/// use sqlx_askama_template::{SqlTemplate, with_savepoint};
+///
+/// #[derive(SqlTemplate)]
+/// #[template(source = "insert into orders (id) values ({{ e(id) }})")]
+/// struct CreateOrder {
+///     id: i64,
+/// }
+///
+/// #[derive(SqlTemplate)]
+/// #[template(source = "update loyalty set points = points + {{ e(points) }}")]
+/// struct AddPoints {
+///     points: i64,
+/// }
+///
+/// # async fn run(pool: SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+/// let mut tx = pool.begin().await?;
+/// CreateOrder { id: 1 }.adapter().execute(&mut *tx).await?;
+/// let loyalty = with_savepoint(&mut *tx, "loyalty", |conn| {
+///     Box::pin(async move { AddPoints { points: 10 }.adapter().execute(conn).await })
+/// })
+/// .await;
+/// tx.commit().await?; // the order is kept even if `loyalty` failed
+/// # Ok(())
+/// # }
+/// ```
+pub async fn with_savepoint<DB, C, F, R>(conn: &mut C, name: &str, f: F) -> Result<R, Error>
+where
+    DB: Database,
+    C: Connection<Database = DB> + 'static,
+    for<'c> &'c mut C: Executor<'c, Database = DB>,
+    F: for<'a> FnOnce(&'a mut C) -> BoxFuture<'a, Result<R, Error>>,
+{
+    let savepoint = begin_savepoint(conn, name).await?;
+    let result = f(conn).await;
+    let end = end_savepoint(conn, &savepoint, result.is_ok()).await;
+    let value = result?;
+    end?;
+    Ok(value)
+}
+
+/// Creates the savepoint `name` on `conn`
+#[doc(hidden)]
+pub async fn begin_savepoint<DB, C>(conn: &mut C, name: &str) -> Result<Savepoint, Error>
+where
+    DB: Database,
+    C: Connection<Database = DB> + 'static,
+    for<'c> &'c mut C: Executor<'c, Database = DB>,
+{
+    let db_type = detect_backend_db(&mut *conn).await?.0;
+    let savepoint = db_type.savepoint(name);
+    SqlTemplateExecute::<DB>::new(savepoint.create.clone(), None)
+        .execute(&mut *conn)
+        .await?;
+    Ok(savepoint)
+}
+
+/// Releases `savepoint` if `keep`, rolls back to it otherwise
+#[doc(hidden)]
+pub async fn end_savepoint<DB, C>(
+    conn: &mut C,
+    savepoint: &Savepoint,
+    keep: bool,
+) -> Result<(), Error>
+where
+    DB: Database,
+    C: Connection<Database = DB> + 'static,
+    for<'c> &'c mut C: Executor<'c, Database = DB>,
+{
+    if !keep {
+        SqlTemplateExecute::<DB>::new(savepoint.rollback.clone(), None)
+            .execute(&mut *conn)
+            .await?;
+    }
+    if let Some(release) = &savepoint.release {
+        SqlTemplateExecute::<DB>::new(release.clone(), None)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,148 @@
+use std::fmt::Write;
+
+use crate::{error::UsageError, statement::split_trailing};
+
+/// Direction of a [`SortSpec`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SortDirection {
+    /// `ASC`
+    #[default]
+    Asc,
+    /// `DESC`
+    Desc,
+}
+
+/// Placement of `NULL` values in a [`SortSpec`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NullsOrder {
+    /// `NULLS FIRST`
+    First,
+    /// `NULLS LAST`
+    Last,
+}
+
+/// One runtime-chosen sort key, appended as `ORDER BY` by
+/// `DBAdapter::with_sort`
+///
+/// The column must be listed in the `#[sql_template(order_by = "...")]`
+/// allow-list of the template, see `SqlTemplate::SORT_COLUMNS`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SortSpec {
+    /// Column name, as written in the allow-list
+    pub column: String,
+    /// Sort direction
+    pub direction: SortDirection,
+    /// `NULL` placement, the database default if `None`
+    pub nulls: Option<NullsOrder>,
+}
+
+impl SortSpec {
+    /// Ascending sort on `column`
+    pub fn asc(column: impl Into<String>) -> Self {
+        Self {
+            column: column.into(),
+            direction: SortDirection::Asc,
+            nulls: None,
+        }
+    }
+    /// Descending sort on `column`
+    pub fn desc(column: impl Into<String>) -> Self {
+        Self {
+            column: column.into(),
+            direction: SortDirection::Desc,
+            nulls: None,
+        }
+    }
+    /// Places `NULL` values first
+    pub fn nulls_first(mut self) -> Self {
+        self.nulls = Some(NullsOrder::First);
+        self
+    }
+    /// Places `NULL` values last
+    pub fn nulls_last(mut self) -> Self {
+        self.nulls = Some(NullsOrder::Last);
+        self
+    }
+}
+
+/// Fails with the first column of `sort` missing from `allowed`
+#[doc(hidden)]
+pub fn check_sort_columns(sort: &[SortSpec], allowed: &[&str]) -> Result<(), UsageError> {
+    match sort
+        .iter()
+        .find(|spec| !allowed.contains(&spec.column.as_str()))
+    {
+        Some(spec) => Err(UsageError::SortColumn(spec.column.clone())),
+        None => Ok(()),
+    }
+}
+
+/// Appends ` ORDER BY ...` for `sort`
+///
+/// Without `native_nulls` (MySQL, SQL Server), `NULLS FIRST/LAST` is emulated
+/// with a leading `CASE WHEN column IS NULL` key.
+#[doc(hidden)]
+pub fn write_sort(sql: &mut String, sort: &[SortSpec], native_nulls: bool) {
+    let trailing = split_trailing(sql);
+    for (i, spec) in sort.iter().enumerate() {
+        sql.push_str(if i == 0 { " ORDER BY " } else { ", " });
+        let column = &spec.column;
+        if let (Some(nulls), false) = (spec.nulls, native_nulls) {
+            let (null, other) = match nulls {
+                NullsOrder::First => (0, 1),
+                NullsOrder::Last => (1, 0),
+            };
+            let _ = write!(
+                sql,
+                "CASE WHEN {column} IS NULL THEN {null} ELSE {other} END, "
+            );
+        }
+        sql.push_str(column);
+        sql.push_str(match spec.direction {
+            SortDirection::Asc => " ASC",
+            SortDirection::Desc => " DESC",
+        });
+        match (spec.nulls, native_nulls) {
+            (Some(NullsOrder::First), true) => sql.push_str(" NULLS FIRST"),
+            (Some(NullsOrder::Last), true) => sql.push_str(" NULLS LAST"),
+            _ => {}
+        }
+    }
+    sql.push_str(&trailing);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn native_sort() {
+        let mut sql = "select * from t -- all\n".to_string();
+        let sort = [SortSpec::asc("a").nulls_last(), SortSpec::desc("b")];
+        write_sort(&mut sql, &sort, true);
+        assert_eq!(
+            sql,
+            "select * from t ORDER BY a ASC NULLS LAST, b DESC -- all\n"
+        );
+    }
+
+    #[test]
+    fn emulated_nulls() {
+        let mut sql = "select * from t".to_string();
+        write_sort(&mut sql, &[SortSpec::desc("a").nulls_first()], false);
+        assert_eq!(
+            sql,
+            "select * from t ORDER BY CASE WHEN a IS NULL THEN 0 ELSE 1 END, a DESC"
+        );
+    }
+
+    #[test]
+    fn sort_columns() {
+        let sort = [SortSpec::asc("a"), SortSpec::asc("b")];
+        assert_eq!(check_sort_columns(&sort, &["a", "b"]), Ok(()));
+        assert_eq!(
+            check_sort_columns(&sort, &["a"]),
+            Err(UsageError::SortColumn("b".to_string()))
+        );
+    }
+}
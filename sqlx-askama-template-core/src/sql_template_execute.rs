@@ -1,24 +1,58 @@
+use std::{fmt, time::Duration};
+
 use futures_core::stream::BoxStream;
 use futures_util::{StreamExt, TryStreamExt};
 use sqlx_core::{
     Either, Error,
-    arguments::IntoArguments,
+    arguments::{Arguments, IntoArguments},
     database::{Database, HasStatementCache},
     executor::{Execute, Executor},
     from_row::FromRow,
     query::{Map, Query, query, query_with},
     query_as::{QueryAs, query_as, query_as_with},
     sql_str::{AssertSqlSafe, SqlSafeStr, SqlStr},
+    statement::Statement,
 };
+/// Statement-level execution options, applied together with
+/// `DBAdapter::with_options`
+///
+/// `None` and `false` fields keep the adapter setting. sqlx has no fetch size
+/// or row buffering hint; rows are always streamed as the driver reads them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryOptions {
+    /// Prepared statement caching, see [`SqlTemplateExecute::set_persistent`]
+    pub persistent: Option<bool>,
+    /// Database-side timeout, see `DBAdapter::with_db_timeout`
+    pub timeout: Option<Duration>,
+    /// Row limit of unbounded `SELECT` statements, see
+    /// `DBAdapter::with_safety_limit`
+    pub max_rows: Option<u64>,
+    /// Query tag comment, see `DBAdapter::with_query_tag`
+    pub query_tag: bool,
+}
+
 /// Internal executor for SQL templates
 pub struct SqlTemplateExecute<DB: Database> {
     /// Reference to SQL query string
-    pub(crate) sql: String,
+    #[doc(hidden)]
+    pub sql: String,
     /// SQL parameters
-    pub(crate) arguments: Option<DB::Arguments>,
+    #[doc(hidden)]
+    pub arguments: Option<DB::Arguments>,
     /// Persistent flag
-    pub(crate) persistent: bool,
+    #[doc(hidden)]
+    pub persistent: bool,
+    /// Statement prepared for `sql`, see [`SqlTemplateExecute::with_statement`]
+    #[doc(hidden)]
+    pub statement: Option<DB::Statement>,
 }
+/// Rendered query owning its SQL and arguments, returned by
+/// `SqlTemplate::render_owned`
+///
+/// It borrows nothing from the template, so it is `Send + 'static` and can be
+/// moved into spawned tasks.
+pub type RenderedQuery<DB> = SqlTemplateExecute<DB>;
+
 impl<DB: Database> Clone for SqlTemplateExecute<DB>
 where
     DB::Arguments: Clone,
@@ -28,9 +62,21 @@ where
             sql: self.sql.clone(),
             arguments: self.arguments.clone(),
             persistent: self.persistent,
+            statement: self.statement.clone(),
         }
     }
 }
+/// Summarizes the query: SQL length and argument count, leaving out values
+impl<DB: Database> fmt::Debug for SqlTemplateExecute<DB> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SqlTemplateExecute")
+            .field("sql_len", &self.sql.len())
+            .field("arguments", &self.arguments.as_ref().map(Arguments::len))
+            .field("persistent", &self.persistent)
+            .field("prepared", &self.statement.is_some())
+            .finish()
+    }
+}
 impl<DB: Database> SqlTemplateExecute<DB> {
     /// Creates a new SQL template executor
     pub fn new(sql: String, arguments: Option<DB::Arguments>) -> Self {
@@ -38,6 +84,7 @@ impl<DB: Database> SqlTemplateExecute<DB> {
             sql,
             arguments,
             persistent: true,
+            statement: None,
         }
     }
     /// If `true`, the statement will get prepared once and cached to the
@@ -54,6 +101,63 @@ impl<DB: Database> SqlTemplateExecute<DB> {
         self.persistent = persistent;
         self
     }
+    /// Applies the options that need no database dialect, i.e.
+    /// [`QueryOptions::persistent`]; the others are applied by
+    /// `DBAdapter::with_options`
+    pub fn with_options(mut self, options: QueryOptions) -> Self {
+        if let Some(persistent) = options.persistent {
+            self.persistent = persistent;
+        }
+        self
+    }
+    /// Attaches a statement prepared for the same SQL, returned by
+    /// [`Execute::statement`] so the driver can skip describing the query
+    ///
+    /// Ignored if `statement` was prepared for another SQL, e.g. when a
+    /// dynamic template rendered differently; reuse it for static templates.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use sqlx::{Execute, SqlitePool};
+    /// use sqlx_askama_template::SqlTemplate;
+    ///
+    /// #[derive(SqlTemplate, Default)]
+    /// #[template(source = "select * from users where id = {{ e(id) }}")]
+    /// struct UserQuery {
+    ///     id: i64,
+    /// }
+    ///
+    /// # async fn run(pool: SqlitePool, ids: Vec<i64>) -> Result<(), Box<dyn std::error::Error>> {
+    /// let prepared = UserQuery::default().render_executable()?.prepare(&pool).await?;
+    /// let statement = prepared.statement().cloned().unwrap();
+    /// for id in ids {
+    ///     let row = UserQuery { id }
+    ///         .render_executable()?
+    ///         .with_statement(statement.clone())
+    ///         .fetch_one(&pool)
+    ///         .await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_statement(mut self, statement: DB::Statement) -> Self {
+        if statement.sql().as_str() == self.sql {
+            self.statement = Some(statement);
+        }
+        self
+    }
+    /// Prepares the SQL on `executor` and attaches the statement, see
+    /// [`Self::with_statement`]
+    pub async fn prepare<'c, E>(mut self, executor: E) -> Result<Self, Error>
+    where
+        E: Executor<'c, Database = DB>,
+    {
+        let statement = executor
+            .prepare(AssertSqlSafe(self.sql.clone()).into_sql_str())
+            .await?;
+        self.statement = Some(statement);
+        Ok(self)
+    }
 }
 impl<'q, DB> SqlTemplateExecute<DB>
 where
@@ -327,10 +431,11 @@ impl<'q, DB: Database> Execute<'q, DB> for SqlTemplateExecute<DB> {
         AssertSqlSafe(self.sql).into_sql_str()
     }
 
-    /// Gets prepared statement (not supported in this implementation)
+    /// Gets the prepared statement attached by [`SqlTemplateExecute::with_statement`]
+    /// or [`SqlTemplateExecute::prepare`]
     #[inline]
     fn statement(&self) -> Option<&DB::Statement> {
-        None
+        self.statement.as_ref()
     }
 
     /// Takes ownership of the bound arguments
@@ -345,3 +450,56 @@ impl<'q, DB: Database> Execute<'q, DB> for SqlTemplateExecute<DB> {
         self.persistent
     }
 }
+
+/// Runs the same rendered SQL and arguments several times, e.g. on each shard,
+/// without rendering the template again; the arguments are cloned per run
+///
+/// # Example
+/// ```no_run
+/// use sqlx::{Executor, SqlitePool};
+/// use sqlx_askama_template::SqlTemplate;
+///
+/// #[derive(SqlTemplate)]
+/// #[template(source = "select * from users where id = {{ e(id) }}")]
+/// struct UserQuery {
+///     id: i64,
+/// }
+///
+/// # async fn run(shards: Vec<SqlitePool>) -> Result<(), Box<dyn std::error::Error>> {
+/// let executable = UserQuery { id: 1 }.render_executable()?;
+/// for shard in &shards {
+///     let rows = shard.fetch_all(&executable).await?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+impl<'q, DB> Execute<'q, DB> for &SqlTemplateExecute<DB>
+where
+    DB: Database,
+    DB::Arguments: Clone + Sync,
+{
+    /// Returns a copy of the SQL query string
+    #[inline]
+    fn sql(self) -> SqlStr {
+        tracing::debug!("Executing SQL: {}", self.sql);
+        AssertSqlSafe(self.sql.clone()).into_sql_str()
+    }
+
+    /// Gets the attached prepared statement
+    #[inline]
+    fn statement(&self) -> Option<&DB::Statement> {
+        self.statement.as_ref()
+    }
+
+    /// Clones the bound arguments
+    #[inline]
+    fn take_arguments(&mut self) -> Result<Option<DB::Arguments>, sqlx_core::error::BoxDynError> {
+        Ok(self.arguments.clone())
+    }
+
+    /// Checks if query is persistent
+    #[inline]
+    fn persistent(&self) -> bool {
+        self.persistent
+    }
+}
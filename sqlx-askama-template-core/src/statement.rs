@@ -0,0 +1,356 @@
+use std::fmt;
+
+/// Kind of SQL statement, checked with `DBAdapter::expect_statement`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatementKind {
+    /// `SELECT` (also `VALUES`/`TABLE`)
+    Select,
+    /// `INSERT`
+    Insert,
+    /// `UPDATE`
+    Update,
+    /// `DELETE`
+    Delete,
+    /// Anything, no check (default)
+    #[default]
+    Any,
+}
+
+impl StatementKind {
+    /// Kind of `sql`, from its leading keyword
+    ///
+    /// Comments are skipped, and for a `WITH` statement the main statement
+    /// after the common table expressions decides. Returns `None` for other
+    /// statements (DDL, `MERGE`, ...).
+    pub fn of(sql: &str) -> Option<Self> {
+        // `(select ...) union (select ...)`
+        if let Some(inner) = sql.trim_start().strip_prefix('(') {
+            return Self::of(inner);
+        }
+        let mut words = top_level_words(sql);
+        let mut word = words.next()?;
+        if word.eq_ignore_ascii_case("with") {
+            word = words.find(|w| Self::from_keyword(w).is_some())?;
+        }
+        Self::from_keyword(word)
+    }
+    /// Whether `sql` is a statement of this kind
+    pub fn matches(self, sql: &str) -> bool {
+        self == Self::Any || Self::of(sql) == Some(self)
+    }
+    fn from_keyword(word: &str) -> Option<Self> {
+        let kinds = [
+            ("select", Self::Select),
+            ("values", Self::Select),
+            ("table", Self::Select),
+            ("insert", Self::Insert),
+            ("update", Self::Update),
+            ("delete", Self::Delete),
+        ];
+        kinds
+            .into_iter()
+            .find(|(kw, _)| word.eq_ignore_ascii_case(kw))
+            .map(|(_, kind)| kind)
+    }
+}
+
+impl fmt::Display for StatementKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Select => "SELECT",
+            Self::Insert => "INSERT",
+            Self::Update => "UPDATE",
+            Self::Delete => "DELETE",
+            Self::Any => "any",
+        })
+    }
+}
+
+/// Rows counted by `DBAdapter::count` for a `GROUP BY` query, set with
+/// `DBAdapter::with_count_over`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CountOver {
+    /// The rows of the query, one per group (default)
+    #[default]
+    Groups,
+    /// The rows before grouping: the top-level `GROUP BY` and the clauses
+    /// after it (`HAVING`, `ORDER BY`, `LIMIT`) are dropped
+    Rows,
+}
+
+/// Rewrites a query with a top-level `GROUP BY` as
+/// `select {select_list} from ...`, dropping the `GROUP BY` and everything
+/// after it
+///
+/// Returns `Ok(false)`, leaving `sql` as is, when there is no top-level
+/// `GROUP BY`; fails for compound (`UNION`...) queries.
+#[doc(hidden)]
+pub fn write_ungrouped_sql(sql: &mut String, select_list: &str) -> Result<bool, String> {
+    let words: Vec<(usize, &str)> = top_level_words(sql)
+        .map(|word| (word.as_ptr() as usize - sql.as_ptr() as usize, word))
+        .collect();
+    let is = |i: usize, kw: &str| {
+        words
+            .get(i)
+            .is_some_and(|(_, w)| w.eq_ignore_ascii_case(kw))
+    };
+    let Some(group) = (0..words.len()).find(|&i| is(i, "group") && is(i + 1, "by")) else {
+        return Ok(false);
+    };
+    if (0..words.len()).any(|i| is(i, "union") || is(i, "intersect") || is(i, "except")) {
+        return Err("can not count the ungrouped rows of a compound query".to_string());
+    }
+    let select = (0..group).find(|&i| is(i, "select"));
+    let from = select.and_then(|select| (select + 1..group).find(|&i| is(i, "from")));
+    let (Some(select), Some(from)) = (select, from) else {
+        return Err("can not find the `SELECT ... FROM` of the grouped query".to_string());
+    };
+    let (select_start, select_word) = words[select];
+    let head = &sql[..select_start + select_word.len()];
+    let body = sql[words[from].0..words[group].0].trim_end();
+    *sql = format!("{head} {select_list} {body}");
+    Ok(true)
+}
+
+/// Whether the first top-level word of `sql` is the keyword `kw` (ASCII
+/// case-insensitive)
+#[doc(hidden)]
+pub fn starts_with_keyword(sql: &str, kw: &str) -> bool {
+    top_level_words(sql)
+        .next()
+        .is_some_and(|word| word.eq_ignore_ascii_case(kw))
+}
+
+/// Whether one of `keywords` appears in `sql` outside parentheses, quotes and
+/// comments
+#[doc(hidden)]
+pub fn has_top_level_keyword(sql: &str, keywords: &[&str]) -> bool {
+    top_level_words(sql).any(|word| keywords.iter().any(|kw| word.eq_ignore_ascii_case(kw)))
+}
+
+/// Whether `sql` has an `ORDER BY` outside parentheses, quotes and comments
+#[doc(hidden)]
+pub fn has_top_level_order_by(sql: &str) -> bool {
+    top_level_order_by(sql).is_some()
+}
+
+/// Byte offset of the `ORDER BY` of `sql` outside parentheses, quotes and
+/// comments
+#[doc(hidden)]
+pub fn top_level_order_by(sql: &str) -> Option<usize> {
+    let mut words = top_level_words(sql).peekable();
+    while let Some(word) = words.next() {
+        if word.eq_ignore_ascii_case("order")
+            && words.peek().is_some_and(|w| w.eq_ignore_ascii_case("by"))
+        {
+            return Some(word.as_ptr() as usize - sql.as_ptr() as usize);
+        }
+    }
+    None
+}
+
+/// Keys of the top-level `ORDER BY` of `sql`, each with its direction and
+/// `NULLS` placement, up to a top-level `LIMIT`/`OFFSET`/`FETCH`/`FOR`
+#[doc(hidden)]
+pub fn top_level_order_by_keys(sql: &str) -> Option<Vec<&str>> {
+    let at = top_level_order_by(sql)?;
+    let clause = &sql[at..];
+    let mut words = top_level_words(clause).skip(2);
+    let start = words.next()?.as_ptr() as usize - clause.as_ptr() as usize;
+    let end = words
+        .find(|w| {
+            ["limit", "offset", "fetch", "for"]
+                .iter()
+                .any(|kw| w.eq_ignore_ascii_case(kw))
+        })
+        .map_or(clause.len(), |w| {
+            w.as_ptr() as usize - clause.as_ptr() as usize
+        });
+    let clause = &clause[start..end];
+    let bytes = clause.as_bytes();
+    let mut keys = Vec::new();
+    let (mut depth, mut from, mut i) = (0usize, 0, 0);
+    while i < bytes.len() {
+        match bytes[i] {
+            c @ (b'\'' | b'"' | b'`') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != c {
+                    i += 1;
+                }
+            }
+            b'(' => depth += 1,
+            b')' => depth = depth.saturating_sub(1),
+            b',' if depth == 0 => {
+                keys.push(clause[from..i].trim());
+                from = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    keys.push(clause[from..].trim());
+    Some(keys)
+}
+
+/// Words of `sql` outside parentheses, quotes and comments
+#[doc(hidden)]
+pub fn top_level_words(sql: &str) -> impl Iterator<Item = &str> {
+    let bytes = sql.as_bytes();
+    let mut depth = 0usize;
+    let mut i = 0;
+    std::iter::from_fn(move || {
+        while i < bytes.len() {
+            let c = bytes[i];
+            match c {
+                b'\'' | b'"' | b'`' => {
+                    i += 1;
+                    while i < bytes.len() && bytes[i] != c {
+                        i += 1;
+                    }
+                }
+                b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                    while i < bytes.len() && bytes[i] != b'\n' {
+                        i += 1;
+                    }
+                }
+                b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                    i += 2;
+                    while i < bytes.len() && !(bytes[i] == b'*' && bytes.get(i + 1) == Some(&b'/'))
+                    {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                b'(' => depth += 1,
+                b')' => depth = depth.saturating_sub(1),
+                _ if c.is_ascii_alphanumeric() || c == b'_' => {
+                    let start = i;
+                    while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_')
+                    {
+                        i += 1;
+                    }
+                    if depth == 0 {
+                        return Some(&sql[start..i]);
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        None
+    })
+}
+
+/// Removes and returns the trailing whitespace, comments and semicolons of
+/// `sql`, so that a clause appended to it is not swallowed by a trailing
+/// `-- comment`
+#[doc(hidden)]
+pub fn split_trailing(sql: &mut String) -> String {
+    let bytes = sql.as_bytes();
+    let mut end = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        match c {
+            b'\'' | b'"' | b'`' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != c {
+                    i += 1;
+                }
+                end = (i + 1).min(bytes.len());
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i += 2;
+                while i < bytes.len() && !(bytes[i] == b'*' && bytes.get(i + 1) == Some(&b'/')) {
+                    i += 1;
+                }
+                i += 1;
+            }
+            b';' => {}
+            _ if c.is_ascii_whitespace() => {}
+            _ => end = i + 1,
+        }
+        i += 1;
+    }
+    sql.split_off(end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statement_kind_of() {
+        assert_eq!(
+            StatementKind::of("/* x */ select 1"),
+            Some(StatementKind::Select)
+        );
+        assert_eq!(
+            StatementKind::of("with t as (select 1) delete from a"),
+            Some(StatementKind::Delete)
+        );
+        assert_eq!(StatementKind::of("drop table a"), None);
+    }
+
+    #[test]
+    fn split_trailing_comments_and_semicolons() {
+        let mut sql = "select 1 -- note\n ; /* end */ ".to_string();
+        assert_eq!(split_trailing(&mut sql), " -- note\n ; /* end */ ");
+        assert_eq!(sql, "select 1");
+
+        let mut sql = "select '-- not a comment;'".to_string();
+        assert_eq!(split_trailing(&mut sql), "");
+        assert_eq!(sql, "select '-- not a comment;'");
+    }
+
+    #[test]
+    fn top_level_words_skip_nested_and_quoted() {
+        let sql = "select a, (select b from c) as \"order\" from t -- order by\n where x = 'by'";
+        let words: Vec<_> = top_level_words(sql).collect();
+        assert_eq!(words, ["select", "a", "as", "from", "t", "where", "x"]);
+        assert!(!has_top_level_order_by(sql));
+        assert!(has_top_level_order_by("select * from t ORDER  BY id"));
+        assert_eq!(
+            top_level_order_by("select (x) from t order by id"),
+            Some(18)
+        );
+        assert!(starts_with_keyword("  SELECT 1", "select"));
+        assert!(has_top_level_keyword("select 1 union select 2", &["union"]));
+    }
+
+    #[test]
+    fn order_by_keys() {
+        assert_eq!(
+            top_level_order_by_keys(
+                "select * from t order by u.name desc nulls last, coalesce(a, b), 2 limit 5"
+            ),
+            Some(vec!["u.name desc nulls last", "coalesce(a, b)", "2"])
+        );
+        assert_eq!(
+            top_level_order_by_keys("select * from (select a from t order by a) s"),
+            None
+        );
+    }
+
+    #[test]
+    fn ungrouped_sql() {
+        let mut sql =
+            "select a, count(*) from t where x > 1 group by a having count(*) > 2 order by a"
+                .to_string();
+        assert_eq!(write_ungrouped_sql(&mut sql, "count(1)"), Ok(true));
+        assert_eq!(sql, "select count(1) from t where x > 1");
+
+        let mut sql = "select a from t".to_string();
+        assert_eq!(write_ungrouped_sql(&mut sql, "count(1)"), Ok(false));
+        assert_eq!(sql, "select a from t");
+
+        let mut sql = "select a from t group by a union select b from u".to_string();
+        assert!(write_ungrouped_sql(&mut sql, "count(1)").is_err());
+    }
+}
@@ -0,0 +1,701 @@
+use std::{
+    any::Any,
+    cell::{Cell, RefCell},
+    fmt,
+    ops::Deref,
+};
+
+use sqlx_core::{
+    Error, arguments::Arguments, database::Database, encode::Encode, error::BoxDynError,
+    types::Type,
+};
+
+use crate::{
+    array_bind::{array_binder, bind_any_array},
+    bound_param::{BoundParam, push_bound_param},
+    context::current_value,
+    error::{Error as TemplateError, ParamEncodeError, UsageError},
+    fragment::SqlFragment,
+    list_split::window_list,
+    naming::{renders_postgres, write_identifier},
+};
+/// SQL template argument processor handling safe parameter encoding and placeholder generation
+///
+/// # Generic Parameters
+/// - `'q`: Lifetime for database arguments
+/// - `DB`: Database type implementing [`sqlx::Database`]
+/// - `D`: Template data type
+pub struct TemplateArg<'q, DB: Database, D> {
+    /// Stores any encoding errors
+    error: RefCell<Option<Error>>,
+    /// Stores SQL parameters
+    arguments: RefCell<Option<DB::Arguments>>,
+    format_placeholder_fn: Option<fn(usize, &mut String)>,
+    /// Parameters encoded now are secret
+    secret: Cell<bool>,
+    data: &'q D,
+}
+
+/// Summarizes the encoding state: argument count and pending error
+impl<DB: Database, D> fmt::Debug for TemplateArg<'_, DB, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let arguments = self
+            .arguments
+            .try_borrow()
+            .ok()
+            .map(|args| args.as_ref().map_or(0, Arguments::len));
+        let failed = self.error.try_borrow().ok().map(|error| error.is_some());
+        f.debug_struct("TemplateArg")
+            .field("arguments", &arguments)
+            .field("failed", &failed)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'q, DB: Database, D> TemplateArg<'q, DB, D> {
+    /// Creates a new TemplateArg instance wrapping template data
+    ///
+    /// # Arguments
+    /// * `d` - Reference to template data with lifetime `'t`
+    pub fn new(d: &'q D) -> Self {
+        TemplateArg {
+            error: RefCell::new(None),
+            arguments: RefCell::new(take_inherited::<DB>()),
+            format_placeholder_fn: None,
+            secret: Cell::new(false),
+            data: d,
+        }
+    }
+    /// Sets custom placeholder formatting function
+    ///
+    /// # Arguments
+    /// * `format_placeholder` - Function that takes parameter index and appends placeholder
+    pub fn set_format_placeholder_fn(&mut self, format_placeholder: fn(usize, &mut String)) {
+        self.format_placeholder_fn = Some(format_placeholder);
+    }
+
+    /// Encodes a single parameter and returns its placeholder
+    ///
+    /// # Arguments
+    /// * `t` - Value implementing [`sqlx::Encode`] and [`sqlx::Type`]
+    ///
+    /// # Returns
+    /// Placeholder string (e.g., `$1` or `?`)
+    ///
+    /// # Example
+    /// ```
+    /// use sqlx::Postgres;
+    /// use sqlx_askama_template::SqlTemplate;
+    ///
+    /// #[derive(SqlTemplate)]
+    /// #[template(source = "select * from users where id = {{ e(user_id) }}")]
+    /// struct UserQuery {
+    ///     user_id: i64,
+    /// }
+    ///
+    /// let (sql, _) = SqlTemplate::<Postgres>::render(&UserQuery { user_id: 1 })?;
+    /// assert_eq!(sql, "select * from users where id = $1");
+    /// # Ok::<(), sqlx_askama_template::Error>(())
+    /// ```
+    pub fn e<'t, ImplEncode>(&self, t: ImplEncode) -> String
+    where
+        ImplEncode: Encode<'t, DB> + Type<DB>,
+    {
+        self.encode(t, None)
+    }
+    /// Like [`Self::e`], naming the parameter in encoding errors
+    ///
+    /// # Example
+    /// ```
+    /// use sqlx::Postgres;
+    /// use sqlx_askama_template::SqlTemplate;
+    ///
+    /// #[derive(SqlTemplate)]
+    /// #[template(source = r#"select * from users where id = {{ e_named("user_id", user_id) }}"#)]
+    /// struct UserQuery {
+    ///     user_id: i64,
+    /// }
+    ///
+    /// let (sql, _) = SqlTemplate::<Postgres>::render(&UserQuery { user_id: 1 })?;
+    /// assert_eq!(sql, "select * from users where id = $1");
+    /// # Ok::<(), sqlx_askama_template::Error>(())
+    /// ```
+    pub fn e_named<'t, ImplEncode>(&self, label: &str, t: ImplEncode) -> String
+    where
+        ImplEncode: Encode<'t, DB> + Type<DB>,
+    {
+        self.encode(t, Some(label))
+    }
+    /// Like [`Self::e`], marking the parameter secret in its [`BoundParam`]
+    ///
+    /// The derive turns `e(field)` into this call for `#[param(secret)]` fields.
+    pub fn e_secret<'t, ImplEncode>(&self, t: ImplEncode) -> String
+    where
+        ImplEncode: Encode<'t, DB> + Type<DB>,
+    {
+        self.secret(|| self.e(t))
+    }
+    /// Like [`Self::e_named`], marking the parameter secret in its [`BoundParam`]
+    pub fn e_named_secret<'t, ImplEncode>(&self, label: &str, t: ImplEncode) -> String
+    where
+        ImplEncode: Encode<'t, DB> + Type<DB>,
+    {
+        self.secret(|| self.e_named(label, t))
+    }
+    /// Like [`Self::el`], marking every parameter secret in its [`BoundParam`]
+    pub fn el_secret<'t, ImplEncode>(
+        &self,
+        args: impl ::std::iter::IntoIterator<Item = ImplEncode>,
+    ) -> String
+    where
+        ImplEncode: Encode<'t, DB> + Type<DB>,
+    {
+        self.secret(|| self.el(args))
+    }
+    /// Runs `f` with the parameters it encodes marked secret
+    fn secret<R>(&self, f: impl FnOnce() -> R) -> R {
+        let previous = self.secret.replace(true);
+        let result = f();
+        self.secret.set(previous);
+        result
+    }
+    fn encode<'t, ImplEncode>(&self, t: ImplEncode, label: Option<&str>) -> String
+    where
+        ImplEncode: Encode<'t, DB> + Type<DB>,
+    {
+        self.encode_with(
+            |arguments| arguments.add(t),
+            label,
+            std::any::type_name::<ImplEncode>(),
+        )
+    }
+    fn encode_with(
+        &self,
+        add: impl FnOnce(&mut DB::Arguments) -> Result<(), BoxDynError>,
+        label: Option<&str>,
+        type_name: &'static str,
+    ) -> String {
+        let mut arguments = self.arguments.borrow_mut().take().unwrap_or_default();
+        let mut err = self.error.borrow_mut();
+        let index = arguments.len() + 1;
+        let param_err = |source| {
+            Error::Encode(Box::new(ParamEncodeError {
+                index,
+                label: label.map(str::to_string),
+                source,
+            }))
+        };
+
+        if let Err(encode_err) = add(&mut arguments)
+            && err.is_none()
+        {
+            *err = Some(param_err(encode_err));
+        }
+
+        let mut placeholder = String::new();
+        if let Some(encode_placeholder_fn) = &self.format_placeholder_fn {
+            encode_placeholder_fn(arguments.len(), &mut placeholder);
+        } else if let Err(e) = arguments.format_placeholder(&mut placeholder)
+            && err.is_none()
+        {
+            *err = Some(param_err(Box::new(e)));
+        }
+        *self.arguments.borrow_mut() = Some(arguments);
+        push_bound_param(|| BoundParam {
+            index,
+            placeholder: placeholder.clone(),
+            name: label.map(str::to_string),
+            type_name: type_name.trim_start_matches('&'),
+            secret: self.secret.get(),
+        });
+        placeholder
+    }
+    /// Encodes an iterable of parameters and returns parenthesized placeholders
+    ///
+    /// # Arguments
+    /// * `args` - Iterator of encodable values
+    ///
+    /// # Returns
+    /// Comma-separated placeholders wrapped in parentheses
+    ///
+    /// # Example
+    /// ```
+    /// use sqlx::Postgres;
+    /// use sqlx_askama_template::SqlTemplate;
+    ///
+    /// #[derive(SqlTemplate)]
+    /// #[template(source = "select * from users where id in {{ el(ids) }}")]
+    /// #[add_type(i64)]
+    /// struct UsersQuery {
+    ///     #[ignore_type]
+    ///     ids: Vec<i64>,
+    /// }
+    ///
+    /// let (sql, _) = SqlTemplate::<Postgres>::render(&UsersQuery { ids: vec![1, 2, 3] })?;
+    /// assert_eq!(sql, "select * from users where id in ($1,$2,$3)");
+    /// # Ok::<(), sqlx_askama_template::Error>(())
+    /// ```
+    pub fn el<'t, ImplEncode>(
+        &self,
+        args: impl ::std::iter::IntoIterator<Item = ImplEncode>,
+    ) -> String
+    where
+        ImplEncode: Encode<'t, DB> + Type<DB>,
+    {
+        let args = window_list(args.into_iter().collect());
+        let mut placeholder = String::new();
+        placeholder.push('(');
+
+        for arg in args {
+            placeholder.push_str(&self.e(arg));
+
+            placeholder.push(',');
+        }
+
+        if placeholder.ends_with(",") {
+            placeholder.pop();
+        }
+        placeholder.push(')');
+
+        placeholder
+    }
+
+    /// Appends `cast` to each placeholder of `placeholders`: `$1::uuid` for
+    /// `e()`, `($1::uuid,$2::uuid)` for `el()`
+    ///
+    /// The derive wraps the binds of `#[sql_cast("::uuid")]` fields in this call.
+    ///
+    /// # Example
+    /// ```
+    /// use sqlx::Postgres;
+    /// use sqlx_askama_template::SqlTemplate;
+    ///
+    /// #[derive(SqlTemplate)]
+    /// #[template(source = r#"select * from users where id = {{ sql_cast(e(id), "::uuid") }}"#)]
+    /// struct UserQuery {
+    ///     id: String,
+    /// }
+    ///
+    /// let id = "67e55044-10b1-426f-9247-bb680e5fe0c8".to_string();
+    /// let (sql, _) = SqlTemplate::<Postgres>::render(&UserQuery { id })?;
+    /// assert_eq!(sql, "select * from users where id = $1::uuid");
+    /// # Ok::<(), sqlx_askama_template::Error>(())
+    /// ```
+    pub fn sql_cast(&self, placeholders: impl AsRef<str>, cast: &str) -> String {
+        let placeholders = placeholders.as_ref();
+        match placeholders
+            .strip_prefix('(')
+            .and_then(|list| list.strip_suffix(')'))
+        {
+            Some(list) => {
+                let list: Vec<String> = list
+                    .split(',')
+                    .filter(|placeholder| !placeholder.is_empty())
+                    .map(|placeholder| format!("{placeholder}{cast}"))
+                    .collect();
+                format!("({})", list.join(","))
+            }
+            None => format!("{placeholders}{cast}"),
+        }
+    }
+
+    /// Encodes a list for an `IN` filter, choosing the form by dialect
+    ///
+    /// Renders `= ANY($1)` with the list bound as one native array when the
+    /// query runs on PostgreSQL, keeping a single statement for every list
+    /// length; elsewhere `IN ($1, $2...)` as with [`Self::el`]. The dialect is
+    /// the one the adapter detected, so `sqlx::Any` templates bind arrays too.
+    ///
+    /// On PostgreSQL the element type must be registered with
+    /// [`crate::register_array_type`], except for `sqlx::Any` whose list is
+    /// bound as an array literal cast to the array type (`= ANY($1::int8[])`);
+    /// an unregistered type fails the render with [`UsageError::Unsupported`].
+    /// An empty list matches no row: `IN (NULL)`, or an empty array.
+    ///
+    /// # Example
+    /// ```
+    /// use sqlx::Postgres;
+    /// use sqlx_askama_template::SqlTemplate;
+    ///
+    /// use sqlx::Sqlite;
+    /// use sqlx_askama_template::register_array_type;
+    ///
+    /// #[derive(SqlTemplate)]
+    /// #[template(source = "select * from users where id {{ e_in(ids) }}")]
+    /// #[add_type(i64)]
+    /// struct UsersQuery {
+    ///     #[ignore_type]
+    ///     ids: Vec<i64>,
+    /// }
+    ///
+    /// register_array_type::<Postgres, i64>();
+    /// let (sql, _) = SqlTemplate::<Postgres>::render(&UsersQuery { ids: vec![1, 2] })?;
+    /// assert_eq!(sql, "select * from users where id = ANY($1)");
+    /// let (sql, _) = SqlTemplate::<Sqlite>::render(&UsersQuery { ids: vec![1, 2] })?;
+    /// assert_eq!(sql, "select * from users where id IN (?,?)");
+    /// let (sql, _) = SqlTemplate::<Sqlite>::render(&UsersQuery { ids: vec![] })?;
+    /// assert_eq!(sql, "select * from users where id IN (NULL)");
+    /// # Ok::<(), sqlx_askama_template::Error>(())
+    /// ```
+    pub fn e_in<'t, T>(&self, values: &'t [T]) -> String
+    where
+        T: Clone + Send + 'static,
+        &'t T: Encode<'t, DB> + Type<DB>,
+    {
+        if !renders_postgres::<DB>() {
+            if values.is_empty() {
+                return "IN (NULL)".to_string();
+            }
+            return format!("IN {}", self.el(values));
+        }
+        let type_name = std::any::type_name::<Vec<T>>();
+        if let Some(bind) = array_binder::<DB, T>() {
+            let placeholder = self.encode_with(
+                |arguments| bind(arguments, values.to_vec()),
+                None,
+                type_name,
+            );
+            return format!("= ANY({placeholder})");
+        }
+        if DB::NAME == sqlx_core::any::Any::NAME {
+            let mut array_type = "";
+            let placeholder = self.encode_with(
+                |arguments| {
+                    array_type = bind_any_array::<DB, T>(arguments, values)?;
+                    Ok(())
+                },
+                None,
+                type_name,
+            );
+            return format!("= ANY({placeholder}::{array_type})");
+        }
+        let mut err = self.error.borrow_mut();
+        if err.is_none() {
+            *err = Some(Error::from(UsageError::Unsupported(format!(
+                "e_in() binds a PostgreSQL array of `{}`, register it with `register_array_type`",
+                std::any::type_name::<T>()
+            ))));
+        }
+        String::new()
+    }
+
+    /// Includes a pre-built SQL fragment, binding its values in order
+    ///
+    /// Each `?` marker outside quotes is replaced by a placeholder of the target database.
+    ///
+    /// # Example
+    /// ```
+    /// use sqlx::Postgres;
+    /// use sqlx_askama_template::SqlTemplate;
+    ///
+    /// use sqlx_askama_template::SqlFragment;
+    ///
+    /// #[derive(SqlTemplate)]
+    /// #[template(source = "select * from users where {{ sql_include(filter) }}")]
+    /// #[add_type(i64)]
+    /// struct UserQuery {
+    ///     #[ignore_type]
+    ///     filter: SqlFragment<i64>,
+    /// }
+    ///
+    /// let filter = SqlFragment::new("age > ? and score < ?", vec![18, 100]);
+    /// let (sql, _) = SqlTemplate::<Postgres>::render(&UserQuery { filter })?;
+    /// assert_eq!(sql, "select * from users where age > $1 and score < $2");
+    /// # Ok::<(), sqlx_askama_template::Error>(())
+    /// ```
+    pub fn sql_include<'t, V>(&self, fragment: &'t SqlFragment<V>) -> String
+    where
+        V: Encode<'t, DB> + Type<DB>,
+    {
+        let segments = fragment.segments();
+        if segments.len() != fragment.values.len() + 1 {
+            let mut err = self.error.borrow_mut();
+            if err.is_none() {
+                *err = Some(Error::from(UsageError::Fragment(format!(
+                    "sql fragment has {} bind markers but {} values",
+                    segments.len() - 1,
+                    fragment.values.len()
+                ))));
+            }
+        }
+        let mut sql = String::new();
+        let mut values = fragment.values.iter();
+        for (i, segment) in segments.iter().enumerate() {
+            if i > 0
+                && let Some(value) = values.next()
+            {
+                sql.push_str(&self.e(value));
+            }
+            sql.push_str(segment);
+        }
+        sql
+    }
+
+    /// Renders a nested template in place, its parameters appended to the
+    /// arguments of this one with the placeholder numbering continued
+    ///
+    /// `render` receives the placeholder function of this template and the
+    /// buffer for the nested SQL. Used by the `include_fragment()` helper of
+    /// the derive.
+    #[doc(hidden)]
+    pub fn include_nested(
+        &self,
+        render: impl FnOnce(
+            Option<fn(usize, &mut String)>,
+            &mut String,
+        ) -> Result<Option<DB::Arguments>, TemplateError>,
+    ) -> String {
+        let arguments = self.arguments.borrow_mut().take().unwrap_or_default();
+        INHERITED.with(|i| *i.borrow_mut() = Some(Box::new(arguments)));
+        let clear = ClearInherited;
+        let mut sql = String::new();
+        let result = render(self.format_placeholder_fn, &mut sql);
+        // Still set when the nested template did not render through a `TemplateArg`
+        let unclaimed = take_inherited::<DB>();
+        drop(clear);
+        let error = match (result, unclaimed) {
+            (Ok(arguments), None) => {
+                *self.arguments.borrow_mut() = arguments;
+                None
+            }
+            (Ok(arguments), Some(own)) => {
+                *self.arguments.borrow_mut() = Some(own);
+                arguments.filter(|arguments| arguments.len() > 0).map(|_| {
+                    Error::from(UsageError::Fragment(
+                        "the included fragment binds its parameters without `TemplateArg`, \
+                         derive `SqlTemplate` for it"
+                            .to_string(),
+                    ))
+                })
+            }
+            (Err(e), own) => {
+                *self.arguments.borrow_mut() = own;
+                Some(e.into())
+            }
+        };
+        if let Some(error) = error {
+            let mut err = self.error.borrow_mut();
+            if err.is_none() {
+                *err = Some(error);
+            }
+        }
+        sql
+    }
+
+    /// Returns a value of the current [`crate::TemplateContext`], or an empty
+    /// string if it is not set
+    ///
+    /// # Example
+    /// ```
+    /// use sqlx::Postgres;
+    /// use sqlx_askama_template::SqlTemplate;
+    ///
+    /// use sqlx_askama_template::TemplateContext;
+    ///
+    /// #[derive(SqlTemplate)]
+    /// #[template(source = r#"select * from users where app = {{ e(ctx("app")) }}"#)]
+    /// #[add_type(String)]
+    /// struct UserQuery;
+    ///
+    /// let ctx = TemplateContext::new().with("app", "billing");
+    /// let (sql, _) = SqlTemplate::<Postgres>::render_with_context(&UserQuery, &ctx)?;
+    /// assert_eq!(sql, "select * from users where app = $1");
+    /// # Ok::<(), sqlx_askama_template::Error>(())
+    /// ```
+    pub fn ctx(&self, key: &str) -> String {
+        current_value(key, |v| v.unwrap_or_default().to_string())
+    }
+    /// Returns whether a value of the current [`crate::TemplateContext`] is
+    /// `true`, `1`, `yes` or `on` (case-insensitive)
+    ///
+    /// # Example
+    /// ```
+    /// use sqlx::Postgres;
+    /// use sqlx_askama_template::SqlTemplate;
+    ///
+    /// use sqlx_askama_template::TemplateContext;
+    ///
+    /// #[derive(SqlTemplate)]
+    /// #[template(source = r#"
+    ///     select * from users
+    ///     {%- if ctx_flag("soft_delete") %} where deleted_at is null{% endif %}
+    /// "#)]
+    /// struct UserQuery;
+    ///
+    /// let ctx = TemplateContext::new().with("soft_delete", "yes");
+    /// let (sql, _) = SqlTemplate::<Postgres>::render_with_context(&UserQuery, &ctx)?;
+    /// assert_eq!(sql.trim(), "select * from users where deleted_at is null");
+    /// # Ok::<(), sqlx_askama_template::Error>(())
+    /// ```
+    pub fn ctx_flag(&self, key: &str) -> bool {
+        current_value(key, |v| {
+            v.is_some_and(|v| {
+                ["true", "1", "yes", "on"]
+                    .iter()
+                    .any(|t| v.eq_ignore_ascii_case(t))
+            })
+        })
+    }
+
+    /// Returns `name` as a quoted identifier, mapped by the global
+    /// [`crate::NamingConvention`]
+    ///
+    /// # Example
+    /// ```
+    /// use sqlx::Postgres;
+    /// use sqlx_askama_template::SqlTemplate;
+    ///
+    /// #[derive(SqlTemplate)]
+    /// #[template(source = r#"select * from users u order by {{ ident("u.createdAt") }}"#)]
+    /// struct UserQuery;
+    ///
+    /// let (sql, _) = SqlTemplate::<Postgres>::render(&UserQuery)?;
+    /// assert_eq!(sql, r#"select * from users u order by "u"."createdAt""#);
+    /// # Ok::<(), sqlx_askama_template::Error>(())
+    /// ```
+    pub fn ident(&self, name: &str) -> String {
+        let mut sql = String::new();
+        write_identifier::<DB>(name, &mut sql);
+        sql
+    }
+    /// Returns `names` as a comma-separated list of quoted identifiers, see
+    /// [`Self::ident`]
+    ///
+    /// # Example
+    /// ```
+    /// use sqlx::Postgres;
+    /// use sqlx_askama_template::SqlTemplate;
+    ///
+    /// #[derive(SqlTemplate)]
+    /// #[template(source = r#"select {{ columns(["id", "firstName"]) }} from users"#)]
+    /// struct UserQuery;
+    ///
+    /// let (sql, _) = SqlTemplate::<Postgres>::render(&UserQuery)?;
+    /// assert_eq!(sql, r#"select "id", "firstName" from users"#);
+    /// # Ok::<(), sqlx_askama_template::Error>(())
+    /// ```
+    pub fn columns<S: AsRef<str>>(&self, names: impl IntoIterator<Item = S>) -> String {
+        let mut sql = String::new();
+        for (i, name) in names.into_iter().enumerate() {
+            if i > 0 {
+                sql.push_str(", ");
+            }
+            write_identifier::<DB>(name.as_ref(), &mut sql);
+        }
+        sql
+    }
+
+    /// Takes any encoding error that occurred
+    pub fn get_err(&self) -> Option<Error> {
+        self.error.borrow_mut().take()
+    }
+
+    /// Takes ownership of the encoded arguments
+    pub fn get_arguments(&self) -> Option<DB::Arguments> {
+        self.arguments.borrow_mut().take()
+    }
+}
+
+impl<'t, DB: Database, D> Deref for TemplateArg<'t, DB, D> {
+    type Target = &'t D;
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+thread_local! {
+    static ACTIVE_ARG: RefCell<Option<Box<dyn Any>>> = const { RefCell::new(None) };
+}
+
+/// Restores the previous active argument when the render ends, even by panic
+struct Restore(Option<Box<dyn Any>>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        ACTIVE_ARG.with(|a| *a.borrow_mut() = previous);
+    }
+}
+
+thread_local! {
+    /// Arguments handed by [`TemplateArg::include_nested`] to the next
+    /// `TemplateArg` created for the same database
+    static INHERITED: RefCell<Option<Box<dyn Any>>> = const { RefCell::new(None) };
+}
+
+/// Takes the arguments of an including template, if they are for `DB`
+fn take_inherited<DB: Database>() -> Option<DB::Arguments> {
+    INHERITED.with(|i| {
+        let mut inherited = i.borrow_mut();
+        match inherited.take()?.downcast::<DB::Arguments>() {
+            Ok(arguments) => Some(*arguments),
+            Err(other) => {
+                *inherited = Some(other);
+                None
+            }
+        }
+    })
+}
+
+/// Drops arguments left unclaimed by a nested render, even by panic
+struct ClearInherited;
+
+impl Drop for ClearInherited {
+    fn drop(&mut self) {
+        INHERITED.with(|i| i.borrow_mut().take());
+    }
+}
+
+/// Runs `render` with an active [`TemplateArg`] collecting the parameters of
+/// the helpers generated for `#[sql_template(askama_template)]`, returning it
+///
+/// Used by the derive.
+#[doc(hidden)]
+pub fn render_with_active_arg<DB, R>(
+    format_placeholder: Option<fn(usize, &mut String)>,
+    render: impl FnOnce() -> R,
+) -> (R, TemplateArg<'static, DB, ()>)
+where
+    DB: Database,
+    DB::Arguments: 'static,
+{
+    let mut arg = TemplateArg::<DB, ()>::new(&());
+    if let Some(format_placeholder) = format_placeholder {
+        arg.set_format_placeholder_fn(format_placeholder);
+    }
+    let previous = ACTIVE_ARG.with(|a| a.replace(Some(Box::new(arg))));
+    let restore = Restore(previous);
+    let result = render();
+    let arg = ACTIVE_ARG.with(|a| a.borrow_mut().take());
+    drop(restore);
+    let arg = arg
+        .and_then(|arg| arg.downcast().ok())
+        .expect("active template arg");
+    (result, *arg)
+}
+
+/// Calls `f` with the [`TemplateArg`] of the running
+/// [`render_with_active_arg`], or, outside of it, with one rendering `?`
+/// placeholders, e.g. when the template is rendered as plain text
+///
+/// Used by the derive.
+#[doc(hidden)]
+pub fn with_active_arg<DB, R>(f: impl FnOnce(&TemplateArg<'static, DB, ()>) -> R) -> R
+where
+    DB: Database,
+    DB::Arguments: 'static,
+{
+    // Taken out while `f` runs, so a template included by `f` can render
+    // with its own active argument
+    let active = Restore(ACTIVE_ARG.with(|a| a.borrow_mut().take()));
+    if let Some(arg) = active
+        .0
+        .as_ref()
+        .and_then(|arg| arg.downcast_ref::<TemplateArg<'static, DB, ()>>())
+    {
+        return f(arg);
+    }
+    let mut arg = TemplateArg::new(&());
+    arg.set_format_placeholder_fn(|_, placeholder| placeholder.push('?'));
+    f(&arg)
+}
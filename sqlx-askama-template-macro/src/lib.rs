@@ -1,10 +1,10 @@
 use proc_macro::TokenStream;
 use proc_macro2::Span;
-use quote::{format_ident, quote};
+use quote::{ToTokens, format_ident, quote, quote_spanned};
 use std::collections::BTreeSet;
 use syn::{
-    DeriveInput, LifetimeParam, LitStr, Meta, Path, Token, parse::Parser, parse_macro_input,
-    punctuated::Punctuated,
+    DeriveInput, LifetimeParam, LitStr, Meta, Path, Token, parse_macro_input,
+    punctuated::Punctuated, spanned::Spanned,
 };
 
 // 用于比较类型的辅助结构
@@ -14,207 +14,3906 @@ struct TypeIdentifier(String);
 fn get_type_identifier(ty: &syn::Type) -> TypeIdentifier {
     TypeIdentifier(quote!(#ty).to_string())
 }
+/// 传给 askama 的模板参数，以及让编译器追踪外部模板来源的语句
+#[derive(Default)]
+struct TemplateArgs {
+    args: Punctuated<Meta, Token![,]>,
+    tracked: proc_macro2::TokenStream,
+    /// 未指定 `escape` 时按 `ext` 或 `path` 扩展名会进行 HTML 转义的参数
+    html_escaped: Option<Span>,
+    /// 交给 askama 查找的 `path` 模板的内容
+    path_source: Option<String>,
+}
+
+/// askama 使用 HTML 转义器的扩展名
+const HTML_EXTENSIONS: &[&str] = &[
+    "askama", "html", "htm", "j2", "jinja", "jinja2", "rinja", "svg", "xml",
+];
+
 /// 处理并增强 `#[template]` 属性，添加必要的默认值
-fn process_template_attr(input: &DeriveInput) -> Punctuated<Meta, Token![,]> {
-    let mut args = Punctuated::<Meta, Token![,]>::new();
+fn process_template_attr(input: &DeriveInput, krate: &Path) -> syn::Result<TemplateArgs> {
+    let mut args = TemplateArgs::default();
     for attr in &input.attrs {
         if !attr.path().is_ident("template") {
             continue;
         }
-        // 处理template属性
-        let mut has_askama = false;
-        let mut has_source = false;
-        let mut has_ext = false;
+        // 处理template属性，无法解析时报告出错的位置
+        let nested = attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+        add_template_args(&mut args, nested, krate)?;
+    }
+
+    Ok(args)
+}
+
+/// 名值属性的字符串值
+fn lit_str(meta: &Meta) -> syn::Result<&LitStr> {
+    match meta {
+        Meta::NameValue(syn::MetaNameValue {
+            value:
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(lit),
+                    ..
+                }),
+            ..
+        }) => Ok(lit),
+        _ => Err(syn::Error::new_spanned(meta, "expected `name = \"...\"`")),
+    }
+}
 
-        let nested = match attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated) {
-            Ok(n) => n,
-            Err(_) => continue,
+/// 读取 `source_env`/`source_file` 指定的模板内容，返回内容与追踪语句
+///
+/// `env!`/`include_str!` 让编译器在环境变量或文件变化时重新展开宏。
+fn resolve_external_source(
+    source_env: Option<&LitStr>,
+    source_file: Option<&LitStr>,
+    relative_to: Option<&LitStr>,
+) -> syn::Result<Option<(LitStr, proc_macro2::TokenStream)>> {
+    if let Some(var) = source_env {
+        let value = std::env::var(var.value()).map_err(|_| {
+            syn::Error::new(
+                var.span(),
+                format!("environment variable `{}` is not set", var.value()),
+            )
+        })?;
+        let tracked = quote! { const _: &str = ::std::env!(#var); };
+        return Ok(Some((LitStr::new(&value, var.span()), tracked)));
+    }
+    let Some(file) = source_file else {
+        return match relative_to {
+            Some(relative_to) => Err(syn::Error::new(
+                relative_to.span(),
+                "`relative_to` needs `source_file`",
+            )),
+            None => Ok(None),
         };
-        for meta in nested {
-            if meta.path().is_ident("source") {
-                has_source = true;
+    };
+    let base_var = match relative_to.map(LitStr::value).as_deref() {
+        None | Some("manifest") => "CARGO_MANIFEST_DIR",
+        Some("out_dir") => "OUT_DIR",
+        Some(_) => {
+            return Err(syn::Error::new(
+                relative_to.map_or_else(Span::call_site, LitStr::span),
+                "expected `relative_to = \"manifest\"` or `\"out_dir\"`",
+            ));
+        }
+    };
+    let base = std::env::var(base_var)
+        .map_err(|_| syn::Error::new(file.span(), format!("`{base_var}` is not set")))?;
+    let path = std::path::Path::new(&base).join(file.value());
+    let value = std::fs::read_to_string(&path).map_err(|e| {
+        syn::Error::new(
+            file.span(),
+            format!("failed to read `{}`: {e}", path.display()),
+        )
+    })?;
+    let path = LitStr::new(&path.to_string_lossy(), file.span());
+    let tracked = quote! { const _: &str = ::std::include_str!(#path); };
+    Ok(Some((LitStr::new(&value, file.span()), tracked)))
+}
+
+/// 展开 `source = include_str!("...")` 与 `source = concat!(...)`，返回内容
+///
+/// `concat!` 的参数可以是字面量、`include_str!` 与嵌套的 `concat!`；常量的值在宏展开时
+/// 无法读取，报错。`include_str!` 原样写入 `tracked`，文件变化时重新展开宏。
+fn expand_source_macro(
+    mac: &syn::Macro,
+    tracked: &mut proc_macro2::TokenStream,
+) -> syn::Result<String> {
+    if mac.path.is_ident("include_str") {
+        let file: LitStr = mac.parse_body()?;
+        // 与 `include_str!` 相同，相对于调用所在的源文件
+        let dir = file
+            .span()
+            .unwrap()
+            .local_file()
+            .and_then(|path| path.parent().map(std::path::Path::to_path_buf))
+            .ok_or_else(|| {
+                syn::Error::new(
+                    file.span(),
+                    "can not locate the file containing `include_str!`",
+                )
+            })?;
+        let path = dir.join(file.value());
+        let value = std::fs::read_to_string(&path).map_err(|e| {
+            syn::Error::new(
+                file.span(),
+                format!("failed to read `{}`: {e}", path.display()),
+            )
+        })?;
+        tracked.extend(quote! { const _: &str = ::std::#mac; });
+        return Ok(value);
+    }
+    if !mac.path.is_ident("concat") {
+        return Err(syn::Error::new_spanned(
+            &mac.path,
+            "expected a string literal, `include_str!(...)` or `concat!(...)`",
+        ));
+    }
+    let parts = mac.parse_body_with(Punctuated::<syn::Expr, Token![,]>::parse_terminated)?;
+    let mut value = String::new();
+    for part in parts {
+        match part {
+            syn::Expr::Lit(syn::ExprLit { lit, .. }) => match lit {
+                syn::Lit::Str(lit) => value.push_str(&lit.value()),
+                syn::Lit::Char(lit) => value.push(lit.value()),
+                syn::Lit::Int(lit) => value.push_str(lit.base10_digits()),
+                syn::Lit::Bool(lit) => value.push_str(&lit.value.to_string()),
+                lit => {
+                    return Err(syn::Error::new_spanned(
+                        lit,
+                        "unsupported literal in `concat!`",
+                    ));
+                }
+            },
+            syn::Expr::Macro(syn::ExprMacro { mac, .. }) => {
+                value.push_str(&expand_source_macro(&mac, tracked)?);
+            }
+            part => {
+                return Err(syn::Error::new_spanned(
+                    part,
+                    "the derive can not read constants, use literals, `include_str!(...)` or \
+                     a shared file with `{% include %}`",
+                ));
+            }
+        }
+    }
+    Ok(value)
+}
+
+/// `path` 模板的 SQL 目录与追踪语句：`root` 参数，否则为环境变量
+/// `SQLX_ASKAMA_TEMPLATE_ROOT`（可在 `.cargo/config.toml` 的 `[env]` 中设置）
+///
+/// 两者都没有时返回 `None`，由 askama 按其配置的目录查找。
+fn resolve_sql_root(root: Option<&LitStr>) -> Option<(String, proc_macro2::TokenStream)> {
+    if let Some(root) = root {
+        return Some((root.value(), quote! {}));
+    }
+    let root = std::env::var(SQL_ROOT_ENV).ok()?;
+    let tracked = quote! {
+        const _: ::std::option::Option<&str> = ::std::option_env!(#SQL_ROOT_ENV);
+    };
+    Some((root, tracked))
+}
+
+/// 所有 `path` 模板共用的 SQL 目录，相对于 crate 根目录
+const SQL_ROOT_ENV: &str = "SQLX_ASKAMA_TEMPLATE_ROOT";
+
+/// 添加一组模板参数，并补充必要的默认值
+///
+/// `source_env = "VAR"` 与 `source_file = "...", relative_to = "manifest" | "out_dir"`
+/// 在编译期读取为 `source`，`source = include_str!(...)`/`concat!(...)` 展开为字面量，设置了 SQL 目录的 `path` 同样如此。HTML 转义会破坏渲染出的 SQL，未指定 `escape` 时
+/// 补充 `escape = "none"`，显式指定 HTML 转义器则报错。
+fn add_template_args(
+    template: &mut TemplateArgs,
+    nested: impl IntoIterator<Item = Meta>,
+    krate: &Path,
+) -> syn::Result<()> {
+    let mut has_askama = false;
+    let mut has_source = false;
+    let mut has_ext = false;
+    let mut has_escape = false;
+    let mut sources = 0;
+    // 多余的模板来源，报错指向它
+    let mut extra_source = None;
+    let (mut source_env, mut source_file, mut relative_to) = (None, None, None);
+    let (mut path, mut root) = (None, None);
+    for mut meta in nested {
+        if let Meta::NameValue(name_value) = &mut meta
+            && name_value.path.is_ident("source")
+            && let syn::Expr::Macro(syn::ExprMacro { mac, .. }) = &name_value.value
+        {
+            let value = expand_source_macro(mac, &mut template.tracked)?;
+            let lit = LitStr::new(&value, mac.path.span());
+            name_value.value = syn::parse_quote! { #lit };
+        }
+        let args = &mut template.args;
+        if meta.path().is_ident("source_env") {
+            source_env = Some(lit_str(&meta)?.clone());
+            sources += 1;
+            extra_source = extra_source.or((sources > 1).then(|| meta.span()));
+            continue;
+        }
+        if meta.path().is_ident("source_file") {
+            source_file = Some(lit_str(&meta)?.clone());
+            sources += 1;
+            extra_source = extra_source.or((sources > 1).then(|| meta.span()));
+            continue;
+        }
+        if meta.path().is_ident("relative_to") {
+            relative_to = Some(lit_str(&meta)?.clone());
+            continue;
+        }
+        if meta.path().is_ident("root") {
+            root = Some(lit_str(&meta)?.clone());
+            continue;
+        }
+        if meta.path().is_ident("source") || meta.path().is_ident("path") {
+            sources += 1;
+            extra_source = extra_source.or((sources > 1).then(|| meta.span()));
+        }
+        if meta.path().is_ident("source") {
+            has_source = true;
+        }
+        if meta.path().is_ident("ext") {
+            has_ext = true;
+            let ext = lit_str(&meta)?;
+            if HTML_EXTENSIONS.contains(&ext.value().as_str()) {
+                template.html_escaped.get_or_insert(ext.span());
+            }
+        }
+        if meta.path().is_ident("path") {
+            let lit = lit_str(&meta)?;
+            let file = lit.value();
+            let ext = std::path::Path::new(&file).extension();
+            if ext.is_some_and(|ext| HTML_EXTENSIONS.iter().any(|html| ext == *html)) {
+                template.html_escaped.get_or_insert(lit.span());
+            }
+            path = Some((lit.clone(), meta));
+            continue;
+        }
+        if meta.path().is_ident("escape") {
+            has_escape = true;
+            let escape = lit_str(&meta)?;
+            if HTML_EXTENSIONS.contains(&escape.value().as_str()) {
+                return Err(syn::Error::new(
+                    escape.span(),
+                    "HTML escaping corrupts the rendered SQL, use `escape = \"none\"`",
+                ));
+            }
+        }
+        if meta.path().is_ident("askama") {
+            has_askama = true;
+        }
+        args.push(meta);
+    }
+    if let Some(span) = extra_source {
+        return Err(syn::Error::new(
+            span,
+            "use only one of `source`, `path`, `source_env` and `source_file`",
+        ));
+    }
+    if let Some((path, meta)) = path {
+        match resolve_sql_root(root.as_ref()) {
+            // 在 SQL 目录下查找，按 source_file 读取
+            Some((root, tracked)) => {
+                let file = std::path::Path::new(&root).join(path.value());
+                source_file = Some(LitStr::new(&file.to_string_lossy(), path.span()));
+                template.tracked.extend(tracked);
+            }
+            None => {
+                template.path_source = Some(path_template(&path, &template.args)?);
+                template.args.push(meta);
+            }
+        }
+    } else if let Some(root) = &root {
+        return Err(syn::Error::new(root.span(), "`root` needs `path`"));
+    }
+    if let Some((source, tracked)) = resolve_external_source(
+        source_env.as_ref(),
+        source_file.as_ref(),
+        relative_to.as_ref(),
+    )? {
+        template.args.push(syn::parse_quote! { source = #source });
+        template.tracked.extend(tracked);
+        has_source = true;
+    }
+    if has_escape {
+        template.html_escaped = None;
+    }
+    let args = &mut template.args;
+
+    // 设置默认值
+
+    if !has_askama {
+        let askama_meta = Meta::NameValue(syn::MetaNameValue {
+            path: syn::Path::from(syn::Ident::new("askama", Span::call_site())),
+            eq_token: <syn::Token![=]>::default(),
+            value: syn::Expr::Path(syn::ExprPath {
+                attrs: Vec::new(),
+                qself: None,
+                path: syn::parse_quote! { #krate::askama },
+            }),
+        });
+        args.push_punct(Token![,](Span::call_site()));
+        args.push_value(askama_meta);
+    }
+
+    if has_source && !has_ext {
+        // 添加 ext = "txt"
+        let ext_meta = Meta::NameValue(syn::MetaNameValue {
+            path: syn::Path::from(syn::Ident::new("ext", Span::call_site())),
+            eq_token: <syn::Token![=]>::default(),
+            value: syn::Expr::Lit(syn::ExprLit {
+                attrs: Vec::new(),
+                lit: syn::Lit::Str(LitStr::new("txt", Span::call_site())),
+            }),
+        });
+        args.push_punct(Token![,](Span::call_site()));
+        args.push_value(ext_meta);
+    }
+    if !has_escape {
+        args.push(syn::parse_quote! { escape = "none" });
+    }
+    Ok(())
+}
+
+/// 没有控制流的内联模板按顺序绑定的参数，无法静态确定时返回 `None`
+///
+/// 只接受 [`static_bind`] 的绑定调用和不含调用的插值，出现 `{% ... %}`、`el()` 等
+/// 其他调用或自定义语法时放弃；`lists` 中的列表字段按 `e_in()` 绑定，参数个数随
+/// 列表长度变化，同样放弃。
+fn static_params(template: &TemplateArgs, lists: &[String], prefix: &str) -> Option<Vec<String>> {
+    let mut source = None;
+    for meta in &template.args {
+        if meta.path().is_ident("syntax") || meta.path().is_ident("config") {
+            return None;
+        }
+        if meta.path().is_ident("source") {
+            source = Some(lit_str(meta).ok()?.value());
+        }
+    }
+    let source = source?;
+    let is_field = |expr: &str| {
+        !expr.is_empty()
+            && expr.split('.').all(|part| {
+                let part = part.trim();
+                part.starts_with(|c: char| c.is_alphabetic() || c == '_')
+                    && part.chars().all(|c| c.is_alphanumeric() || c == '_')
+            })
+    };
+    let mut params = Vec::new();
+    let mut rest = source.as_str();
+    while let Some(start) = rest.find('{') {
+        rest = &rest[start + 1..];
+        let end = match rest.chars().next() {
+            Some('%') => return None,
+            Some('#') => "#}",
+            Some('{') => "}}",
+            _ => continue,
+        };
+        let close = rest.find(end)?;
+        let expr = rest[1..close].trim_matches(|c: char| "-+~".contains(c) || c.is_whitespace());
+        rest = &rest[close + 2..];
+        if end == "#}" || !expr.contains('(') {
+            continue;
+        }
+        let arg = static_bind(expr, prefix)?.trim();
+        let root = arg.strip_prefix("self.").unwrap_or(arg);
+        if !is_field(arg) || lists.iter().any(|list| list == root) {
+            return None;
+        }
+        params.push(arg.replace(' ', ""));
+    }
+    Some(params)
+}
+
+/// 绑定单个值的调用的参数：`e(field)`、`e_named("label", field)` 及其 `_secret`
+/// 版本，可带 `helper_prefix` 前缀，也可由 `sql_cast(..., "::type")` 包裹
+///
+/// 包括 `#[param(secret)]` 与 `#[sql_cast]` 字段改写后的调用，见 [`mark_field_binds`]。
+fn static_bind<'a>(expr: &'a str, prefix: &str) -> Option<&'a str> {
+    let (name, args) = expr.trim().split_once('(')?;
+    let args = args.strip_suffix(')')?;
+    let name = name.trim();
+    let helper = match name {
+        "e" | "e_secret" | "e_named" | "e_named_secret" | "sql_cast" => name,
+        _ => name.strip_prefix(prefix).filter(|_| !prefix.is_empty())?,
+    };
+    let is_str = |lit: &str| {
+        let lit = lit.trim();
+        lit.len() >= 2 && lit.starts_with('"') && lit.ends_with('"')
+    };
+    match helper {
+        "e" | "e_secret" => Some(args),
+        "e_named" | "e_named_secret" => {
+            let comma = top_level(args, ',')?;
+            is_str(&args[..comma]).then(|| &args[comma + 1..])
+        }
+        "sql_cast" => {
+            let comma = top_level(args, ',')?;
+            is_str(&args[comma + 1..]).then_some(())?;
+            static_bind(&args[..comma], prefix)
+        }
+        _ => None,
+    }
+}
+
+/// 渲染出的 SQL 与字段值无关的模板只含绑定的源码：按顺序拼接各 `{{ ... }}` 绑定标签
+///
+/// 在 [`static_params`] 的基础上不允许不含调用的插值，其值会写入 SQL。
+fn static_binds(template: &TemplateArgs, lists: &[String], prefix: &str) -> Option<String> {
+    static_params(template, lists, prefix)?;
+    let source = template_source(template);
+    let mut binds = String::new();
+    let mut rest = source.as_str();
+    while let Some(start) = rest.find('{') {
+        rest = &rest[start..];
+        let end = match rest[1..].chars().next() {
+            Some('#') => "#}",
+            Some('{') => "}}",
+            _ => {
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        let close = rest.find(end)? + 2;
+        if end == "}}" {
+            if !rest[..close].contains('(') {
+                return None;
+            }
+            binds.push_str(&rest[..close]);
+        }
+        rest = &rest[close..];
+    }
+    Some(binds)
+}
+
+/// 检查模板是否以 `{{ field }}` 直接插入未标记 `#[raw]` 的字段
+///
+/// 表达式以字段开头（可带 `self.`、成员访问、方法调用或过滤器）即视为直接插值，
+/// 值未经绑定写入 SQL，有注入风险。`#[flatten]` 字段的内部字段无法在此判断，
+/// 自定义 `syntax` 或 `config` 时不检查。
+fn check_raw_interpolation(fields: &syn::Fields, template: &TemplateArgs) -> syn::Result<()> {
+    if template
+        .args
+        .iter()
+        .any(|meta| meta.path().is_ident("syntax") || meta.path().is_ident("config"))
+    {
+        return Ok(());
+    }
+    let checked: Vec<String> = fields
+        .iter()
+        .filter(|field| {
+            !has_flatten(field) && !field.attrs.iter().any(|attr| attr.path().is_ident("raw"))
+        })
+        .filter_map(|field| field.ident.as_ref().map(ToString::to_string))
+        .collect();
+    if checked.is_empty() {
+        return Ok(());
+    }
+    let span = template
+        .args
+        .iter()
+        .find(|meta| meta.path().is_ident("source"))
+        .and_then(|meta| lit_str(meta).ok())
+        .map_or_else(Span::call_site, LitStr::span);
+    let source = template_source(template);
+    let mut rest = source.as_str();
+    while let Some(start) = rest.find('{') {
+        rest = &rest[start + 1..];
+        let end = match rest.chars().next() {
+            Some('{') => "}}",
+            Some('#') => "#}",
+            Some('%') => {
+                // `{% raw %}` 中的内容原样输出
+                let Some(close) = rest.find("%}") else { break };
+                let tag = rest.get(1..close).unwrap_or_default();
+                let tag = tag.trim_matches(|c: char| "-+~".contains(c) || c.is_whitespace());
+                rest = &rest[close + 2..];
+                if tag == "raw" {
+                    let Some(end) = rest.find("endraw") else {
+                        break;
+                    };
+                    rest = &rest[end..];
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        let Some(close) = rest.find(end) else { break };
+        let expr = rest.get(1..close).unwrap_or_default();
+        let expr = expr.trim_matches(|c: char| "-+~".contains(c) || c.is_whitespace());
+        rest = &rest[close + 2..];
+        if end == "#}" {
+            continue;
+        }
+        let expr = expr.strip_prefix("self.").unwrap_or(expr);
+        let root_len = expr
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(expr.len());
+        let (root, after) = expr.split_at(root_len);
+        if after.trim_start().starts_with('(') || !checked.iter().any(|field| field == root) {
+            continue;
+        }
+        return Err(syn::Error::new(
+            span,
+            format!(
+                "`{{{{ {expr} }}}}` writes field `{root}` into the SQL without binding it; \
+                 use `{{{{ e({root}) }}}}`, or mark the field `#[raw]` if it is interpolated on purpose"
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// `#[sql_template(strict)]`：模板中从未出现的字段（`#[ignore_type]` 除外）报错
+///
+/// 字段在任一模板的 `{{ }}` 或 `{% %}` 中出现即视为使用；自定义 `syntax` 或
+/// `config` 时无法区分标签与文本，改为查找整个源码。模板 `include`、`extends`
+/// 或 `import` 其他模板时，字段可能在其中使用，不做检查。
+fn check_unused_fields(fields: &syn::Fields, templates: &[&TemplateArgs]) -> syn::Result<()> {
+    let mut used = BTreeSet::new();
+    for template in templates {
+        let source = template_source(template);
+        let custom = template
+            .args
+            .iter()
+            .any(|meta| meta.path().is_ident("syntax") || meta.path().is_ident("config"));
+        let words = match custom {
+            true => identifiers(&source),
+            false => tag_identifiers(&source),
+        };
+        if ["include", "extends", "import"]
+            .iter()
+            .any(|tag| words.contains(*tag))
+        {
+            return Ok(());
+        }
+        used.extend(words);
+    }
+    let unused = fields
+        .iter()
+        .filter(|field| {
+            !field
+                .attrs
+                .iter()
+                .any(|attr| attr.path().is_ident("ignore_type"))
+        })
+        .filter_map(|field| field.ident.as_ref())
+        .filter(|ident| !used.contains(&ident.to_string()));
+    let mut errors = None::<syn::Error>;
+    for ident in unused {
+        let error = syn::Error::new_spanned(
+            ident,
+            format!(
+                "field `{ident}` is never used in the template; remove it, \
+                 or mark it `#[ignore_type]` if it is only read from Rust"
+            ),
+        );
+        match &mut errors {
+            Some(errors) => errors.combine(error),
+            None => errors = Some(error),
+        }
+    }
+    errors.map_or(Ok(()), Err)
+}
+
+/// 源码中的所有标识符
+fn identifiers(source: &str) -> BTreeSet<String> {
+    source
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| word.starts_with(|c: char| c.is_alphabetic() || c == '_'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// `{{ }}` 与 `{% %}` 标签中的标识符，跳过 `{# #}` 注释与 `{% raw %}` 中的内容
+fn tag_identifiers(source: &str) -> BTreeSet<String> {
+    let mut words = BTreeSet::new();
+    let mut rest = source;
+    while let Some(start) = rest.find('{') {
+        rest = &rest[start + 1..];
+        let end = match rest.chars().next() {
+            Some('{') => "}}",
+            Some('%') => "%}",
+            Some('#') => "#}",
+            _ => continue,
+        };
+        let Some(close) = rest.find(end) else { break };
+        let tag = rest.get(1..close).unwrap_or_default();
+        rest = &rest[close + 2..];
+        if end == "#}" {
+            continue;
+        }
+        let tag_words = identifiers(tag);
+        let trimmed = tag.trim_matches(|c: char| "-+~".contains(c) || c.is_whitespace());
+        if end == "%}" && trimmed == "raw" {
+            let Some(end) = rest.find("endraw") else {
+                break;
+            };
+            rest = &rest[end..];
+        }
+        words.extend(tag_words);
+    }
+    words
+}
+
+/// 开启 `validate-sql` feature 时检查模板的 SQL 语法
+///
+/// 模板在宏展开时无法渲染，只检查 [`sql_skeleton`] 得到的近似 SQL：
+/// 条件分支全部取第一个或全部取最后一个，两者都解析失败才报错。`lists` 中列表
+/// 字段的 `e(field)` 按改写后的 `e_in(field)` 检查。
+fn check_sql_syntax(
+    template: &TemplateArgs,
+    options: &SqlTemplateOptions,
+    lists: &[String],
+) -> syn::Result<()> {
+    let custom = template
+        .args
+        .iter()
+        .any(|meta| meta.path().is_ident("syntax") || meta.path().is_ident("config"));
+    if options.unvalidated || custom {
+        return Ok(());
+    }
+    #[cfg(feature = "validate-sql")]
+    validate_sql(template, &options.db, &options.helper_prefix, lists)?;
+    #[cfg(not(feature = "validate-sql"))]
+    let _ = lists;
+    Ok(())
+}
+
+#[cfg(feature = "validate-sql")]
+fn validate_sql(
+    template: &TemplateArgs,
+    db: &[proc_macro2::TokenStream],
+    prefix: &str,
+    lists: &[String],
+) -> syn::Result<()> {
+    let marks: Vec<BindMark> = lists
+        .iter()
+        .map(|name| BindMark {
+            name: name.clone(),
+            secret: false,
+            cast: None,
+            list: true,
+            secret_attr: quote! {},
+            cast_attr: quote! {},
+        })
+        .collect();
+    let source = rewrite_field_binds(&template_source(template), &marks, prefix);
+    let span = template
+        .args
+        .iter()
+        .filter(|meta| meta.path().is_ident("source") || meta.path().is_ident("path"))
+        .find_map(|meta| lit_str(meta).ok())
+        .map_or_else(Span::call_site, LitStr::span);
+    let mut dialects: Vec<&str> = db
+        .iter()
+        .map(|db| match db.to_string().rsplit(' ').next() {
+            Some("Postgres") => "postgres",
+            Some("MySql") => "mysql",
+            Some("Sqlite") => "sqlite",
+            _ => "generic",
+        })
+        .collect();
+    if dialects.is_empty() {
+        dialects.push("generic");
+    }
+    for name in dialects {
+        let Some(dialect) = sqlparser::dialect::dialect_from_str(name) else {
+            continue;
+        };
+        let numbered = name == "postgres";
+        let (Some(first), Some(last)) = (
+            sql_skeleton(&source, prefix, numbered, true),
+            sql_skeleton(&source, prefix, numbered, false),
+        ) else {
+            return Ok(());
+        };
+        let parse = |sql: &str| sqlparser::parser::Parser::parse_sql(dialect.as_ref(), sql);
+        if let (Err(e), Err(_)) = (parse(&first), parse(&last)) {
+            return Err(syn::Error::new(
+                span,
+                format!(
+                    "SQL syntax error ({name}): {e}; \
+                     `#[sql_template(validate_sql = false)]` skips this check"
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 去掉模板语法后的近似 SQL，含 `include`、`macro` 等无法展开的标签时返回 `None`
+///
+/// `e()` 等（可带 `helper_prefix` 前缀 `prefix`）写为占位符，`el()` 写为 `(?)`，其余表达式写为标识符；循环体保留一次，
+/// `if` 只保留第一个（`first_branches`）或最后一个分支，`match` 只保留第一个 `when`，
+/// `{% block count %}` 不计入。
+#[cfg(feature = "validate-sql")]
+fn sql_skeleton(
+    source: &str,
+    prefix: &str,
+    numbered: bool,
+    first_branches: bool,
+) -> Option<String> {
+    // 每层块是否输出，`match` 另记是否已有 `when`
+    let mut frames: Vec<(bool, bool)> = Vec::new();
+    let mut sql = String::new();
+    let mut params = 0;
+    let mut rest = source;
+    loop {
+        let active = frames.iter().all(|(keep, _)| *keep);
+        let Some(start) = rest.find('{') else {
+            if active {
+                sql.push_str(rest);
+            }
+            break;
+        };
+        if active {
+            sql.push_str(&rest[..start]);
+        }
+        rest = &rest[start + 1..];
+        let end = match rest.chars().next() {
+            Some('{') => "}}",
+            Some('%') => "%}",
+            Some('#') => "#}",
+            _ => {
+                // `{schema}` 在执行时由 `with_schema` 替换为带引号的 schema 名
+                if let Some(after) = rest.strip_prefix("schema}") {
+                    if active {
+                        sql.push_str("sql_schema");
+                    }
+                    rest = after;
+                    continue;
+                }
+                if active {
+                    sql.push('{');
+                }
+                continue;
+            }
+        };
+        let close = rest.find(end)?;
+        let tag = rest[1..close].trim_matches(|c: char| "-+~".contains(c) || c.is_whitespace());
+        rest = &rest[close + 2..];
+        if end == "#}" {
+            continue;
+        }
+        if end == "}}" {
+            if active {
+                let mut placeholder = || {
+                    params += 1;
+                    match numbered {
+                        true => format!("${params}"),
+                        false => "?".to_string(),
+                    }
+                };
+                let helper = tag
+                    .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .next()
+                    .unwrap_or_default();
+                let helper = helper.strip_prefix(prefix).unwrap_or(helper);
+                let list = tag.contains("el(") || tag.contains("el_secret(");
+                match helper {
+                    "e" | "e_named" | "e_secret" | "e_named_secret" => sql.push_str(&placeholder()),
+                    "el" | "el_secret" => sql.push_str(&format!("({})", placeholder())),
+                    "e_in" => sql.push_str(&format!("IN ({})", placeholder())),
+                    "sql_cast" if list => sql.push_str(&format!("({})", placeholder())),
+                    "sql_cast" => sql.push_str(&placeholder()),
+                    "sql_include" | "include_fragment" => return None,
+                    _ => sql.push_str("sql_value"),
+                }
+            }
+            continue;
+        }
+        let mut words = tag.split_whitespace();
+        match words.next().unwrap_or_default() {
+            "if" => frames.push((first_branches, false)),
+            "elif" => frames.last_mut()?.0 = false,
+            "else" if words.next() == Some("if") => frames.last_mut()?.0 = false,
+            "else" => {
+                let frame = frames.last_mut()?;
+                // `if` 的 else 分支在只保留最后一个分支时输出，`for` 的 else 不输出
+                frame.0 = !first_branches && !frame.1;
+            }
+            "for" => frames.push((true, true)),
+            "match" => frames.push((false, false)),
+            "when" => {
+                let frame = frames.last_mut()?;
+                frame.0 = !frame.1;
+                frame.1 = true;
+            }
+            "block" => frames.push((words.next() != Some("count"), false)),
+            "filter" => frames.push((true, false)),
+            "endif" | "endfor" | "endmatch" | "endblock" | "endfilter" => {
+                frames.pop()?;
+            }
+            "raw" => {
+                let endraw = rest.find("endraw")?;
+                let content = &rest[..endraw];
+                if active {
+                    sql.push_str(&content[..content.rfind('{')?]);
+                }
+                rest = &rest[endraw..];
+                rest = &rest[rest.find("%}")? + 2..];
+            }
+            "include" | "extends" | "import" | "macro" | "call" => return None,
+            _ => {}
+        }
+    }
+    Some(sql)
+}
+
+/// 模板中绑定时需要改写的字段：`#[param(secret)]`、`#[sql_cast("...")]` 与列表字段
+struct BindMark {
+    name: String,
+    secret: bool,
+    cast: Option<String>,
+    /// 列表字段（见 [`list_element`]）的 `e(field)` 改写为 `e_in(field)`
+    list: bool,
+    /// `#[param(secret)]` 与 `#[sql_cast]` 属性，用于报错
+    secret_attr: proc_macro2::TokenStream,
+    cast_attr: proc_macro2::TokenStream,
+}
+
+/// 结构体中需要改写绑定的字段
+fn bind_marks(input: &DeriveInput) -> Vec<BindMark> {
+    let syn::Data::Struct(data) = &input.data else {
+        return Vec::new();
+    };
+    data.fields
+        .iter()
+        .filter_map(|field| {
+            let attr_tokens = |name: &str| {
+                let attr = field.attrs.iter().find(|a| a.path().is_ident(name));
+                attr.map_or_else(|| quote! { #field }, |attr| quote! { #attr })
+            };
+            let secret = is_secret(field).unwrap_or(false);
+            let cast = parse_sql_cast(field).ok().flatten();
+            let mark = BindMark {
+                name: field.ident.as_ref()?.to_string(),
+                list: !secret && cast.is_none() && list_field(field).is_some(),
+                secret,
+                cast,
+                secret_attr: attr_tokens("param"),
+                cast_attr: attr_tokens("sql_cast"),
+            };
+            (mark.secret || mark.cast.is_some() || mark.list).then_some(mark)
+        })
+        .collect()
+}
+
+/// 改写绑定 `marks` 字段的 `e()`、`e_named()` 与 `el()` 调用
+///
+/// 机密字段改用对应的 `*_secret` 调用，带类型转换的字段外层包上
+/// `sql_cast(..., "::uuid")`。只改写 `source`（包括编译期读取为 `source` 的模板）
+/// 中的 `{{ }}` 与 `{% %}` 标签，参数以字段开头时才改写。带 `prefix` 的调用
+/// 改写为同样带前缀的辅助函数。
+///
+/// 源码无法改写时（`path` 模板、自定义 `syntax` 或 `config`）报错，指向字段上的
+/// 属性，以免机密值被当作普通参数绑定、类型转换被遗漏。
+fn mark_field_binds(
+    attrs: &Punctuated<Meta, Token![,]>,
+    marks: &[BindMark],
+    prefix: &str,
+) -> syn::Result<Punctuated<Meta, Token![,]>> {
+    if marks.is_empty() {
+        return Ok(attrs.clone());
+    }
+    if !rewritable(attrs) {
+        check_unrewritable_marks(
+            marks,
+            "a `path` template not read at compile time through a SQL `root`, \
+             or a template with a custom `syntax`/`config`",
+        )?;
+        return Ok(attrs.clone());
+    }
+    Ok(attrs
+        .iter()
+        .map(|meta| match lit_str(meta) {
+            Ok(lit) if meta.path().is_ident("source") => {
+                let source = LitStr::new(
+                    &rewrite_field_binds(&lit.value(), marks, prefix),
+                    lit.span(),
+                );
+                syn::parse_quote! { source = #source }
+            }
+            _ => meta.clone(),
+        })
+        .collect())
+}
+
+/// 模板源码能否改写：`source`（包括编译期读取为 `source` 的模板），且没有自定义
+/// `syntax` 或 `config`
+fn rewritable(attrs: &Punctuated<Meta, Token![,]>) -> bool {
+    attrs.iter().any(|meta| meta.path().is_ident("source"))
+        && !attrs
+            .iter()
+            .any(|meta| meta.path().is_ident("syntax") || meta.path().is_ident("config"))
+}
+
+/// 按 `e_in()` 绑定的列表字段的元素类型：`Vec<T>`、`&[T]`、`[T; N]` 与 `&Vec<T>`
+///
+/// 带 `bind_as`、`bind_with`、`#[flatten]` 或 `#[fragment]` 的字段不是列表；`u8`
+/// 列表是字节串，元素类型含引用或生命周期时无法登记数组类型，同样不算列表。
+fn list_field(field: &syn::Field) -> Option<&syn::Type> {
+    let plain = ["bind_as", "bind_with", "flatten", "fragment"];
+    if field
+        .attrs
+        .iter()
+        .any(|attr| plain.iter().any(|name| attr.path().is_ident(name)))
+    {
+        return None;
+    }
+    list_element(&field.ty)
+}
+
+/// 列表类型的元素类型，见 [`list_field`]
+fn list_element(ty: &syn::Type) -> Option<&syn::Type> {
+    let elem = match ty {
+        syn::Type::Reference(reference) => match &*reference.elem {
+            syn::Type::Slice(slice) => &*slice.elem,
+            syn::Type::Reference(_) => return None,
+            other => return list_element(other),
+        },
+        syn::Type::Array(array) => &*array.elem,
+        syn::Type::Path(path) if path.qself.is_none() => {
+            let last = path.path.segments.last()?;
+            let syn::PathArguments::AngleBracketed(args) = &last.arguments else {
+                return None;
+            };
+            match (last.ident == "Vec", args.args.first(), args.args.len()) {
+                (true, Some(syn::GenericArgument::Type(elem)), 1) => elem,
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    let borrowed = elem.to_token_stream().into_iter().any(|token| {
+        matches!(token, proc_macro2::TokenTree::Punct(p) if p.as_char() == '&' || p.as_char() == '\'')
+    });
+    let bytes = matches!(elem, syn::Type::Path(path) if path.path.is_ident("u8"));
+    (!borrowed && !bytes).then_some(elem)
+}
+
+/// 无法改写 `template` 的绑定调用时，对每个需要改写的字段属性报错
+fn check_unrewritable_marks(marks: &[BindMark], template: &str) -> syn::Result<()> {
+    let secret = marks.iter().filter(|mark| mark.secret).map(|mark| {
+        syn::Error::new_spanned(
+            &mark.secret_attr,
+            format!(
+                "`#[param(secret)]` can not rewrite the binds of {template}; \
+                 mark the field `#[sensitive]` and call `e_secret()` in the template instead"
+            ),
+        )
+    });
+    let cast = marks.iter().filter(|mark| mark.cast.is_some()).map(|mark| {
+        syn::Error::new_spanned(
+            &mark.cast_attr,
+            format!(
+                "`#[sql_cast]` can not rewrite the binds of {template}; \
+                 remove it and call `sql_cast()` in the template instead"
+            ),
+        )
+    });
+    let errors = secret.chain(cast);
+    match errors.reduce(|mut all, e| {
+        all.combine(e);
+        all
+    }) {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// 改写源码中各标签内的绑定调用，见 [`mark_field_binds`]
+fn rewrite_field_binds(source: &str, marks: &[BindMark], prefix: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..=start]);
+        rest = &rest[start + 1..];
+        let end = match rest.chars().next() {
+            Some('{') => "}}",
+            Some('%') => "%}",
+            Some('#') => "#}",
+            _ => continue,
+        };
+        let Some(close) = rest.find(end) else { break };
+        let tag = &rest[..close];
+        rest = &rest[close..];
+        if end == "#}" {
+            out.push_str(tag);
+            continue;
+        }
+        out.push_str(&rewrite_bind_tag(tag, marks, prefix));
+        let trimmed = tag[1..].trim_matches(|c: char| "-+~".contains(c) || c.is_whitespace());
+        if end == "%}" && trimmed == "raw" {
+            // `{% raw %}` 中的内容原样输出
+            let Some(raw_end) = rest.find("endraw") else {
+                break;
+            };
+            out.push_str(&rest[..raw_end]);
+            rest = &rest[raw_end..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// 改写一个标签内的 `e(field)`、`e_named("label", field)` 与 `el(field)`
+///
+/// 列表字段只改写 `e(field)`，其他调用保持不变。
+fn rewrite_bind_tag(tag: &str, marks: &[BindMark], prefix: &str) -> String {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let mut out = String::with_capacity(tag.len());
+    // 在 tag 的某个位置之后补上的 `sql_cast` 结尾
+    let mut suffixes: Vec<(usize, String)> = Vec::new();
+    let mut in_string = false;
+    let mut prev = ' ';
+    let mut chars = tag.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let skip = in_string
+            || c == '"'
+            || !is_ident(c)
+            || is_ident(prev)
+            || prev == '.' && !out.ends_with("self.");
+        if skip {
+            if in_string || c == '"' {
+                in_string ^= c == '"' && (!in_string || prev != '\\');
+            }
+            out.push(c);
+            prev = c;
+            for (_, suffix) in suffixes.extract_if(.., |(at, _)| *at == i) {
+                out.push_str(&suffix);
+            }
+            continue;
+        }
+        let len = tag[i..]
+            .find(|c: char| !is_ident(c))
+            .unwrap_or(tag.len() - i);
+        let word = &tag[i..i + len];
+        while chars.next_if(|&(j, _)| j < i + len).is_some() {}
+        prev = word.chars().next_back().unwrap_or(c);
+        let rest = &tag[i + len..];
+        let open = rest.len() - rest.trim_start().len();
+        let args = rest.trim_start().strip_prefix('(');
+        // `helper_prefix` 生成的 `sql_e()` 等同样改写，保留调用所用的前缀
+        let (helper_prefix, helper) = word
+            .strip_prefix(prefix)
+            .map_or(("", word), |helper| (prefix, helper));
+        let arg = match (helper, args) {
+            ("e" | "el", Some(args)) => Some(args),
+            ("e_named", Some(args)) => top_level(args, ',').map(|comma| &args[comma + 1..]),
+            _ => None,
+        };
+        let mark = arg.and_then(|arg| {
+            let arg = arg.trim_start().trim_start_matches(['&', ' ']);
+            let arg = arg.strip_prefix("self.").unwrap_or(arg);
+            let root = &arg[..arg.find(|c: char| !is_ident(c)).unwrap_or(arg.len())];
+            marks.iter().find(|mark| mark.name == root)
+        });
+        let Some(mark) = mark else {
+            out.push_str(word);
+            continue;
+        };
+        if mark.list {
+            // 只改写以整个字段为参数的 `e(field)`
+            let whole = arg.is_some_and(|arg| {
+                let arg = arg.trim_start().trim_start_matches(['&', ' ']);
+                let arg = arg.strip_prefix("self.").unwrap_or(arg);
+                arg[mark.name.len()..].trim_start().starts_with(')')
+            });
+            out.push_str(helper_prefix);
+            out.push_str(if helper == "e" && whole {
+                "e_in"
+            } else {
+                helper
+            });
+            continue;
+        }
+        let close = args
+            .and_then(|args| top_level(args, ')'))
+            .map(|close| i + len + open + 1 + close);
+        if let (Some(cast), Some(close)) = (&mark.cast, close) {
+            out.push_str(helper_prefix);
+            out.push_str("sql_cast(");
+            suffixes.push((close, format!(", \"{cast}\")")));
+        }
+        out.push_str(word);
+        if mark.secret {
+            out.push_str("_secret");
+        }
+    }
+    out
+}
+
+/// 参数列表中第一个顶层 `target`（`,` 或 `)`）的位置，跳过括号与字符串中的内容
+///
+/// 参数列表在找到 `,` 之前结束时返回 `None`。
+fn top_level(args: &str, target: char) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut prev = ' ';
+    for (i, c) in args.char_indices() {
+        match c {
+            '"' if !in_string || prev != '\\' => in_string = !in_string,
+            _ if in_string => {}
+            ')' if depth == 0 && target == ')' => return Some(i),
+            ',' if depth == 0 && target == ',' => return Some(i),
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' if depth == 0 => return None,
+            ')' | ']' | '}' => depth -= 1,
+            _ => {}
+        }
+        prev = c;
+    }
+    None
+}
+
+/// 解析字段上的 `#[sql_cast("::uuid")]`
+fn parse_sql_cast(field: &syn::Field) -> syn::Result<Option<String>> {
+    let Some(attr) = field.attrs.iter().find(|a| a.path().is_ident("sql_cast")) else {
+        return Ok(None);
+    };
+    let lit: LitStr = attr.parse_args()?;
+    let cast = lit.value();
+    // 转换原样写入 SQL 与模板字符串，只允许类型名所需的字符
+    let allowed = |c: char| c.is_alphanumeric() || " _:.,()[]".contains(c);
+    if cast.trim().is_empty() || !cast.chars().all(allowed) {
+        return Err(syn::Error::new(
+            lit.span(),
+            "expected a cast such as `\"::uuid\"` or `\"::varchar(32)[]\"`",
+        ));
+    }
+    Ok(Some(cast))
+}
+
+/// 带 `{% block count %}` 的模板分成主查询与计数查询的模板参数
+///
+/// 主查询使用去掉该块的源码，计数查询使用原源码并只渲染该块（askama 的
+/// `block` 参数）。只检查 `source`（包括编译期读取为 `source` 的模板），
+/// 自定义 `syntax` 或 `config` 时不检查。
+fn count_block_args(
+    template: &TemplateArgs,
+) -> Option<(Punctuated<Meta, Token![,]>, Punctuated<Meta, Token![,]>)> {
+    if template
+        .args
+        .iter()
+        .any(|meta| meta.path().is_ident("syntax") || meta.path().is_ident("config"))
+    {
+        return None;
+    }
+    let source = template
+        .args
+        .iter()
+        .find(|meta| meta.path().is_ident("source"))?;
+    let lit = lit_str(source).ok()?;
+    let main_source = LitStr::new(&strip_count_block(&lit.value())?, lit.span());
+    let main = template
+        .args
+        .iter()
+        .map(|meta| match meta.path().is_ident("source") {
+            true => syn::parse_quote! { source = #main_source },
+            false => meta.clone(),
+        })
+        .collect();
+    let mut count = template.args.clone();
+    count.push(syn::parse_quote! { block = "count" });
+    Some((main, count))
+}
+
+/// 去掉 `{% block count %} ... {% endblock %}` 后的源码，没有该块时返回 `None`
+fn strip_count_block(source: &str) -> Option<String> {
+    let mut depth = 0;
+    let mut start = None;
+    let mut pos = 0;
+    while let Some(open) = source[pos..].find("{%") {
+        let open = pos + open;
+        let close = open + source[open..].find("%}")?;
+        pos = close + 2;
+        let tag =
+            source[open + 2..close].trim_matches(|c: char| "-+~".contains(c) || c.is_whitespace());
+        let mut words = tag.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("block"), Some("count")) if start.is_none() => {
+                start = Some(open);
+                depth = 1;
+            }
+            (Some("block"), _) if start.is_some() => depth += 1,
+            (Some("endblock"), _) if start.is_some() => {
+                depth -= 1;
+                if depth == 0 {
+                    // `{%-` 与 `-%}` 的空白裁剪在去掉该块后仍然生效
+                    let start = start?;
+                    let mut before = &source[..start];
+                    if source[start + 2..].starts_with('-') {
+                        before = before.trim_end();
+                    }
+                    let mut after = &source[pos..];
+                    if source[..close].ends_with('-') {
+                        after = after.trim_start();
+                    }
+                    return Some(format!("{before}{after}"));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// 模板源码的 `SQL_TEMPLATE_SOURCE` 常量与 `template_source()`，以及静态模板的
+/// `PARAM_COUNT` 与 `PARAM_NAMES` 常量，`lists` 与 `prefix` 见 [`static_params`]
+fn param_consts(
+    template: &TemplateArgs,
+    lists: &[String],
+    prefix: &str,
+) -> proc_macro2::TokenStream {
+    let source = template_source(template);
+    let mut consts = quote! {
+        /// Source of the SQL template: the inline `source`, or the text of the
+        /// `path` template
+        pub const SQL_TEMPLATE_SOURCE: &'static str = #source;
+        /// Returns [`Self::SQL_TEMPLATE_SOURCE`], e.g. for query registries
+        pub fn template_source() -> &'static str {
+            Self::SQL_TEMPLATE_SOURCE
+        }
+    };
+    let Some(params) = static_params(template, lists, prefix) else {
+        return consts;
+    };
+    let count = params.len();
+    consts.extend(quote! {
+        /// Number of bind parameters of the query, which has no control flow
+        pub const PARAM_COUNT: usize = #count;
+        /// Fields bound by the query, in placeholder order
+        pub const PARAM_NAMES: &'static [&'static str] = &[#(#params),*];
+    });
+    consts
+}
+
+/// 模板中以 `e(field)` 绑定、改写为 `e_in(field)` 的列表字段名
+///
+/// 任一模板无法改写时不改写列表字段，字段按原类型绑定。
+fn list_binds(input: &DeriveInput, templates: &[&TemplateArgs], prefix: &str) -> Vec<String> {
+    if !templates.iter().all(|template| rewritable(&template.args)) {
+        return Vec::new();
+    }
+    let sources: Vec<String> = templates.iter().map(|t| template_source(t)).collect();
+    bind_marks(input)
+        .into_iter()
+        .filter(|mark| mark.list)
+        .filter(|mark| {
+            let marks = std::slice::from_ref(mark);
+            sources
+                .iter()
+                .any(|source| rewrite_field_binds(source, marks, prefix) != *source)
+        })
+        .map(|mark| mark.name)
+        .collect()
+}
+
+/// 模板源码：`source` 参数，或 `path` 模板的内容
+fn template_source(template: &TemplateArgs) -> String {
+    for meta in &template.args {
+        let Ok(lit) = lit_str(meta) else {
+            continue;
+        };
+        if meta.path().is_ident("source") {
+            return lit.value();
+        }
+    }
+    template.path_source.clone().unwrap_or_default()
+}
+
+/// 按 askama 的查找方式读取 `path` 模板：依次在配置文件（`config` 参数，
+/// 默认为 `askama.toml`）`[general]` 中 `dirs` 列出的目录下查找，未配置时为
+/// `templates/`，目录均相对于 crate 根目录
+///
+/// 找不到文件时报错，而不是退回到路径本身，以免 `SQL_TEMPLATE_SOURCE` 与指纹
+/// 悄悄失效。
+fn path_template(path: &LitStr, args: &Punctuated<Meta, Token![,]>) -> syn::Result<String> {
+    let base = std::path::PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default());
+    let config = args
+        .iter()
+        .find(|meta| meta.path().is_ident("config"))
+        .map(lit_str)
+        .transpose()?
+        .map_or_else(|| "askama.toml".to_string(), LitStr::value);
+    let dirs = std::fs::read_to_string(base.join(config))
+        .ok()
+        .and_then(|config| askama_dirs(&config))
+        .unwrap_or_else(|| vec!["templates".to_string()]);
+    for dir in &dirs {
+        if let Ok(source) = std::fs::read_to_string(base.join(dir).join(path.value())) {
+            return Ok(source);
+        }
+    }
+    Err(syn::Error::new(
+        path.span(),
+        format!(
+            "template `{}` not found in {}; set `dirs` in the askama config, or read it \
+             through a SQL `root`",
+            path.value(),
+            dirs.iter()
+                .map(|dir| format!("`{dir}`"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    ))
+}
+
+/// askama 配置中 `[general]` 的 `dirs` 列表，未设置时为 `None`
+///
+/// 只解析所需的子集：字符串数组，可跨行，支持 `#` 注释。
+fn askama_dirs(config: &str) -> Option<Vec<String>> {
+    let mut section = "";
+    let mut lines = config.lines().map(strip_toml_comment);
+    while let Some(line) = lines.next() {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix('[') {
+            section = header.trim_end_matches(']').trim();
+            continue;
+        }
+        let Some(value) = line
+            .strip_prefix("dirs")
+            .and_then(|rest| rest.trim_start().strip_prefix('='))
+        else {
+            continue;
+        };
+        if section != "general" {
+            continue;
+        }
+        let mut value = value.to_string();
+        while !value.contains(']') {
+            let Some(next) = lines.next() else {
+                break;
+            };
+            value.push_str(next);
+        }
+        return Some(toml_strings(&value));
+    }
+    None
+}
+
+/// 去掉字符串之外 `#` 开始的注释
+fn strip_toml_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &line[..i],
+            _ => {}
+        }
+        escaped = false;
+    }
+    line
+}
+
+/// 数组中的基本字符串与字面字符串
+fn toml_strings(value: &str) -> Vec<String> {
+    let mut strings = Vec::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '"' && c != '\'' {
+            continue;
+        }
+        let mut string = String::new();
+        while let Some(next) = chars.next() {
+            match next {
+                '\\' if c == '"' => string.extend(chars.next()),
+                next if next == c => break,
+                next => string.push(next),
+            }
+        }
+        strings.push(string);
+    }
+    strings
+}
+
+/// 模板源码的稳定指纹，与运行时的 `fingerprint` 同为 64 位 FNV-1a
+///
+/// 对补充默认值后的 `#[template]` 参数求哈希；`path` 模板另加上文件内容。
+fn template_fingerprint(template: &TemplateArgs) -> u64 {
+    let mut text = String::new();
+    for meta in &template.args {
+        text.push_str(&quote!(#meta).to_string());
+        text.push('\n');
+    }
+    if let Some(source) = &template.path_source {
+        text.push_str(source);
+    }
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// `#[sql_template(...)]` 属性选项
+#[derive(Default)]
+struct SqlTemplateOptions {
+    /// 直接使用结构体自身的 askama `Template` 实现，不生成 Wrapper
+    askama_template: bool,
+    /// 生成实现所针对的数据库类型，为空时对 `DB` 泛型实现
+    db: Vec<proc_macro2::TokenStream>,
+    /// 默认是否缓存为预编译语句
+    persistent: Option<bool>,
+    /// 允许运行时排序的列
+    order_by: Option<Vec<String>>,
+    /// 默认分页大小
+    default_page_size: Option<i64>,
+    /// `crate = "..."` 指定的本库路径，用于经由门面 crate 重新导出或重命名依赖的情况
+    krate: Option<Path>,
+    /// 生成 `builder()` 与构建器类型
+    builder: bool,
+    /// 模板中未使用的字段报错
+    strict: bool,
+    /// `registry = false`：不加入 `registry()` 的模板清单
+    unregistered: bool,
+    /// `validate_sql = false`：开启 `validate-sql` feature 时也不检查 SQL 语法
+    unvalidated: bool,
+    /// 生成以 `:1`、`:2` 标记参数显示 SQL 的 `Display`，可指定渲染所用的数据库
+    display: Option<Option<proc_macro2::TokenStream>>,
+    /// `render()` 固定使用的占位符风格，如 `Dollar`
+    placeholder: Option<syn::Ident>,
+    /// `helper_prefix = "sql_"`：另外生成 `sql_e`、`sql_el` 等加前缀的辅助函数
+    helper_prefix: String,
+}
+
+impl SqlTemplateOptions {
+    /// 生成代码中引用本库的路径，默认为 `::sqlx_askama_template`
+    fn krate(&self) -> Path {
+        self.krate
+            .clone()
+            .unwrap_or_else(|| syn::parse_quote! { ::sqlx_askama_template })
+    }
+    /// `SqlTemplate` 实现中覆盖默认值的关联项，`tag` 为模块路径之后的语句名
+    fn overrides(&self, tag: &str, template: &TemplateArgs) -> proc_macro2::TokenStream {
+        let persistent = self
+            .persistent
+            .map(|persistent| quote! { const PERSISTENT: bool = #persistent; });
+        let sort_columns = self.order_by.as_ref().map(
+            |columns| quote! { const SORT_COLUMNS: &'static [&'static str] = &[#(#columns),*]; },
+        );
+        let placeholder = self.placeholder.as_ref().map(|style| {
+            let krate = self.krate();
+            quote! {
+                const PLACEHOLDER_STYLE: ::std::option::Option<#krate::PlaceholderStyle> =
+                    ::std::option::Option::Some(#krate::PlaceholderStyle::#style);
+            }
+        });
+        let fingerprint = proc_macro2::Literal::u64_suffixed(template_fingerprint(template));
+        let default_page_size = self
+            .default_page_size
+            .map(|size| quote! { const DEFAULT_PAGE_SIZE: ::std::option::Option<i64> = ::std::option::Option::Some(#size); });
+        quote! {
+            #persistent
+            #sort_columns
+            #default_page_size
+            #placeholder
+            const QUERY_TAG: &'static str = ::std::concat!(::std::module_path!(), "::", #tag);
+            const TEMPLATE_FINGERPRINT: u64 = #fingerprint;
+        }
+    }
+
+    /// 开启 `registry` feature 时提交到 `registry()` 清单的模板条目
+    fn registry_entry(&self, tag: &str, template: &TemplateArgs) -> proc_macro2::TokenStream {
+        if !cfg!(feature = "registry") || self.unregistered {
+            return quote! {};
+        }
+        let krate = self.krate();
+        let source = template_source(template);
+        let fingerprint = proc_macro2::Literal::u64_suffixed(template_fingerprint(template));
+        quote! {
+            #krate::registry_support::inventory::submit! {
+                #krate::RegisteredTemplate {
+                    name: ::std::concat!(::std::module_path!(), "::", #tag),
+                    source: #source,
+                    fingerprint: #fingerprint,
+                }
+            }
+        }
+    }
+}
+
+/// 解析 `#[sql_template(...)]` 属性
+fn parse_sql_template_attr(input: &DeriveInput) -> syn::Result<SqlTemplateOptions> {
+    let mut options = SqlTemplateOptions::default();
+    let mut askama_template = Span::call_site();
+    for attr in &input.attrs {
+        if !attr.path().is_ident("sql_template") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("askama_template") {
+                options.askama_template = true;
+                askama_template = meta.path.span();
+            } else if meta.path.is_ident("db") {
+                let lit: LitStr = meta.value()?.parse()?;
+                for name in lit.value().split(',').map(str::trim) {
+                    let db = db_type_path(&LitStr::new(name, lit.span()))?;
+                    if options
+                        .db
+                        .iter()
+                        .any(|seen| seen.to_string() == db.to_string())
+                    {
+                        return Err(syn::Error::new(
+                            lit.span(),
+                            format!("duplicate database `{name}`"),
+                        ));
+                    }
+                    options.db.push(db);
+                }
+            } else if meta.path.is_ident("persistent") {
+                let value: syn::LitBool = meta.value()?.parse()?;
+                options.persistent = Some(value.value);
+            } else if meta.path.is_ident("order_by") {
+                let lit: LitStr = meta.value()?.parse()?;
+                let columns: Vec<String> = lit
+                    .value()
+                    .split(',')
+                    .map(|column| column.trim().to_string())
+                    .filter(|column| !column.is_empty())
+                    .collect();
+                // 列名原样写入 SQL，只允许 `name` 或 `table.name`
+                let is_column = |column: &str| {
+                    column.split('.').all(|part| {
+                        part.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                            && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                    })
+                };
+                if let Some(column) = columns.iter().find(|column| !is_column(column)) {
+                    return Err(syn::Error::new(
+                        lit.span(),
+                        format!("`{column}` is not a column name"),
+                    ));
+                }
+                options.order_by = Some(columns);
+            } else if meta.path.is_ident("crate") {
+                options.krate = Some(parse_crate_path(&meta)?);
+            } else if meta.path.is_ident("builder") {
+                options.builder = true;
+            } else if meta.path.is_ident("strict") {
+                options.strict = true;
+            } else if meta.path.is_ident("registry") {
+                let registered: syn::LitBool = meta.value()?.parse()?;
+                options.unregistered = !registered.value;
+            } else if meta.path.is_ident("validate_sql") {
+                let validated: syn::LitBool = meta.value()?.parse()?;
+                options.unvalidated = !validated.value;
+            } else if meta.path.is_ident("display") {
+                let db = match meta.input.peek(Token![=]) {
+                    true => Some(db_type_path(&meta.value()?.parse()?)?),
+                    false => None,
+                };
+                options.display = Some(db);
+            } else if meta.path.is_ident("placeholder") {
+                let lit: LitStr = meta.value()?.parse()?;
+                let style = match lit.value().as_str() {
+                    "dollar" => "Dollar",
+                    "question" => "Question",
+                    "at" => "AtP",
+                    _ => {
+                        return Err(syn::Error::new(
+                            lit.span(),
+                            "expected `placeholder = \"dollar\"`, `\"question\"` or `\"at\"`",
+                        ));
+                    }
+                };
+                options.placeholder = Some(syn::Ident::new(style, lit.span()));
+            } else if meta.path.is_ident("helper_prefix") {
+                let lit: LitStr = meta.value()?.parse()?;
+                let prefix = lit.value();
+                if prefix.is_empty() || syn::parse_str::<syn::Ident>(&format!("{prefix}e")).is_err()
+                {
+                    return Err(syn::Error::new(
+                        lit.span(),
+                        "`helper_prefix` must begin an identifier, e.g. `\"sql_\"`",
+                    ));
+                }
+                options.helper_prefix = prefix;
+            } else if meta.path.is_ident("default_page_size") {
+                let lit: syn::LitInt = meta.value()?.parse()?;
+                let size: i64 = lit.base10_parse()?;
+                if size < 1 {
+                    return Err(syn::Error::new(
+                        lit.span(),
+                        "`default_page_size` must be at least 1",
+                    ));
+                }
+                options.default_page_size = Some(size);
+            } else {
+                return Err(meta.error("unknown `sql_template` option"));
+            }
+            Ok(())
+        })?;
+    }
+    match (options.db.len(), options.askama_template) {
+        (0, true) => Err(syn::Error::new(
+            askama_template,
+            "`askama_template` needs a database, e.g. `db = \"postgres\"`",
+        )),
+        (2.., true) => Err(syn::Error::new(
+            askama_template,
+            "`askama_template` supports a single database",
+        )),
+        _ => Ok(options),
+    }
+}
+
+/// `crate = "path"` 的值，如 `crate = "my_facade::sql"`
+fn parse_crate_path(meta: &syn::meta::ParseNestedMeta) -> syn::Result<Path> {
+    let lit: LitStr = meta.value()?.parse()?;
+    lit.parse()
+}
+
+/// 数据库名称对应的 sqlx 类型
+fn db_type_path(name: &LitStr) -> syn::Result<proc_macro2::TokenStream> {
+    match name.value().to_ascii_lowercase().as_str() {
+        "postgres" => Ok(quote! { ::sqlx::Postgres }),
+        "mysql" => Ok(quote! { ::sqlx::MySql }),
+        "sqlite" => Ok(quote! { ::sqlx::Sqlite }),
+        "any" => Ok(quote! { ::sqlx::Any }),
+        _ => Err(syn::Error::new(
+            name.span(),
+            "unknown database, expected `postgres`, `mysql`, `sqlite` or `any`",
+        )),
+    }
+}
+
+/// 派生宏共用的泛型、类型约束与调试信息
+struct Analysis {
+    /// 结构体泛型，加上数据生命周期与 `DB` 类型参数
+    wrapper_generics: syn::Generics,
+    /// 结构体泛型，加上数据生命周期
+    sql_generics: syn::Generics,
+    data_lifetime: proc_macro2::TokenStream,
+    bound_types: proc_macro2::TokenStream,
+    /// `Vec<DebugParam>` 表达式，字段通过 `data` 访问
+    debug_params: proc_macro2::TokenStream,
+    /// `#[bind_with = "..."]` 字段及其转换函数
+    bindings: Vec<(syn::Ident, Path)>,
+    /// `e(field)` 改写为 `e_in(field)` 的列表字段，见 [`list_binds`]
+    lists: Vec<String>,
+}
+
+/// 分析结构体的泛型与字段，生成 Wrapper 的泛型参数与类型约束
+///
+/// `lists` 中的列表字段在模板中改写为 `e_in(field)`，约束改为元素类型，见
+/// [`list_binds`]。
+fn analyze(input: &DeriveInput, krate: &Path, lists: Vec<String>) -> syn::Result<Analysis> {
+    let generics = &without_defaults(&input.generics);
+    // 处理生命周期参数：唯一的生命周期即数据生命周期；没有或有多个时引入新的 `'q`，
+    // 多个时每个结构体生命周期都长于 `'q`
+    let mut lifetimes = generics.lifetimes();
+    let (mut wrapper_generics, data_lifetime) =
+        if let (Some(lt), None) = (lifetimes.next(), lifetimes.next()) {
+            let generics = generics.clone();
+            let lt_ident = &lt.lifetime;
+            (generics, quote! { #lt_ident })
+        } else {
+            let mut generics = generics.clone();
+            let mut name = String::from("'q");
+            while generics
+                .lifetimes()
+                .any(|lt| lt.lifetime.ident == name[1..])
+            {
+                name.push('_');
+            }
+            let data_lifetime = syn::Lifetime::new(&name, proc_macro2::Span::call_site());
+            for lt in generics.lifetimes_mut() {
+                lt.colon_token.get_or_insert_default();
+                lt.bounds.push(data_lifetime.clone());
+            }
+            generics.params.insert(
+                0,
+                syn::GenericParam::Lifetime(LifetimeParam::new(data_lifetime.clone())),
+            );
+            (generics, quote! { #data_lifetime })
+        };
+    let sql_generics = wrapper_generics.clone();
+
+    // 添加DB类型参数
+    wrapper_generics
+        .params
+        .push(syn::GenericParam::Type(syn::TypeParam {
+            attrs: Vec::new(),
+            ident: format_ident!("DB"),
+            colon_token: None,
+            bounds: syn::punctuated::Punctuated::new(),
+            eq_token: None,
+            default: None,
+        }));
+
+    // 收集需要绑定的类型
+    let mut seen_types = BTreeSet::new();
+    let mut bound_types = proc_macro2::TokenStream::new();
+    let mut bindings = Vec::new();
+
+    // 处理字段类型，结构体上的 `#[ignore_type]` 忽略所有字段，只保留显式列出的类型
+    let ignore_all = input
+        .attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("ignore_type"));
+    let is_listed = |field: &syn::Field| {
+        lists
+            .iter()
+            .any(|list| field.ident.as_ref().is_some_and(|ident| ident == list))
+    };
+    if let syn::Data::Struct(data_struct) = &input.data {
+        // 模板中的 `e(field)` 改写为 `e_in(field)` 的列表字段约束元素类型，先于其他
+        // 字段登记，同一类型的字段不再添加只对数据生命周期成立的约束
+        for field in &data_struct.fields {
+            let has_ignore = ignore_all
+                || field
+                    .attrs
+                    .iter()
+                    .any(|attr| attr.path().is_ident("ignore_type"));
+            let Some(elem) = list_field(field).filter(|_| is_listed(field) && !has_ignore) else {
+                continue;
+            };
+            if seen_types.insert(get_type_identifier(elem)) {
+                bound_types.extend(quote_spanned! {elem.span()=>
+                    #elem: for<'template_local_lifetime> ::sqlx::Encode<'template_local_lifetime, DB> + ::sqlx::Type<DB>,
+                });
+            }
+            bound_types.extend(quote_spanned! {elem.span()=>
+                #elem: ::std::clone::Clone + ::std::marker::Send + 'static,
+            });
+        }
+        for field in &data_struct.fields {
+            is_secret(field)?;
+            parse_sql_cast(field)?;
+            let has_ignore = ignore_all
+                || field
+                    .attrs
+                    .iter()
+                    .any(|attr| attr.path().is_ident("ignore_type"));
+            let bind_as = parse_bind_as(field)?;
+            if let Some(bind_with) = parse_bind_with(field)? {
+                // 模板中读到的是转换结果，字段本身不绑定
+                bindings.push(bind_with);
+            } else if is_fragment(field) {
+                // 嵌套模板经 `include_fragment()` 渲染，参数并入本模板
+                let ty = replace_lifetimes(
+                    field.ty.to_token_stream(),
+                    &syn::Lifetime::new("'sql_fragment", Span::call_site()),
+                );
+                bound_types.extend(quote_spanned! {field.ty.span()=>
+                    for<'sql_fragment> &'sql_fragment #ty: #krate::SqlTemplate<'sql_fragment, DB>,
+                });
+            } else if has_flatten(field) {
+                // 嵌套参数结构体本身不绑定，字段通过 `address.city` 访问
+                let ty = &field.ty;
+                bound_types.extend(quote_spanned! {ty.span()=>
+                    #ty: #krate::SqlParams,
+                });
+            } else if let Some(ty) = bind_as {
+                // 字段按另一类型编码（如 newtype 按内部类型），约束改为该类型
+                if seen_types.insert(get_type_identifier(&ty)) {
+                    bound_types.extend(quote_spanned! {ty.span()=>
+                        #ty: ::sqlx::Encode<#data_lifetime, DB> + ::sqlx::Type<DB>,
+                    });
+                }
+            } else if !has_ignore && !is_listed(field) {
+                let ty = &field.ty;
+                let ident = get_type_identifier(ty);
+                if seen_types.insert(ident) {
+                    bound_types.extend(quote_spanned! {ty.span()=>
+                        #ty: ::sqlx::Encode<#data_lifetime, DB> + ::sqlx::Type<DB>,
+                    });
+                }
+            }
+        }
+    }
+
+    // 收集字段调试信息
+    let debug_params = match &input.data {
+        syn::Data::Struct(data_struct) => debug_params(&data_struct.fields, krate),
+        _ => quote! { ::std::vec::Vec::new() },
+    };
+
+    // 处理结构体与字段上的 add_type 属性
+    add_type_bounds(
+        &input.attrs,
+        &data_lifetime,
+        &mut seen_types,
+        &mut bound_types,
+    )?;
+    if let syn::Data::Struct(data_struct) = &input.data {
+        for field in &data_struct.fields {
+            add_type_bounds(
+                &field.attrs,
+                &data_lifetime,
+                &mut seen_types,
+                &mut bound_types,
+            )?;
+        }
+    }
+
+    Ok(Analysis {
+        wrapper_generics,
+        sql_generics,
+        data_lifetime,
+        bound_types,
+        debug_params,
+        bindings,
+        lists,
+    })
+}
+
+/// 去掉类型与常量参数默认值的泛型参数，默认值只能出现在结构体定义中的末尾参数上，
+/// 生成的 Wrapper 与实现在其后追加 `DB` 等参数
+fn without_defaults(generics: &syn::Generics) -> syn::Generics {
+    let mut generics = generics.clone();
+    for param in &mut generics.params {
+        match param {
+            syn::GenericParam::Type(ty) => {
+                ty.eq_token = None;
+                ty.default = None;
+            }
+            syn::GenericParam::Const(c) => {
+                c.eq_token = None;
+                c.default = None;
+            }
+            syn::GenericParam::Lifetime(_) => {}
+        }
+    }
+    generics
+}
+
+/// 解析字段上的 `#[bind_with = "path::to::fn"]`
+fn parse_bind_with(field: &syn::Field) -> syn::Result<Option<(syn::Ident, Path)>> {
+    let Some(attr) = field
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("bind_with"))
+    else {
+        return Ok(None);
+    };
+    let Some(ident) = &field.ident else {
+        return Err(syn::Error::new_spanned(
+            attr,
+            "`bind_with` needs a named field",
+        ));
+    };
+    let path = lit_str(&attr.meta)?.parse::<Path>()?;
+    Ok(Some((ident.clone(), path)))
+}
+
+/// 解析字段上的 `#[bind_as(Type)]`，与其他改变绑定方式的属性互斥
+fn parse_bind_as(field: &syn::Field) -> syn::Result<Option<syn::Type>> {
+    let Some(attr) = field
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("bind_as"))
+    else {
+        return Ok(None);
+    };
+    if let Some(other) = field.attrs.iter().find(|attr| {
+        ["ignore_type", "bind_with", "flatten"]
+            .iter()
+            .any(|name| attr.path().is_ident(name))
+    }) {
+        return Err(syn::Error::new_spanned(
+            other,
+            "`bind_as` can not be combined with `ignore_type`, `bind_with` or `flatten`",
+        ));
+    }
+    attr.parse_args().map(Some)
+}
+
+/// 字段是否标记了 `#[fragment]`
+fn is_fragment(field: &syn::Field) -> bool {
+    field
+        .attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("fragment"))
+}
+
+/// 将类型中除 `'static` 外的生命周期都替换为 `lifetime`
+fn replace_lifetimes(
+    tokens: proc_macro2::TokenStream,
+    lifetime: &syn::Lifetime,
+) -> proc_macro2::TokenStream {
+    let mut out = proc_macro2::TokenStream::new();
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(tree) = tokens.next() {
+        match tree {
+            proc_macro2::TokenTree::Punct(punct) if punct.as_char() == '\'' => match tokens.next_if(
+                |next| matches!(next, proc_macro2::TokenTree::Ident(ident) if ident != "static"),
+            ) {
+                Some(_) => lifetime.to_tokens(&mut out),
+                None => out.extend([proc_macro2::TokenTree::Punct(punct)]),
+            },
+            proc_macro2::TokenTree::Group(group) => {
+                let mut replaced = proc_macro2::Group::new(
+                    group.delimiter(),
+                    replace_lifetimes(group.stream(), lifetime),
+                );
+                replaced.set_span(group.span());
+                out.extend([proc_macro2::TokenTree::Group(replaced)]);
+            }
+            tree => out.extend([tree]),
+        }
+    }
+    out
+}
+
+/// 字段是否标记了 `#[flatten]`
+fn has_flatten(field: &syn::Field) -> bool {
+    field
+        .attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("flatten"))
+}
+
+/// 字段是否标记了 `#[param(secret)]`
+fn is_secret(field: &syn::Field) -> syn::Result<bool> {
+    let mut secret = false;
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("param"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("secret") {
+                secret = true;
+                Ok(())
+            } else {
+                Err(meta.error("unknown `param` option, expected `secret`"))
+            }
+        })?;
+    }
+    Ok(secret)
+}
+
+/// 构造 `Vec<DebugParam>` 的表达式，字段通过 `data` 访问，
+/// `#[flatten]` 字段展开为嵌套结构体的字段，`#[fragment]` 字段展开为嵌套模板的
+/// 参数，其中的 `DB` 由调用方替换为具体数据库
+fn debug_params(fields: &syn::Fields, krate: &Path) -> proc_macro2::TokenStream {
+    let mut params = proc_macro2::TokenStream::new();
+    for (i, field) in fields.iter().enumerate() {
+        let sensitive = field
+            .attrs
+            .iter()
+            .any(|attr| attr.path().is_ident("sensitive"))
+            || is_secret(field).unwrap_or(false);
+        let raw = field.attrs.iter().any(|attr| attr.path().is_ident("raw"));
+        let (member, field_name) = match &field.ident {
+            Some(ident) => (quote! { #ident }, ident.to_string()),
+            None => {
+                let index = syn::Index::from(i);
+                (quote! { #index }, i.to_string())
+            }
+        };
+        if has_flatten(field) {
+            params.extend(quote! {
+                params.extend(#krate::SqlParams::debug_params(&data.#member));
+            });
+            continue;
+        }
+        if is_fragment(field) {
+            let ty = replace_lifetimes(
+                field.ty.to_token_stream(),
+                &syn::Lifetime::new("'_", Span::call_site()),
+            );
+            params.extend(quote! {
+                params.extend(<&#ty as #krate::SqlTemplate<'_, DB>>::debug_params(&&data.#member));
+            });
+            continue;
+        }
+        params.extend(quote! {
+            params.push(
+                #krate::DebugParam::new(
+                    #field_name,
+                    (&#krate::DebugProbe(&data.#member)).debug_value(),
+                    #sensitive,
+                    (&#krate::DebugProbe(&data.#member)).is_string(),
+                )
+                .with_raw(#raw),
+            );
+        });
+    }
+    quote! {{
+        #[allow(unused_mut)]
+        let mut params = ::std::vec::Vec::new();
+        #params
+        params
+    }}
+}
+
+/// `#[add_type(...)]` 中的一项：类型，或 `preset = "chrono, uuid"`
+enum AddType {
+    Type(Box<syn::Type>),
+    Preset(LitStr),
+}
+
+impl syn::parse::Parse for AddType {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        if input.peek(syn::Ident) && input.peek2(Token![=]) {
+            let ident: syn::Ident = input.parse()?;
+            if ident != "preset" {
+                return Err(syn::Error::new(ident.span(), "expected `preset = \"...\"`"));
+            }
+            input.parse::<Token![=]>()?;
+            return Ok(Self::Preset(input.parse()?));
+        }
+        Ok(Self::Type(Box::new(input.parse()?)))
+    }
+}
+
+/// 预设类型族经由 `sqlx::types` 的路径，需启用 sqlx 的同名特性
+const ADD_TYPE_PRESETS: &[(&str, &[&str])] = &[
+    (
+        "chrono",
+        &[
+            "::sqlx::types::chrono::NaiveDate",
+            "::sqlx::types::chrono::NaiveTime",
+            "::sqlx::types::chrono::NaiveDateTime",
+            "::sqlx::types::chrono::DateTime<::sqlx::types::chrono::Utc>",
+        ],
+    ),
+    (
+        "time",
+        &[
+            "::sqlx::types::time::Date",
+            "::sqlx::types::time::Time",
+            "::sqlx::types::time::PrimitiveDateTime",
+            "::sqlx::types::time::OffsetDateTime",
+        ],
+    ),
+    ("uuid", &["::sqlx::types::Uuid"]),
+    ("decimal", &["::sqlx::types::Decimal"]),
+    ("bigdecimal", &["::sqlx::types::BigDecimal"]),
+    ("json", &["::sqlx::types::JsonValue"]),
+];
+
+/// 去掉路径前缀的类型，如 `:: sqlx :: types :: Uuid` 为 `Uuid`
+fn unqualified(ty: &str) -> String {
+    let tokens: Vec<&str> = ty.split_whitespace().collect();
+    tokens
+        .iter()
+        .enumerate()
+        .filter(|&(i, token)| *token != "::" && tokens.get(i + 1) != Some(&"::"))
+        .map(|(_, token)| *token)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `preset = "chrono, uuid"` 展开的类型：每个类型 `T` 及其 `Option<T>` 与 `Option<&'q T>`
+fn preset_types(
+    presets: &LitStr,
+    data_lifetime: &proc_macro2::TokenStream,
+) -> syn::Result<Vec<syn::Type>> {
+    let mut types = Vec::new();
+    for name in presets.value().split(',').map(str::trim) {
+        let Some((_, paths)) = ADD_TYPE_PRESETS.iter().find(|(preset, _)| *preset == name) else {
+            let known: Vec<_> = ADD_TYPE_PRESETS
+                .iter()
+                .map(|(preset, _)| format!("`{preset}`"))
+                .collect();
+            return Err(syn::Error::new(
+                presets.span(),
+                format!(
+                    "unknown preset `{name}`, expected one of {}",
+                    known.join(", ")
+                ),
+            ));
+        };
+        for path in *paths {
+            let ty: syn::Type = syn::parse_str(path)?;
+            types.push(syn::parse_quote! { ::std::option::Option<&#data_lifetime #ty> });
+            types.push(syn::parse_quote! { ::std::option::Option<#ty> });
+            types.push(ty);
+        }
+    }
+    Ok(types)
+}
+
+/// 为 `#[add_type(...)]` 列出的类型添加 `Encode + Type` 约束
+fn add_type_bounds(
+    attrs: &[syn::Attribute],
+    data_lifetime: &proc_macro2::TokenStream,
+    seen_types: &mut BTreeSet<TypeIdentifier>,
+    bound_types: &mut proc_macro2::TokenStream,
+) -> syn::Result<()> {
+    for attr in attrs {
+        if !attr.path().is_ident("add_type") {
+            continue;
+        }
+        let items = attr.parse_args_with(Punctuated::<AddType, Token![,]>::parse_terminated)?;
+        let mut types = Vec::new();
+        for item in items {
+            match item {
+                AddType::Type(ty) => types.push((ty.span(), *ty, false)),
+                AddType::Preset(presets) => types.extend(
+                    preset_types(&presets, data_lifetime)?
+                        .into_iter()
+                        .map(|ty| (presets.span(), ty, true)),
+                ),
+            }
+        }
+        for (span, ty, preset) in types {
+            let ident = get_type_identifier(&ty);
+            let have_lifetime = ident.0.contains('\'');
+            // 预设类型按去掉路径的类型名去重，字段中的 `Uuid` 与
+            // `::sqlx::types::Uuid` 的两个约束会产生歧义
+            let preset_ident = TypeIdentifier(format!("preset: {}", unqualified(&ident.0)));
+            let duplicate = match preset {
+                true => seen_types.iter().any(|seen| {
+                    unqualified(seen.0.trim_start_matches("preset: ")) == unqualified(&ident.0)
+                }),
+                false => seen_types.contains(&preset_ident),
+            };
+            if duplicate {
+                continue;
+            }
+            if preset {
+                seen_types.insert(preset_ident);
+            }
+            if seen_types.insert(ident) {
+                if have_lifetime {
+                    //非引用类型且包含生命周期如slef.Vec<i64>.first()->Option<&'a i64>数据来源自结构体本身的字段生命周期相同;或者如&str这样的静态引用，使用结构体本身生命周期
+                    bound_types.extend(quote_spanned! {span=>
+                        #ty: ::sqlx::Encode<#data_lifetime, DB> + ::sqlx::Type<DB>,
+                    });
+                } else {
+                    bound_types.extend(quote_spanned! {span=>
+                        #ty: for<'template_local_lifetime> ::sqlx::Encode<'template_local_lifetime, DB> + ::sqlx::Type<DB>,
+                    });
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[proc_macro_derive(
+    SqlTemplate,
+    attributes(
+        template,
+        sql_template,
+        add_type,
+        ignore_type,
+        sensitive,
+        param,
+        raw,
+        flatten,
+        fragment,
+        bind_with,
+        bind_as,
+        sql_cast
+    )
+)]
+pub fn sql_template(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let generics = &input.generics;
+    let options = match parse_sql_template_attr(&input) {
+        Ok(options) => options,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    if let syn::Data::Enum(data) = &input.data {
+        return enum_impl(&input, data, &options)
+            .unwrap_or_else(syn::Error::into_compile_error)
+            .into();
+    }
+    let krate = options.krate();
+    //处理template
+    let template_attrs = match process_template_attr(&input, &krate) {
+        Ok(template_attrs) => template_attrs,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    let lists = match options.askama_template {
+        true => Vec::new(),
+        false => list_binds(&input, &[&template_attrs], &options.helper_prefix),
+    };
+    let analysis = match analyze(&input, &krate, lists) {
+        Ok(analysis) => analysis,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    if let syn::Data::Struct(data) = &input.data
+        && let Err(e) = check_raw_interpolation(&data.fields, &template_attrs)
+            .and_then(|()| match options.strict {
+                true => check_unused_fields(&data.fields, &[&template_attrs]),
+                false => Ok(()),
+            })
+            .and_then(|()| check_sql_syntax(&template_attrs, &options, &analysis.lists))
+    {
+        return e.to_compile_error().into();
+    }
+    let Analysis {
+        sql_generics,
+        data_lifetime,
+        debug_params,
+        ..
+    } = &analysis;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let where_clause = open_where_clause(where_clause);
+
+    let param_consts = param_consts(&template_attrs, &analysis.lists, &options.helper_prefix);
+    let builder = if options.builder {
+        match builder_impl(&input, &krate) {
+            Ok(builder) => builder,
+            Err(e) => return e.to_compile_error().into(),
+        }
+    } else {
+        quote! {}
+    };
+
+    if let (true, [db]) = (options.askama_template, options.db.as_slice()) {
+        // 结构体自身的 Template 实现不经过 add_template_args 补充的 escape
+        if let Some(span) = template_attrs.html_escaped {
+            return syn::Error::new(
+                span,
+                "HTML escaping corrupts the rendered SQL, add `escape = \"none\"` to `#[template]`",
+            )
+            .to_compile_error()
+            .into();
+        }
+        if let Some((field, _)) = analysis.bindings.first() {
+            return syn::Error::new(
+                field.span(),
+                "`bind_with` is not supported with `askama_template`",
+            )
+            .to_compile_error()
+            .into();
+        }
+        if let Err(e) = check_unrewritable_marks(&bind_marks(&input), "an `askama_template` struct")
+        {
+            return e.to_compile_error().into();
+        }
+        if count_block_args(&template_attrs).is_some() {
+            let template = input.attrs.iter().find(|a| a.path().is_ident("template"));
+            return syn::Error::new(
+                template.map_or_else(Span::call_site, Spanned::span),
+                "`{% block count %}` is not supported with `askama_template`",
+            )
+            .to_compile_error()
+            .into();
+        }
+        let (sql_impl_generics, _, _) = sql_generics.split_for_impl();
+        let overrides = options.overrides(&name.to_string(), &template_attrs);
+        let owned = owned_impl(&input, Some(db), &overrides, &krate);
+        let display = display_impl(&input, &options);
+        let render_helpers = driver_render_impl(&input, &options);
+        let erased = erased_impl(&input, &options);
+        let registry_entry = options.registry_entry(&name.to_string(), &template_attrs);
+        let askama_debug_params = replace_db(debug_params.clone(), db);
+        let forward = |call| quote! { #krate::with_active_arg::<#db, _>(|arg| arg.#call) };
+        let mut helpers = helper_fns("", db, &krate, forward);
+        if !options.helper_prefix.is_empty() {
+            helpers.extend(helper_fns(&options.helper_prefix, db, &krate, forward));
+        }
+        let expanded = quote! {
+            // 模板中的辅助函数，参数写入当前渲染的 TemplateArg
+            #[allow(dead_code)]
+            impl #impl_generics #name #ty_generics #where_clause {
+                #param_consts
+                #helpers
+            }
+
+            impl #sql_impl_generics #krate::SqlTemplate<#data_lifetime, #db>
+                for &#data_lifetime #name #ty_generics
+                #where_clause
+            {
+                #overrides
+                fn render_with_placeholder(
+                    self,
+                    format_placeholder: ::std::option::Option<fn(usize, &mut String)>,
+                    sql_buffer: &mut String,
+                ) -> ::std::result::Result<
+                    ::std::option::Option<<#db as ::sqlx::Database>::Arguments>,
+                    #krate::Error,
+                > {
+                    #krate::render_guarded(
+                        ::std::any::type_name::<Self>(),
+                        sql_buffer,
+                        |sql_buffer| {
+                            let start = sql_buffer.len();
+                            let (rendered, arg) = #krate::render_with_active_arg::<#db, _>(
+                                format_placeholder,
+                                || #krate::askama::Template::render_into(self, sql_buffer),
+                            );
+                            rendered.map_err(|e| #krate::Error::Render(
+                                ::sqlx::Error::Encode(::std::boxed::Box::new(e)).into(),
+                            ))?;
+                            #krate::lint_rendered_sql(
+                                ::std::any::type_name::<Self>(),
+                                &sql_buffer[start..],
+                                || <Self as #krate::SqlTemplate<#data_lifetime, #db>>::debug_params(&self),
+                            )?;
+                            if let Some(e) = arg.get_err() {
+                                return ::std::result::Result::Err(#krate::Error::Encode(e.into()));
+                            }
+                            ::std::result::Result::Ok(arg.get_arguments())
+                        },
+                    )
+                }
+
+                fn debug_params(&self) -> ::std::vec::Vec<#krate::DebugParam> {
+                    #[allow(unused_imports)]
+                    use #krate::{DebugFallback as _, DebugValue as _, StringFallback as _, StringValue as _};
+                    let data = self;
+                    #askama_debug_params
+                }
+            }
+
+            #owned
+            #builder
+            #display
+            #render_helpers
+            #erased
+            #registry_entry
+        };
+        return expanded.into();
+    }
+
+    let self_ty = quote! { &#data_lifetime #name #ty_generics };
+    let mut expanded = wrapper_impl(
+        &input,
+        &analysis,
+        &options,
+        &template_attrs,
+        self_ty,
+        quote! { self },
+        &name.to_string(),
+    );
+    let mut overrides = options.overrides(&name.to_string(), &template_attrs);
+    if count_block_args(&template_attrs).is_some() {
+        overrides.extend(quote! { const HAS_COUNT_QUERY: bool = true; });
+    }
+    if static_binds(&template_attrs, &analysis.lists, &options.helper_prefix).is_some() {
+        overrides.extend(quote! { const STATIC_SQL: bool = true; });
+    }
+    if options.db.is_empty() {
+        expanded.extend(owned_impl(&input, None, &overrides, &krate));
+    }
+    for db in &options.db {
+        expanded.extend(owned_impl(&input, Some(db), &overrides, &krate));
+    }
+    expanded.extend(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            #param_consts
+        }
+    });
+    expanded.extend(builder);
+    expanded.extend(display_impl(&input, &options));
+    expanded.extend(driver_render_impl(&input, &options));
+    expanded.extend(erased_impl(&input, &options));
+    expanded.into()
+}
+
+/// 模板辅助函数 `e`、`el` 等，名称加上 `prefix`，函数体由 `forward` 以
+/// `e(t)` 形式的调用生成
+fn helper_fns(
+    prefix: &str,
+    db: &proc_macro2::TokenStream,
+    krate: &Path,
+    forward: impl Fn(proc_macro2::TokenStream) -> proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let helper = |name: &str, call| (format_ident!("{}{}", prefix, name), forward(call));
+    let (e, e_body) = helper("e", quote! { e(t) });
+    let (e_named, e_named_body) = helper("e_named", quote! { e_named(label, t) });
+    let (el, el_body) = helper("el", quote! { el(args) });
+    let (e_secret, e_secret_body) = helper("e_secret", quote! { e_secret(t) });
+    let (e_named_secret, e_named_secret_body) =
+        helper("e_named_secret", quote! { e_named_secret(label, t) });
+    let (el_secret, el_secret_body) = helper("el_secret", quote! { el_secret(args) });
+    let (sql_cast, sql_cast_body) = helper("sql_cast", quote! { sql_cast(placeholders, cast) });
+    let (e_in, e_in_body) = helper("e_in", quote! { e_in(values) });
+    let (sql_include, sql_include_body) = helper("sql_include", quote! { sql_include(fragment) });
+    let (ctx, ctx_body) = helper("ctx", quote! { ctx(key) });
+    let (ctx_flag, ctx_flag_body) = helper("ctx_flag", quote! { ctx_flag(key) });
+    let (ident, ident_body) = helper("ident", quote! { ident(name) });
+    let (columns, columns_body) = helper("columns", quote! { columns(names) });
+    let include_fragment = fragment_helper(prefix, db, krate, forward);
+    quote! {
+        #include_fragment
+        fn #e<'t, ImplEncode>(&self, t: ImplEncode) -> ::std::string::String
+        where
+            ImplEncode: ::sqlx::Encode<'t, #db> + ::sqlx::Type<#db>,
+        {
+            #e_body
+        }
+        fn #e_named<'t, ImplEncode>(&self, label: &str, t: ImplEncode) -> ::std::string::String
+        where
+            ImplEncode: ::sqlx::Encode<'t, #db> + ::sqlx::Type<#db>,
+        {
+            #e_named_body
+        }
+        fn #el<'t, ImplEncode>(
+            &self,
+            args: impl ::std::iter::IntoIterator<Item = ImplEncode>,
+        ) -> ::std::string::String
+        where
+            ImplEncode: ::sqlx::Encode<'t, #db> + ::sqlx::Type<#db>,
+        {
+            #el_body
+        }
+        fn #e_secret<'t, ImplEncode>(&self, t: ImplEncode) -> ::std::string::String
+        where
+            ImplEncode: ::sqlx::Encode<'t, #db> + ::sqlx::Type<#db>,
+        {
+            #e_secret_body
+        }
+        fn #e_named_secret<'t, ImplEncode>(&self, label: &str, t: ImplEncode) -> ::std::string::String
+        where
+            ImplEncode: ::sqlx::Encode<'t, #db> + ::sqlx::Type<#db>,
+        {
+            #e_named_secret_body
+        }
+        fn #el_secret<'t, ImplEncode>(
+            &self,
+            args: impl ::std::iter::IntoIterator<Item = ImplEncode>,
+        ) -> ::std::string::String
+        where
+            ImplEncode: ::sqlx::Encode<'t, #db> + ::sqlx::Type<#db>,
+        {
+            #el_secret_body
+        }
+        fn #sql_cast(&self, placeholders: impl ::std::convert::AsRef<str>, cast: &str) -> ::std::string::String {
+            #sql_cast_body
+        }
+        fn #e_in<'t, T>(&self, values: &'t [T]) -> ::std::string::String
+        where
+            T: ::std::clone::Clone + ::std::marker::Send + 'static,
+            &'t T: ::sqlx::Encode<'t, #db> + ::sqlx::Type<#db>,
+        {
+            #e_in_body
+        }
+        fn #sql_include<'t, V>(
+            &self,
+            fragment: &'t #krate::SqlFragment<V>,
+        ) -> ::std::string::String
+        where
+            V: ::sqlx::Encode<'t, #db> + ::sqlx::Type<#db>,
+        {
+            #sql_include_body
+        }
+        fn #ctx(&self, key: &str) -> ::std::string::String {
+            #ctx_body
+        }
+        fn #ctx_flag(&self, key: &str) -> bool {
+            #ctx_flag_body
+        }
+        fn #ident(&self, name: &str) -> ::std::string::String {
+            #ident_body
+        }
+        fn #columns<S: ::std::convert::AsRef<str>>(
+            &self,
+            names: impl ::std::iter::IntoIterator<Item = S>,
+        ) -> ::std::string::String {
+            #columns_body
+        }
+    }
+}
+
+/// 模板辅助函数 `include_fragment`，名称加上 `prefix`，见 [`helper_fns`]
+///
+/// 嵌套模板以本模板的占位符函数渲染，参数追加到本模板的参数之后。
+fn fragment_helper(
+    prefix: &str,
+    db: &proc_macro2::TokenStream,
+    krate: &Path,
+    forward: impl Fn(proc_macro2::TokenStream) -> proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let name = format_ident!("{}include_fragment", prefix);
+    let body = forward(quote! {
+        include_nested(|format_placeholder, sql_buffer| {
+            #krate::SqlTemplate::<'t, #db>::render_with_placeholder(
+                fragment,
+                format_placeholder,
+                sql_buffer,
+            )
+        })
+    });
+    quote! {
+        fn #name<'t, F>(&self, fragment: &'t F) -> ::std::string::String
+        where
+            &'t F: #krate::SqlTemplate<'t, #db>,
+        {
+            #body
+        }
+    }
+}
+
+/// 枚举的 `SqlTemplate` 实现，每个变体使用自己的 `#[template]`
+///
+/// 每个变体生成一个隐藏结构体，字段为变体字段的引用，并派生 `SqlTemplate`；
+/// 枚举的实现按变体构造该结构体并转发渲染与调试参数。
+fn enum_impl(
+    input: &DeriveInput,
+    data: &syn::DataEnum,
+    options: &SqlTemplateOptions,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    if options.askama_template || options.builder {
+        return Err(syn::Error::new_spanned(
+            name,
+            "`askama_template` and `builder` are not supported on enums",
+        ));
+    }
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "generic enums are not supported by `SqlTemplate`",
+        ));
+    }
+    if let Some(attr) = input.attrs.iter().find(|a| a.path().is_ident("template")) {
+        return Err(syn::Error::new_spanned(
+            attr,
+            "put `#[template(...)]` on each variant of the enum",
+        ));
+    }
+    if data.variants.is_empty() {
+        return Err(syn::Error::new_spanned(
+            name,
+            "`SqlTemplate` needs at least one variant",
+        ));
+    }
+    let krate = options.krate();
+    // 枚举上的 sql_template、add_type 与 ignore_type 属性传给每个变体结构体
+    let shared_attrs: Vec<_> = input
+        .attrs
+        .iter()
+        .filter(|a| {
+            ["sql_template", "add_type", "ignore_type"]
+                .iter()
+                .any(|name| a.path().is_ident(name))
+        })
+        .collect();
+
+    let mut fingerprint_args = TemplateArgs::default();
+    let mut structs = proc_macro2::TokenStream::new();
+    let mut bounds = proc_macro2::TokenStream::new();
+    let mut render_arms = proc_macro2::TokenStream::new();
+    let mut debug_arms = proc_macro2::TokenStream::new();
+    for variant in &data.variants {
+        let ident = &variant.ident;
+        let template_attrs: Vec<_> = variant
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("template"))
+            .collect();
+        if template_attrs.is_empty() {
+            return Err(syn::Error::new_spanned(
+                ident,
+                format!("missing `#[template(...)]` on variant `{ident}`"),
+            ));
+        }
+        let mut variant_args = TemplateArgs::default();
+        for attr in &template_attrs {
+            let nested = attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+            fingerprint_args.args.extend(nested.clone());
+            add_template_args(&mut variant_args, nested, &krate)?;
+        }
+        if let Some(source) = &variant_args.path_source {
+            fingerprint_args
+                .path_source
+                .get_or_insert_default()
+                .push_str(source);
+        }
+        structs.extend(options.registry_entry(&format!("{name}::{ident}"), &variant_args));
+        let variant_attrs = variant
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("add_type") || a.path().is_ident("ignore_type"));
+        let struct_name = format_ident!("__{}{}", name, ident);
+
+        let named = match &variant.fields {
+            syn::Fields::Named(fields) => &fields.named,
+            syn::Fields::Unit => &Punctuated::new(),
+            syn::Fields::Unnamed(fields) => {
+                return Err(syn::Error::new_spanned(
+                    fields,
+                    "tuple variants are not supported by `SqlTemplate`, name the fields",
+                ));
+            }
+        };
+        for field in named {
+            if let Some(attr) = field.attrs.iter().find(|a| {
+                ["bind_with", "flatten", "fragment"]
+                    .iter()
+                    .any(|name| a.path().is_ident(name))
+            }) {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "`bind_with`, `flatten` and `fragment` are not supported on enum variants",
+                ));
+            }
+        }
+        let field_names: Vec<_> = named.iter().map(|field| &field.ident).collect();
+        let field_defs = named.iter().map(|field| {
+            let syn::Field {
+                attrs, ident, ty, ..
+            } = field;
+            quote! { #(#attrs)* #ident: &'q #ty }
+        });
+        let (body, variant_ty) = if named.is_empty() {
+            (quote! { {} }, quote! { #struct_name })
+        } else {
+            (
+                quote! { <'q> { #(#field_defs,)* } },
+                quote! { #struct_name<'x> },
+            )
+        };
+        structs.extend(quote! {
+            #[doc(hidden)]
+            #[allow(dead_code)]
+            #[derive(::std::clone::Clone, #krate::SqlTemplate)]
+            #(#template_attrs)*
+            #(#shared_attrs)*
+            #[sql_template(registry = false)]
+            #(#variant_attrs)*
+            struct #struct_name #body
+        });
+        bounds.extend(quote! {
+            for<'x> &'x #variant_ty: #krate::SqlTemplate<'x, DB>,
+        });
+        render_arms.extend(quote! {
+            #name::#ident { #(#field_names),* } => {
+                <&#struct_name as #krate::SqlTemplate<'_, DB>>::render_with_placeholder(
+                    &#struct_name { #(#field_names),* },
+                    format_placeholder,
+                    sql_buffer,
+                )
+            }
+        });
+        debug_arms.extend(quote! {
+            #name::#ident { #(#field_names),* } => {
+                <&#struct_name as #krate::SqlTemplate<'_, DB>>::debug_params(
+                    &&#struct_name { #(#field_names),* },
+                )
+            }
+        });
+    }
+
+    let overrides = options.overrides(&name.to_string(), &fingerprint_args);
+    let body = quote! {
+        #overrides
+        fn render_with_placeholder(
+            self,
+            format_placeholder: ::std::option::Option<fn(usize, &mut String)>,
+            sql_buffer: &mut String,
+        ) -> ::std::result::Result<
+            ::std::option::Option<<DB as ::sqlx::Database>::Arguments>,
+            #krate::Error,
+        > {
+            match self {
+                #render_arms
+            }
+        }
+
+        fn debug_params(&self) -> ::std::vec::Vec<#krate::DebugParam> {
+            match *self {
+                #debug_arms
             }
-            if meta.path().is_ident("ext") {
-                has_ext = true;
+        }
+    };
+    let mut expanded = structs;
+    if options.db.is_empty() {
+        expanded.extend(quote! {
+            impl<'q, DB> #krate::SqlTemplate<'q, DB> for &'q #name
+            where
+                DB: ::sqlx::Database,
+                #bounds
+            {
+                #body
             }
-            if meta.path().is_ident("askama") {
-                has_askama = true;
+        });
+        expanded.extend(owned_impl(input, None, &overrides, &krate));
+    }
+    for db in &options.db {
+        let bounds = replace_db(bounds.clone(), db);
+        let body = replace_db(body.clone(), db);
+        expanded.extend(quote! {
+            impl<'q> #krate::SqlTemplate<'q, #db> for &'q #name
+            where
+                #bounds
+            {
+                #body
             }
-            args.push(meta);
-        }
+        });
+        expanded.extend(owned_impl(input, Some(db), &overrides, &krate));
+    }
+    expanded.extend(display_impl(input, options));
+    expanded.extend(driver_render_impl(input, options));
+    expanded.extend(erased_impl(input, options));
+    Ok(expanded)
+}
 
-        // 设置默认值
+/// `#[sql_template(builder)]` 生成的 `NameBuilder` 与 `Name::builder()`
+///
+/// 每个字段一个接受 `impl Into<T>` 的设置方法；`Option` 字段可不设置，默认为 `None`，
+/// 其余字段未设置时 `build()` 返回 `BuilderError::MissingField`。
+fn builder_impl(input: &DeriveInput, krate: &Path) -> syn::Result<proc_macro2::TokenStream> {
+    let syn::Data::Struct(syn::DataStruct {
+        fields: syn::Fields::Named(fields),
+        ..
+    }) = &input.data
+    else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "`builder` needs a struct with named fields",
+        ));
+    };
+    let name = &input.ident;
+    let vis = &input.vis;
+    let builder = format_ident!("{}Builder", name);
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let generics = &input.generics;
 
-        if !has_askama {
-            let askama_meta = Meta::NameValue(syn::MetaNameValue {
-                path: syn::Path::from(syn::Ident::new("askama", Span::call_site())),
-                eq_token: <syn::Token![=]>::default(),
-                value: syn::Expr::Path(syn::ExprPath {
-                    attrs: Vec::new(),
-                    qself: None,
-                    path: syn::parse_str::<Path>("::sqlx_askama_template::askama").unwrap(),
-                }),
+    let mut storage = proc_macro2::TokenStream::new();
+    let mut defaults = proc_macro2::TokenStream::new();
+    let mut setters = proc_macro2::TokenStream::new();
+    let mut values = proc_macro2::TokenStream::new();
+    for field in &fields.named {
+        let ident = field.ident.as_ref().expect("named field");
+        let ty = &field.ty;
+        let field_name = ident.to_string();
+        let doc = format!("Sets `{field_name}`");
+        defaults.extend(quote! { #ident: ::std::option::Option::None, });
+        if is_option(ty) {
+            storage.extend(quote! { #ident: #ty, });
+            setters.extend(quote! {
+                #[doc = #doc]
+                #vis fn #ident(mut self, value: impl ::std::convert::Into<#ty>) -> Self {
+                    self.#ident = value.into();
+                    self
+                }
             });
-            args.push_punct(Token![,](Span::call_site()));
-            args.push_value(askama_meta);
-        }
-
-        if has_source && !has_ext {
-            // 添加 ext = "txt"
-            let ext_meta = Meta::NameValue(syn::MetaNameValue {
-                path: syn::Path::from(syn::Ident::new("ext", Span::call_site())),
-                eq_token: <syn::Token![=]>::default(),
-                value: syn::Expr::Lit(syn::ExprLit {
-                    attrs: Vec::new(),
-                    lit: syn::Lit::Str(LitStr::new("txt", Span::call_site())),
-                }),
+            values.extend(quote! { #ident: self.#ident, });
+        } else {
+            storage.extend(quote! { #ident: ::std::option::Option<#ty>, });
+            setters.extend(quote! {
+                #[doc = #doc]
+                #vis fn #ident(mut self, value: impl ::std::convert::Into<#ty>) -> Self {
+                    self.#ident = ::std::option::Option::Some(value.into());
+                    self
+                }
+            });
+            values.extend(quote! {
+                #ident: self.#ident.ok_or(#krate::BuilderError::MissingField(#field_name))?,
             });
-            args.push_punct(Token![,](Span::call_site()));
-            args.push_value(ext_meta);
         }
     }
+    let doc = format!("Builder of [`{name}`], created by [`{name}::builder`]");
+    Ok(quote! {
+        #[doc = #doc]
+        #[must_use]
+        #vis struct #builder #generics #where_clause {
+            #storage
+        }
 
-    args
+        impl #impl_generics ::std::default::Default for #builder #ty_generics #where_clause {
+            fn default() -> Self {
+                Self { #defaults }
+            }
+        }
+
+        impl #impl_generics #builder #ty_generics #where_clause {
+            #setters
+            /// Builds the template; fields that are not an `Option` must have been set
+            #vis fn build(self) -> ::std::result::Result<#name #ty_generics, #krate::BuilderError> {
+                ::std::result::Result::Ok(#name { #values })
+            }
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Starts a builder with every field unset
+            #vis fn builder() -> #builder #ty_generics {
+                ::std::default::Default::default()
+            }
+        }
+    })
 }
 
-#[proc_macro_derive(SqlTemplate, attributes(template, add_type, ignore_type))]
-pub fn sql_template(input: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(input as DeriveInput);
+/// 类型是否为 `Option<...>`
+fn is_option(ty: &syn::Type) -> bool {
+    let syn::Type::Path(path) = ty else {
+        return false;
+    };
+    path.qself.is_none()
+        && path.path.segments.last().is_some_and(|segment| {
+            segment.ident == "Option"
+                && matches!(segment.arguments, syn::PathArguments::AngleBracketed(_))
+        })
+}
+
+/// 结构体按值的 `SqlTemplate` 实现，转发给 `&T` 的实现，`db` 为空时对 `DB` 泛型
+///
+/// 只为不带生命周期参数的结构体生成。`Clone + Send` 与 `&T` 的实现写成
+/// `for<'x>` 约束：结构体未实现 `Clone` 时该实现不可用而不报错，仍可使用 `&T`。
+fn owned_impl(
+    input: &DeriveInput,
+    db: Option<&proc_macro2::TokenStream>,
+    overrides: &proc_macro2::TokenStream,
+    krate: &Path,
+) -> proc_macro2::TokenStream {
+    if input.generics.lifetimes().next().is_some() {
+        return quote! {};
+    }
     let name = &input.ident;
-    let generics = &input.generics;
-    //处理template
-    let template_attrs = process_template_attr(&input);
+    let mut generics = input.generics.clone();
+    generics.params.insert(0, syn::parse_quote! { 'q });
+    let (db, db_bound) = match db {
+        Some(db) => (db.clone(), quote! {}),
+        None => {
+            generics.params.push(syn::parse_quote! { DB });
+            (quote! { DB }, quote! { DB: ::sqlx::Database, })
+        }
+    };
+    let (impl_generics, _, _) = generics.split_for_impl();
+    let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+    let where_clause = open_where_clause(where_clause);
+    quote! {
+        impl #impl_generics #krate::SqlTemplate<'q, #db> for #name #ty_generics
+            #where_clause
+            #db_bound
+            for<'x> #name #ty_generics: ::std::clone::Clone + ::std::marker::Send,
+            for<'x> &'x #name #ty_generics: #krate::SqlTemplate<'x, #db>,
+        {
+            #overrides
+            fn render_with_placeholder(
+                self,
+                format_placeholder: ::std::option::Option<fn(usize, &mut String)>,
+                sql_buffer: &mut String,
+            ) -> ::std::result::Result<
+                ::std::option::Option<<#db as ::sqlx::Database>::Arguments>,
+                #krate::Error,
+            > {
+                <&Self as #krate::SqlTemplate<'_, #db>>::render_with_placeholder(
+                    &self,
+                    format_placeholder,
+                    sql_buffer,
+                )
+            }
 
-    // 处理生命周期参数
-    let (mut wrapper_generics, data_lifetime) = if let Some(lt) = generics.lifetimes().next() {
-        let generics = generics.clone();
-        let lt_ident = &lt.lifetime;
-        (generics, quote! { #lt_ident })
-    } else {
-        let mut generics = generics.clone();
-        let lifetime = LifetimeParam::new(syn::Lifetime::new("'q", proc_macro2::Span::call_site()));
-        generics
-            .params
-            .insert(0, syn::GenericParam::Lifetime(lifetime));
-        (generics, quote! { 'q })
+            fn render_count_with_placeholder(
+                self,
+                format_placeholder: ::std::option::Option<fn(usize, &mut String)>,
+                sql_buffer: &mut String,
+            ) -> ::std::result::Result<
+                ::std::option::Option<<#db as ::sqlx::Database>::Arguments>,
+                #krate::Error,
+            > {
+                <&Self as #krate::SqlTemplate<'_, #db>>::render_count_with_placeholder(
+                    &self,
+                    format_placeholder,
+                    sql_buffer,
+                )
+            }
+
+            fn render_arguments(
+                self,
+            ) -> ::std::result::Result<
+                ::std::option::Option<<#db as ::sqlx::Database>::Arguments>,
+                #krate::Error,
+            > {
+                <&Self as #krate::SqlTemplate<'_, #db>>::render_arguments(&self)
+            }
+
+            fn debug_params(&self) -> ::std::vec::Vec<#krate::DebugParam> {
+                <&Self as #krate::SqlTemplate<'_, #db>>::debug_params(&self)
+            }
+        }
+    }
+}
+
+/// `#[sql_template(display)]` 生成的 `Display`：以 `:1`、`:2` 标记参数的渲染结果
+///
+/// 无需数据库连接；按 `display = "postgres"` 指定的数据库渲染，否则按第一个
+/// `db`，都未指定时按 `Any`。
+fn display_impl(input: &DeriveInput, options: &SqlTemplateOptions) -> proc_macro2::TokenStream {
+    let Some(display) = &options.display else {
+        return quote! {};
+    };
+    let krate = options.krate();
+    let name = &input.ident;
+    let db = display
+        .as_ref()
+        .or(options.db.first())
+        .cloned()
+        .unwrap_or_else(|| quote! { ::sqlx::Any });
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let where_clause = open_where_clause(where_clause);
+    let bound_ty = with_data_lifetime(input, quote! { 'sql_display });
+    let call_ty = with_data_lifetime(input, quote! { '_ });
+    quote! {
+        impl #impl_generics ::std::fmt::Display for #name #ty_generics
+            #where_clause
+            for<'sql_display> &'sql_display #bound_ty: #krate::SqlTemplate<'sql_display, #db>,
+        {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                let mut sql = ::std::string::String::new();
+                let rendered = <&#call_ty as #krate::SqlTemplate<'_, #db>>::render_with_placeholder(
+                    self,
+                    ::std::option::Option::Some(|index, sql| {
+                        sql.push(':');
+                        sql.push_str(&index.to_string());
+                    }),
+                    &mut sql,
+                );
+                match rendered {
+                    ::std::result::Result::Ok(_) => f.write_str(&sql),
+                    ::std::result::Result::Err(e) => ::std::write!(f, "<render error: {e}>"),
+                }
+            }
+        }
+    }
+}
+
+/// 结构体或枚举的 `ErasedSqlTemplate` 实现，未指定 `db` 时对 `DB` 泛型实现，
+/// 否则为每个数据库各生成一个
+fn erased_impl(input: &DeriveInput, options: &SqlTemplateOptions) -> proc_macro2::TokenStream {
+    let krate = options.krate();
+    let name = &input.ident;
+    let bound_ty = with_data_lifetime(input, quote! { 'sql_erased });
+    let call_ty = with_data_lifetime(input, quote! { '_ });
+    let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+    let where_clause = open_where_clause(where_clause);
+    let targets = match options.db.is_empty() {
+        true => vec![quote! { DB }],
+        false => options.db.clone(),
     };
+    let mut expanded = proc_macro2::TokenStream::new();
+    for db in targets {
+        let mut generics = input.generics.clone();
+        let db_bound = match options.db.is_empty() {
+            true => {
+                generics.params.push(syn::parse_quote! { DB });
+                quote! { DB: ::sqlx::Database, }
+            }
+            false => quote! {},
+        };
+        let (impl_generics, _, _) = generics.split_for_impl();
+        let template = quote! { <&#call_ty as #krate::SqlTemplate<'_, #db>> };
+        expanded.extend(quote! {
+            impl #impl_generics #krate::ErasedSqlTemplate<#db> for #name #ty_generics
+                #where_clause
+                #db_bound
+                Self: ::std::marker::Send + ::std::marker::Sync,
+                for<'sql_erased> &'sql_erased #bound_ty: #krate::SqlTemplate<'sql_erased, #db>,
+            {
+                fn render_dyn_with_placeholder(
+                    &self,
+                    format_placeholder: ::std::option::Option<fn(usize, &mut String)>,
+                    sql_buffer: &mut String,
+                ) -> ::std::result::Result<
+                    ::std::option::Option<<#db as ::sqlx::Database>::Arguments>,
+                    #krate::Error,
+                > {
+                    #template::render_with_placeholder(self, format_placeholder, sql_buffer)
+                }
+                fn render_dyn(
+                    &self,
+                ) -> ::std::result::Result<
+                    (
+                        ::std::string::String,
+                        ::std::option::Option<<#db as ::sqlx::Database>::Arguments>,
+                    ),
+                    #krate::Error,
+                > {
+                    #template::render(self)
+                }
+                fn debug_params_dyn(&self) -> ::std::vec::Vec<#krate::DebugParam> {
+                    #template::debug_params(&self)
+                }
+                fn query_tag(&self) -> &'static str {
+                    match #template::QUERY_TAG {
+                        "" => ::std::any::type_name::<Self>(),
+                        tag => tag,
+                    }
+                }
+                fn template_fingerprint(&self) -> u64 {
+                    #template::TEMPLATE_FINGERPRINT
+                }
+                fn persistent(&self) -> bool {
+                    #template::PERSISTENT
+                }
+            }
+        });
+    }
+    expanded
+}
 
-    // 添加DB类型参数
-    wrapper_generics
-        .params
-        .push(syn::GenericParam::Type(syn::TypeParam {
-            attrs: Vec::new(),
-            ident: format_ident!("DB"),
-            colon_token: None,
-            bounds: syn::punctuated::Punctuated::new(),
-            eq_token: None,
-            default: None,
-        }));
+/// 以 `where` 开头、每个条件后跟逗号的 where 子句，可直接追加生成的约束
+///
+/// 原样输出用户的 where 子句时，最后一个条件没有逗号会与追加的约束连在一起。
+fn open_where_clause(where_clause: Option<&syn::WhereClause>) -> proc_macro2::TokenStream {
+    let predicates = where_clause.into_iter().flat_map(|wc| &wc.predicates);
+    quote! { where #(#predicates,)* }
+}
 
-    // 收集需要绑定的类型
-    let mut seen_types = BTreeSet::new();
-    let mut bound_types = proc_macro2::TokenStream::new();
+/// 结构体的生命周期都换成 `lifetime` 后的类型
+///
+/// `SqlTemplate` 只为数据生命周期不长于结构体生命周期的引用实现，
+/// 约束与调用中把结构体的生命周期都换成数据生命周期，`&self` 按协变缩短
+fn with_data_lifetime(
+    input: &DeriveInput,
+    lifetime: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let name = &input.ident;
+    let args = input.generics.params.iter().map(|param| match param {
+        syn::GenericParam::Lifetime(_) => lifetime.clone(),
+        syn::GenericParam::Type(ty) => ty.ident.to_token_stream(),
+        syn::GenericParam::Const(c) => c.ident.to_token_stream(),
+    });
+    match input.generics.params.is_empty() {
+        true => quote! { #name },
+        false => quote! { #name<#(#args),*> },
+    }
+}
 
-    // 处理字段类型
-    if let syn::Data::Struct(data_struct) = &input.data {
-        for field in &data_struct.fields {
-            let has_ignore = field
+/// 启用的数据库驱动 feature 对应的 `render_postgres()` 等固有方法，
+/// 只为结构体实现的数据库生成
+fn driver_render_impl(
+    input: &DeriveInput,
+    options: &SqlTemplateOptions,
+) -> proc_macro2::TokenStream {
+    #[allow(unused_mut)]
+    let mut drivers: Vec<(&str, proc_macro2::TokenStream, &str)> = Vec::new();
+    #[cfg(feature = "postgres")]
+    drivers.push(("render_postgres", quote! { ::sqlx::Postgres }, "PostgreSQL"));
+    #[cfg(feature = "mysql")]
+    drivers.push(("render_mysql", quote! { ::sqlx::MySql }, "MySQL"));
+    #[cfg(feature = "sqlite")]
+    drivers.push(("render_sqlite", quote! { ::sqlx::Sqlite }, "SQLite"));
+    let krate = options.krate();
+    let vis = &input.vis;
+    let bound_ty = with_data_lifetime(input, quote! { 'sql_render });
+    let methods = drivers.into_iter().filter(|(_, db, _)| {
+        options.db.is_empty() || options.db.iter().any(|d| d.to_string() == db.to_string())
+    });
+    let methods = methods.map(|(method, db, backend)| {
+        let method = format_ident!("{}", method);
+        let doc =
+            format!("Renders the SQL and its arguments for {backend}, see `SqlTemplate::render`");
+        quote! {
+            #[doc = #doc]
+            #vis fn #method<'sql_render>(
+                &'sql_render self,
+            ) -> ::std::result::Result<
+                (
+                    ::std::string::String,
+                    ::std::option::Option<<#db as ::sqlx::Database>::Arguments>,
+                ),
+                #krate::Error,
+            >
+            where
+                &'sql_render #bound_ty: #krate::SqlTemplate<'sql_render, #db>,
+            {
+                <&'sql_render #bound_ty as #krate::SqlTemplate<'sql_render, #db>>::render(self)
+            }
+        }
+    });
+    let methods: Vec<_> = methods.collect();
+    if methods.is_empty() {
+        return quote! {};
+    }
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    quote! {
+        #[allow(dead_code)]
+        impl #impl_generics #name #ty_generics #where_clause {
+            #(#methods)*
+        }
+    }
+}
+
+/// 解析 `#[query(name = "...", ...)]` 属性，返回查询名与模板参数
+fn parse_query_attrs(
+    input: &DeriveInput,
+    krate: &Path,
+) -> syn::Result<Vec<(syn::Ident, TemplateArgs)>> {
+    let mut queries: Vec<(syn::Ident, TemplateArgs)> = Vec::new();
+    for attr in &input.attrs {
+        if !attr.path().is_ident("query") {
+            continue;
+        }
+        let nested = attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+        let mut query_name = None;
+        let mut template = Vec::new();
+        for meta in nested {
+            match &meta {
+                Meta::NameValue(nv) if nv.path.is_ident("name") => {
+                    query_name = Some(lit_str(&meta)?.parse::<syn::Ident>()?);
+                }
+                _ => template.push(meta),
+            }
+        }
+        let Some(query_name) = query_name else {
+            return Err(syn::Error::new_spanned(attr, "missing `name = \"...\"`"));
+        };
+        if queries.iter().any(|(name, _)| *name == query_name) {
+            return Err(syn::Error::new_spanned(
+                &query_name,
+                format!("duplicate query `{query_name}`"),
+            ));
+        }
+        let mut args = TemplateArgs::default();
+        add_template_args(&mut args, template, krate)?;
+        queries.push((query_name, args));
+    }
+    if queries.is_empty() {
+        return Err(syn::Error::new(
+            input.ident.span(),
+            "`SqlTemplateSet` needs at least one `#[query(name = \"...\", ...)]`",
+        ));
+    }
+    Ok(queries)
+}
+
+/// `by_id` -> `ById`
+fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|c| c.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// 将多个具名查询组合在一个结构体上，每个 `#[query]` 生成一个返回
+/// `SqlTemplate` 实现的方法，查询共享结构体字段作为参数
+#[proc_macro_derive(
+    SqlTemplateSet,
+    attributes(
+        query,
+        sql_template,
+        add_type,
+        ignore_type,
+        sensitive,
+        param,
+        raw,
+        flatten,
+        fragment,
+        bind_with,
+        bind_as,
+        sql_cast
+    )
+)]
+pub fn sql_template_set(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let options = match parse_sql_template_attr(&input) {
+        Ok(options) if options.askama_template || options.display.is_some() => {
+            let attr = input
                 .attrs
                 .iter()
-                .any(|attr| attr.path().is_ident("ignore_type"));
-            if !has_ignore {
-                let ty = &field.ty;
-                let ident = get_type_identifier(ty);
-                if seen_types.insert(ident) {
-                    bound_types.extend(quote! {
-                        #ty: ::sqlx::Encode<#data_lifetime, DB> + ::sqlx::Type<DB>,
-                    });
+                .find(|a| a.path().is_ident("sql_template"));
+            return syn::Error::new(
+                attr.map_or_else(Span::call_site, Spanned::span),
+                "`askama_template` and `display` are not supported by `SqlTemplateSet`",
+            )
+            .to_compile_error()
+            .into();
+        }
+        Ok(options) => options,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let krate = options.krate();
+    let queries = match parse_query_attrs(&input, &krate) {
+        Ok(queries) => queries,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let name = &input.ident;
+    let vis = &input.vis;
+    let templates: Vec<_> = queries.iter().map(|(_, template)| template).collect();
+    let lists = list_binds(&input, &templates, &options.helper_prefix);
+    let analysis = match analyze(&input, &krate, lists) {
+        Ok(analysis) => analysis,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let Analysis {
+        sql_generics,
+        data_lifetime,
+        ..
+    } = &analysis;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let (sql_impl_generics, sql_ty_generics, _) = sql_generics.split_for_impl();
+    // 结构体没有或有多个生命周期参数时，方法引入数据生命周期
+    let method_generics = if input.generics.lifetimes().count() != 1 {
+        quote! { <#data_lifetime> }
+    } else {
+        quote! {}
+    };
+
+    let mut expanded = proc_macro2::TokenStream::new();
+    let mut methods = proc_macro2::TokenStream::new();
+    if options.strict
+        && let syn::Data::Struct(data) = &input.data
+        && let Err(e) = check_unused_fields(
+            &data.fields,
+            &queries
+                .iter()
+                .map(|(_, template)| template)
+                .collect::<Vec<_>>(),
+        )
+    {
+        return e.to_compile_error().into();
+    }
+    for (query_name, template_attrs) in &queries {
+        if let syn::Data::Struct(data) = &input.data
+            && let Err(e) = check_raw_interpolation(&data.fields, template_attrs)
+                .and_then(|()| check_sql_syntax(template_attrs, &options, &analysis.lists))
+        {
+            return e.to_compile_error().into();
+        }
+        let query_ty = format_ident!("{}{}", name, pascal_case(&query_name.to_string()));
+        let doc = format!("The `{query_name}` query of [`{name}`]");
+        let self_ty = quote! { #query_ty #sql_ty_generics };
+        expanded.extend(quote! {
+            #[doc = #doc]
+            #vis struct #query_ty #sql_generics (pub &#data_lifetime #name #ty_generics) #where_clause;
+
+            impl #sql_impl_generics ::std::clone::Clone for #self_ty #where_clause {
+                fn clone(&self) -> Self {
+                    *self
                 }
             }
+            impl #sql_impl_generics ::std::marker::Copy for #self_ty #where_clause {}
+        });
+        let param_consts = param_consts(template_attrs, &analysis.lists, &options.helper_prefix);
+        expanded.extend(quote! {
+            impl #sql_impl_generics #self_ty #where_clause {
+                #param_consts
+            }
+        });
+        expanded.extend(wrapper_impl(
+            &input,
+            &analysis,
+            &options,
+            template_attrs,
+            self_ty.clone(),
+            quote! { self.0 },
+            &format!("{name}::{query_name}"),
+        ));
+        methods.extend(quote! {
+            #[doc = #doc]
+            #vis fn #query_name #method_generics (&#data_lifetime self) -> #self_ty {
+                #query_ty(self)
+            }
+        });
+    }
+    expanded.extend(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            #methods
+        }
+    });
+    if options.builder {
+        match builder_impl(&input, &krate) {
+            Ok(builder) => expanded.extend(builder),
+            Err(e) => return e.to_compile_error().into(),
         }
     }
+    expanded.into()
+}
 
-    // 处理addtype属性
-    for attr in &input.attrs {
-        if attr.path().is_ident("add_type")
-            && let Meta::List(meta_list) = &attr.meta
-        {
-            let parser = syn::punctuated::Punctuated::<syn::Type, syn::Token![,]>::parse_terminated;
-            if let Ok(types) = parser.parse2(meta_list.tokens.clone()) {
-                for ty in types {
-                    let ident = get_type_identifier(&ty);
-                    let have_lifetime = ident.0.contains('\'');
-                    if seen_types.insert(ident) {
-                        if have_lifetime {
-                            //非引用类型且包含生命周期如slef.Vec<i64>.first()->Option<&'a i64>数据来源自结构体本身的字段生命周期相同;或者如&str这样的静态引用，使用结构体本身生命周期
-                            bound_types.extend(quote! {
-                                #ty: ::sqlx::Encode<#data_lifetime, DB> + ::sqlx::Type<DB>,
-                            });
-                        } else {
-                            bound_types.extend(quote! {
-                            #ty: for<'template_local_lifetime> ::sqlx::Encode<'template_local_lifetime, DB> + ::sqlx::Type<DB>,
-                        });
-                        }
-                    }
-                }
+/// 可被其他模板通过 `#[flatten]` 嵌入的参数结构体，实现 `SqlParams`
+#[proc_macro_derive(SqlParams, attributes(sql_params, sensitive, param, raw, flatten))]
+pub fn sql_params(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let mut krate: Path = syn::parse_quote! { ::sqlx_askama_template };
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|a| a.path().is_ident("sql_params"))
+    {
+        let parsed = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("crate") {
+                krate = parse_crate_path(&meta)?;
+                Ok(())
+            } else {
+                Err(meta.error("unknown `sql_params` option"))
+            }
+        });
+        if let Err(e) = parsed {
+            return e.to_compile_error().into();
+        }
+    }
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let syn::Data::Struct(data_struct) = &input.data else {
+        return syn::Error::new_spanned(name, "`SqlParams` can only be derived for structs")
+            .to_compile_error()
+            .into();
+    };
+    if let Err(e) = data_struct
+        .fields
+        .iter()
+        .try_for_each(|field| is_secret(field).map(drop))
+    {
+        return e.to_compile_error().into();
+    }
+    let debug_params = debug_params(&data_struct.fields, &krate);
+    quote! {
+        impl #impl_generics #krate::SqlParams for #name #ty_generics #where_clause {
+            fn debug_params(&self) -> ::std::vec::Vec<#krate::DebugParam> {
+                #[allow(unused_imports)]
+                use #krate::{DebugFallback as _, DebugValue as _, StringFallback as _, StringValue as _};
+                let data = self;
+                #debug_params
             }
         }
     }
+    .into()
+}
+
+/// 声明模板中可用的自定义过滤器，等同于 `askama::filter_fn`，但无需直接依赖 askama
+///
+/// askama 生成的过滤器代码引用 `askama::` 路径，这里把函数放进隐藏模块，
+/// 以 glob 方式引入外层作用域和 `::sqlx_askama_template::askama`（外层已有同一
+/// askama 时不冲突），再以原可见性重新导出。本库经门面 crate 引入时用
+/// `#[sql_filter(crate = "...")]` 指定其路径
+#[proc_macro_attribute]
+pub fn sql_filter(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut krate: Path = syn::parse_quote! { ::sqlx_askama_template };
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("crate") {
+            krate = parse_crate_path(&meta)?;
+            Ok(())
+        } else {
+            Err(meta.error("unknown `sql_filter` option, expected `crate`"))
+        }
+    });
+    parse_macro_input!(attr with parser);
+    let mut filter = parse_macro_input!(item as syn::ItemFn);
+    let name = filter.sig.ident.clone();
+    let vis = std::mem::replace(&mut filter.vis, syn::parse_quote! { pub });
+    let module = format_ident!("__sql_filter_{}", name);
+    quote! {
+        #[doc(hidden)]
+        #[allow(non_snake_case)]
+        mod #module {
+            #[allow(unused_imports)]
+            use super::*;
+            #[allow(unused_imports)]
+            use #krate::filter_support::*;
+
+            #[askama::filter_fn]
+            #filter
+        }
+        #vis use #module::#name;
+    }
+    .into()
+}
 
-    let (_impl_generics, ty_generics, where_clause) = generics.split_for_impl();
-    let where_clause = where_clause.map_or_else(|| quote! { where }, |wc| quote! { #wc });
+/// 基于 Wrapper 的 `SqlTemplate` 实现，`data` 为模板数据 `&'q Name` 的表达式
+fn wrapper_impl(
+    input: &DeriveInput,
+    analysis: &Analysis,
+    options: &SqlTemplateOptions,
+    template: &TemplateArgs,
+    self_ty: proc_macro2::TokenStream,
+    data: proc_macro2::TokenStream,
+    tag: &str,
+) -> proc_macro2::TokenStream {
+    let name = &input.ident;
+    let Analysis {
+        wrapper_generics,
+        sql_generics,
+        data_lifetime,
+        bound_types,
+        debug_params,
+        bindings,
+        lists,
+    } = analysis;
+    let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+    let where_clause = open_where_clause(where_clause);
     let (wrapper_impl_generics, _, _) = wrapper_generics.split_for_impl();
+    let (sql_impl_generics, _, _) = sql_generics.split_for_impl();
 
-    let expanded = quote! {
-        impl #wrapper_impl_generics ::sqlx_askama_template::SqlTemplate<#data_lifetime, DB>
-            for &#data_lifetime #name #ty_generics
-            #where_clause
-            DB: ::sqlx::Database,
-            #bound_types
-        {
-            fn render_with_placeholder(
-                self,
-                format_placeholder: ::std::option::Option<fn(usize, &mut String)>,
-                sql_buffer: &mut String,
-            ) -> ::std::result::Result<
-                ::std::option::Option<DB::Arguments>,
-                ::sqlx::Error,
-            > {
-                #[derive(::sqlx_askama_template::askama::Template)]
-                #[template(#template_attrs)]
-                struct Wrapper #wrapper_generics (
-                    ::sqlx_askama_template::TemplateArg<#data_lifetime,DB, #name #ty_generics>
-                ) #where_clause
+    // bind_with 字段在 Wrapper 上以同名字段保存转换结果，遮蔽原字段；
+    // 结果类型由推断得到，作为 Wrapper 的类型参数
+    let mut bind_generics = wrapper_generics.clone();
+    let mut bind_fields = proc_macro2::TokenStream::new();
+    let mut bind_values = proc_macro2::TokenStream::new();
+    let mut bind_bounds = proc_macro2::TokenStream::new();
+    for (i, (field, path)) in bindings.iter().enumerate() {
+        let param = format_ident!("SqlxBind{}", i);
+        bind_generics.params.push(syn::parse_quote! { #param });
+        bind_fields.extend(quote! { #field: #param, });
+        bind_values.extend(quote! { #field: #path(&data.#field), });
+        bind_bounds.extend(quote_spanned! {path.span()=>
+            #param: ::sqlx::Encode<#data_lifetime, DB> + ::sqlx::Type<DB>,
+        });
+    }
+    let (bind_impl_generics, bind_ty_generics, _) = bind_generics.split_for_impl();
+    let overrides = options.overrides(tag, template);
+    let registry_entry = options.registry_entry(tag, template);
+    let krate = options.krate();
+    let TemplateArgs {
+        args: template_attrs,
+        tracked,
+        ..
+    } = template;
+
+    // 未指定 `db` 时对 `DB` 泛型实现，否则为每个数据库生成具体实现；
+    // 渲染函数内的 Wrapper 始终对 `DB` 泛型
+    let targets: Vec<_> = if options.db.is_empty() {
+        vec![(
+            quote! { #wrapper_impl_generics },
+            quote! { DB },
+            quote! { DB: ::sqlx::Database, #bound_types },
+        )]
+    } else {
+        options
+            .db
+            .iter()
+            .map(|db| {
+                (
+                    quote! { #sql_impl_generics },
+                    db.clone(),
+                    replace_db(bound_types.clone(), db),
+                )
+            })
+            .collect()
+    };
+
+    // 带 `{% block count %}` 时主查询不含该块，另生成只渲染该块的计数查询
+    let count_args = count_block_args(template);
+    let (main_attrs, count_attrs) = match &count_args {
+        Some((main, count)) => (main, Some(count)),
+        None => (template_attrs, None),
+    };
+    // `#[param(secret)]` 与 `#[sql_cast]` 字段的绑定改用对应的辅助函数，
+    // 列表字段的 `e()` 改用 `e_in()`
+    let mut marks = bind_marks(input);
+    marks.retain(|mark| !mark.list || lists.contains(&mark.name));
+    let prefix = &options.helper_prefix;
+    let main_attrs = &match mark_field_binds(main_attrs, &marks, prefix) {
+        Ok(attrs) => attrs,
+        Err(e) => return e.to_compile_error(),
+    };
+    let count_attrs = match count_attrs
+        .map(|attrs| mark_field_binds(attrs, &marks, prefix))
+        .transpose()
+    {
+        Ok(attrs) => attrs,
+        Err(e) => return e.to_compile_error(),
+    };
+    let count_attrs = count_attrs.as_ref();
+    // 渲染出的 SQL 不变的模板另生成只渲染绑定的 `render_arguments`
+    let binds_attrs = static_binds(template, lists, prefix).map(|binds| {
+        let binds: Punctuated<Meta, Token![,]> = template_attrs
+            .iter()
+            .map(|meta| match meta.path().is_ident("source") {
+                true => syn::parse_quote! { source = #binds },
+                false => meta.clone(),
+            })
+            .collect();
+        mark_field_binds(&binds, &marks, prefix)
+    });
+    let binds_attrs = match binds_attrs.transpose() {
+        Ok(attrs) => attrs,
+        Err(e) => return e.to_compile_error(),
+    };
+
+    // `include_fragment` 与加前缀的辅助函数转发给 `TemplateArg`，
+    // 其余不加前缀的辅助函数经由 `Deref` 使用
+    let wrapper_helpers = {
+        let forward = |call| quote! { self.template_arg.#call };
+        let mut helpers = fragment_helper("", &quote! { DB }, &krate, forward);
+        if !options.helper_prefix.is_empty() {
+            helpers.extend(helper_fns(
+                &options.helper_prefix,
+                &quote! { DB },
+                &krate,
+                forward,
+            ));
+        }
+        quote! {
+            #[allow(dead_code)]
+            impl #bind_impl_generics Wrapper #bind_ty_generics
+                #where_clause
+                DB: ::sqlx::Database,
+                #bound_types
+                #bind_bounds
+            {
+                #helpers
+            }
+        }
+    };
+
+    let mut expanded = proc_macro2::TokenStream::new();
+    for (impl_generics, db, bounds) in targets {
+        let debug_params = replace_db(debug_params.clone(), &db);
+        // 以 `attrs` 为模板参数渲染的方法体，使用 `format_placeholder` 与 `sql_buffer`；
+        // 只渲染绑定时不检查 SQL
+        let render_body = |attrs: &Punctuated<Meta, Token![,]>, lint: bool| {
+            let start = lint.then(|| quote! { let start = sql_buffer.len(); });
+            let lint = lint.then(|| {
+                quote! {
+                    #krate::lint_rendered_sql(
+                        ::std::any::type_name::<Self>(),
+                        &sql_buffer[start..],
+                        || <Self as #krate::SqlTemplate<#data_lifetime, #db>>::debug_params(&self),
+                    )?;
+                }
+            });
+            quote! {
+                #tracked
+                #[derive(#krate::askama::Template)]
+                #[template(#attrs)]
+                struct Wrapper #bind_generics #where_clause
                     DB: ::sqlx::Database,
-                    #bound_types;
+                    #bound_types
+                    #bind_bounds
+                {
+                    template_arg: #krate::TemplateArg<#data_lifetime,DB, #name #ty_generics>,
+                    #bind_fields
+                }
 
-                impl #wrapper_impl_generics ::std::ops::Deref for Wrapper #wrapper_generics
+                #wrapper_helpers
+
+                impl #bind_impl_generics ::std::ops::Deref for Wrapper #bind_ty_generics
                     #where_clause
                     DB: ::sqlx::Database,
                     #bound_types
+                    #bind_bounds
                 {
-                    type Target = ::sqlx_askama_template::TemplateArg<#data_lifetime, DB, #name #ty_generics>;
+                    type Target = #krate::TemplateArg<#data_lifetime, DB, #name #ty_generics>;
                     fn deref(&self) -> &Self::Target {
-                        &self.0
+                        &self.template_arg
                     }
                 }
 
-                let mut wrapper = Wrapper(::sqlx_askama_template::TemplateArg::new(self));
-                if let Some(format_placeholder) = format_placeholder {
-                    wrapper.0.set_format_placeholder_fn(format_placeholder);
-                }
-                let render_res = ::sqlx_askama_template::askama::Template::render_into(&wrapper, sql_buffer)
-                    .map_err(|e| ::sqlx::Error::Encode(::std::boxed::Box::new(e)))?;
-                let arg = wrapper.get_arguments();
-                let encode_err = wrapper.get_err();
+                #krate::render_guarded(
+                    ::std::any::type_name::<Self>(),
+                    sql_buffer,
+                    |sql_buffer| {
+                        let data = #data;
+                        let mut wrapper = Wrapper {
+                            template_arg: #krate::TemplateArg::<#db, _>::new(data),
+                            #bind_values
+                        };
+                        if let Some(format_placeholder) = format_placeholder {
+                            wrapper.template_arg.set_format_placeholder_fn(format_placeholder);
+                        }
+                        #start
+                        #krate::askama::Template::render_into(&wrapper, sql_buffer)
+                            .map_err(|e| #krate::Error::Render(
+                                ::sqlx::Error::Encode(::std::boxed::Box::new(e)).into(),
+                            ))?;
+                        #lint
+                        let arg = wrapper.get_arguments();
+                        let encode_err = wrapper.get_err();
 
-                if let Some(e) = encode_err {
-                    return ::std::result::Result::Err(e);
+                        if let Some(e) = encode_err {
+                            return ::std::result::Result::Err(#krate::Error::Encode(e.into()));
+                        }
+                        ::std::result::Result::Ok(arg)
+                    },
+                )
+            }
+        };
+        // 以 `attrs` 为模板参数渲染的方法
+        let render_fn = |fn_name: syn::Ident, attrs: &Punctuated<Meta, Token![,]>| {
+            let body = render_body(attrs, true);
+            quote! {
+                fn #fn_name(
+                    self,
+                    format_placeholder: ::std::option::Option<fn(usize, &mut String)>,
+                    sql_buffer: &mut String,
+                ) -> ::std::result::Result<
+                    ::std::option::Option<<#db as ::sqlx::Database>::Arguments>,
+                    #krate::Error,
+                > {
+                    #body
+                }
+            }
+        };
+        let render = render_fn(format_ident!("render_with_placeholder"), main_attrs);
+        let render_count = count_attrs.map(|attrs| {
+            let render_count = render_fn(format_ident!("render_count_with_placeholder"), attrs);
+            quote! {
+                const HAS_COUNT_QUERY: bool = true;
+                #render_count
+            }
+        });
+        let render_arguments = binds_attrs.as_ref().map(|attrs| {
+            let body = render_body(attrs, false);
+            quote! {
+                const STATIC_SQL: bool = true;
+                fn render_arguments(
+                    self,
+                ) -> ::std::result::Result<
+                    ::std::option::Option<<#db as ::sqlx::Database>::Arguments>,
+                    #krate::Error,
+                > {
+                    let format_placeholder: ::std::option::Option<fn(usize, &mut String)> =
+                        ::std::option::Option::None;
+                    let sql_buffer = &mut ::std::string::String::new();
+                    #body
                 }
-                ::std::result::Result::Ok(arg)
+            }
+        });
+        expanded.extend(quote! {
+        impl #impl_generics #krate::SqlTemplate<#data_lifetime, #db>
+            for #self_ty
+            #where_clause
+            #bounds
+        {
+            #overrides
+            #render
+            #render_count
+            #render_arguments
+
+            fn debug_params(&self) -> ::std::vec::Vec<#krate::DebugParam> {
+                #[allow(unused_imports)]
+                use #krate::{DebugFallback as _, DebugValue as _, StringFallback as _, StringValue as _};
+                let data = #data;
+                #debug_params
             }
         }
-    };
+        });
+    }
+    expanded.extend(registry_entry);
+    expanded
+}
 
-    expanded.into()
+/// 将生成代码中的 `DB` 类型参数替换为具体的数据库类型
+fn replace_db(
+    tokens: proc_macro2::TokenStream,
+    db: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    tokens
+        .into_iter()
+        .map(|tree| match tree {
+            proc_macro2::TokenTree::Ident(ident) if ident == "DB" => {
+                proc_macro2::TokenStream::from_iter(db.clone().into_iter().map(|mut t| {
+                    t.set_span(ident.span());
+                    t
+                }))
+            }
+            proc_macro2::TokenTree::Group(group) => {
+                let mut replaced =
+                    proc_macro2::Group::new(group.delimiter(), replace_db(group.stream(), db));
+                replaced.set_span(group.span());
+                proc_macro2::TokenTree::Group(replaced).into()
+            }
+            tree => tree.into(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn askama_dirs_of_general_section() {
+        let config = r#"
+            [general]
+            # lookup order
+            dirs = ["sql", 'raw#dir', # trailing comment
+                "a\"b"]
+            whitespace = "suppress"
+            [[escaper]]
+            dirs = ["ignored"]
+        "#;
+        assert_eq!(
+            askama_dirs(config),
+            Some(vec![
+                "sql".to_string(),
+                "raw#dir".to_string(),
+                "a\"b".to_string()
+            ])
+        );
+    }
+
+    fn template(args: proc_macro2::TokenStream) -> TemplateArgs {
+        TemplateArgs {
+            args: syn::parse_quote! { #args },
+            ..TemplateArgs::default()
+        }
+    }
+
+    #[test]
+    fn static_params_in_bind_order() {
+        let args = template(quote! {
+            source = "select {# e(skipped) #} {{ e(self.id) }}, {{e_named(\"user\", user.name)}}, {{ table }}"
+        });
+        assert_eq!(
+            static_params(&args, &[], ""),
+            Some(vec!["self.id".to_string(), "user.name".to_string()])
+        );
+    }
+
+    #[test]
+    fn static_params_of_rewritten_binds() {
+        let args = template(quote! {
+            source = "select {{ sql_cast(e_secret(token), \"::text\") }}, {{ e_named_secret(\"a, b\", id) }}, \
+                      {{ sql_cast(e_named(\"x\", self.x), \"::uuid\") }}"
+        });
+        assert_eq!(
+            static_params(&args, &[], ""),
+            Some(vec![
+                "token".to_string(),
+                "id".to_string(),
+                "self.x".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn static_params_of_prefixed_helpers() {
+        let args = template(quote! {
+            source = "select {{ sql_e(id) }}, {{ sql_e_named(\"n\", name) }}, \
+                      {{ sql_sql_cast(sql_e_secret(token), \"::text\") }}, {{ e(plain) }}"
+        });
+        assert_eq!(
+            static_params(&args, &[], "sql_"),
+            Some(vec![
+                "id".to_string(),
+                "name".to_string(),
+                "token".to_string(),
+                "plain".to_string()
+            ])
+        );
+        let args = template(quote! { source = "select {{ sql_e(id) }}" });
+        assert_eq!(static_params(&args, &[], ""), None);
+    }
+
+    #[test]
+    fn static_params_give_up() {
+        for args in [
+            quote! { source = "select {{ el(ids) }}" },
+            quote! { source = "select {% if a %}{{ e(a) }}{% endif %}" },
+            quote! { source = "select {{ e(a + 1) }}" },
+            quote! { source = "select {{ e(a) }}", syntax = "custom" },
+            quote! { path = "query.sql" },
+            quote! { source = "select {{ e(id) }}, {{ e(self.ids) }}" },
+            quote! { source = "select {{ sql_cast(e(id), cast) }}" },
+            quote! { source = "select {{ sql_cast(el(ids), \"::int\") }}" },
+        ] {
+            let lists = ["ids".to_string()];
+            assert_eq!(
+                static_params(&template(args.clone()), &lists, ""),
+                None,
+                "{args}"
+            );
+        }
+    }
+
+    #[test]
+    fn static_binds_keep_bind_tags() {
+        let args = template(quote! {
+            source = "insert into t values ({{ e(id) }}, {{- e_named(\"n\", name) }}) {# {{ x }} #}"
+        });
+        assert_eq!(
+            static_binds(&args, &[], "").as_deref(),
+            Some("{{ e(id) }}{{- e_named(\"n\", name) }}")
+        );
+        let args = template(quote! { source = "insert into {{ table }} values ({{ e(id) }})" });
+        assert_eq!(static_binds(&args, &[], ""), None);
+    }
+
+    fn mark(name: &str, secret: bool, cast: Option<&str>) -> BindMark {
+        BindMark {
+            name: name.to_string(),
+            secret,
+            cast: cast.map(str::to_string),
+            list: false,
+            secret_attr: quote! {},
+            cast_attr: quote! {},
+        }
+    }
+
+    #[test]
+    fn bind_tag_rewrites() {
+        let marks = [mark("token", true, None), mark("id", false, Some("::uuid"))];
+        let rewrite = |source: &str| rewrite_field_binds(source, &marks, "");
+        assert_eq!(rewrite("{{ e(token) }}"), "{{ e_secret(token) }}");
+        assert_eq!(
+            rewrite("{{ e_named(\"id, x\", self.id) }}"),
+            "{{ sql_cast(e_named(\"id, x\", self.id), \"::uuid\") }}"
+        );
+        assert_eq!(
+            rewrite("{% let v = el(id) %}"),
+            "{% let v = sql_cast(el(id), \"::uuid\") %}"
+        );
+        for unchanged in [
+            "{{ e(other) }}",
+            "{{ e(tokens) }}",
+            "{{ x.e(token) }}",
+            "{{ \"e(token)\" }}",
+            "{# e(token) #}",
+            "e(token)",
+            "{% raw %}{{ e(token) }}{% endraw %}",
+        ] {
+            assert_eq!(rewrite(unchanged), unchanged);
+        }
+    }
+
+    #[test]
+    fn bind_tag_keeps_helper_prefix() {
+        let marks = [mark("token", true, Some("::text"))];
+        assert_eq!(
+            rewrite_bind_tag("{ sql_e(token) ", &marks, "sql_"),
+            "{ sql_sql_cast(sql_e_secret(token), \"::text\") "
+        );
+    }
+
+    #[test]
+    fn bind_tag_rewrites_lists() {
+        let marks = [BindMark {
+            list: true,
+            ..mark("ids", false, None)
+        }];
+        let rewrite = |source: &str| rewrite_field_binds(source, &marks, "sql_");
+        assert_eq!(rewrite("{{ e(ids) }}"), "{{ e_in(ids) }}");
+        assert_eq!(
+            rewrite("{{ sql_e(&self.ids ) }}"),
+            "{{ sql_e_in(&self.ids ) }}"
+        );
+        for unchanged in [
+            "{{ e(ids[0]) }}",
+            "{{ e(ids.len()) }}",
+            "{{ el(ids) }}",
+            "{{ e_named(\"ids\", ids) }}",
+        ] {
+            assert_eq!(rewrite(unchanged), unchanged);
+        }
+    }
+
+    #[test]
+    fn list_element_types() {
+        let elem = |ty: syn::Type| list_element(&ty).map(|elem| quote!(#elem).to_string());
+        assert_eq!(elem(syn::parse_quote!(Vec<i64>)).as_deref(), Some("i64"));
+        assert_eq!(
+            elem(syn::parse_quote!(&'a [String])).as_deref(),
+            Some("String")
+        );
+        assert_eq!(elem(syn::parse_quote!([i32; 3])).as_deref(), Some("i32"));
+        assert_eq!(
+            elem(syn::parse_quote!(&'a Vec<f64>)).as_deref(),
+            Some("f64")
+        );
+        for ty in [
+            quote!(Vec<u8>),
+            quote!(&'a [u8]),
+            quote!(Vec<&'a str>),
+            quote!(Option<Vec<i64>>),
+            quote!(String),
+        ] {
+            assert_eq!(elem(syn::parse2(ty.clone()).unwrap()), None, "{ty}");
+        }
+    }
+
+    #[test]
+    fn askama_dirs_unset() {
+        assert_eq!(askama_dirs("[general]\nwhitespace = \"minimize\"\n"), None);
+        assert_eq!(askama_dirs("[other]\ndirs = [\"sql\"]\n"), None);
+    }
+
+    #[cfg(feature = "validate-sql")]
+    #[test]
+    fn sql_skeleton_of_template() {
+        let source = "select * from {schema}.users where id = {{ e(id) }} \
+                      {% if all %}and 1 = 1{% else %}and id in {{ el(ids) }}{% endif %}";
+        assert_eq!(
+            sql_skeleton(source, "", true, true).as_deref(),
+            Some("select * from sql_schema.users where id = $1 and 1 = 1")
+        );
+        assert_eq!(
+            sql_skeleton(source, "", false, false).as_deref(),
+            Some("select * from sql_schema.users where id = ? and id in (?)")
+        );
+        assert_eq!(
+            sql_skeleton("where {{ sql_include(f) }}", "", true, true),
+            None
+        );
+    }
 }
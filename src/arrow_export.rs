@@ -0,0 +1,154 @@
+use std::sync::Arc;
+
+use arrow_array::{
+    ArrayRef, RecordBatch,
+    builder::{BooleanBuilder, Float64Builder, Int64Builder, StringBuilder},
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use sqlx_core::Error;
+
+use crate::export::{ColumnValue, RowValues};
+
+/// Converts a chunk of rows into an Arrow record batch
+///
+/// When `schema` is `None` it is inferred from the first non-null value of each
+/// column (`Int64`, `Float64`, `Boolean`, otherwise `Utf8`) and stored, so later
+/// batches of the same query share it.
+pub fn rows_to_record_batch<R: RowValues>(
+    rows: &[R],
+    schema: &mut Option<SchemaRef>,
+) -> Result<RecordBatch, Error> {
+    let width = rows.first().map_or(0, |row| row.columns().len());
+    let mut columns = vec![Vec::with_capacity(rows.len()); width];
+    for row in rows {
+        for (i, column) in columns.iter_mut().enumerate() {
+            column.push(row.column_value(i)?);
+        }
+    }
+
+    let schema = match schema {
+        Some(schema) => schema.clone(),
+        None => {
+            let names = rows
+                .first()
+                .map(|row| row.column_names())
+                .unwrap_or_default();
+            let fields: Vec<Field> = names
+                .into_iter()
+                .zip(&columns)
+                .map(|(name, values)| Field::new(name, infer_type(values), true))
+                .collect();
+            schema.insert(Arc::new(Schema::new(fields))).clone()
+        }
+    };
+
+    let arrays = schema
+        .fields()
+        .iter()
+        .zip(&columns)
+        .map(|(field, values)| build_array(field, values))
+        .collect::<Result<Vec<ArrayRef>, Error>>()?;
+    RecordBatch::try_new(schema, arrays).map_err(|e| Error::Decode(Box::new(e)))
+}
+
+fn infer_type(values: &[ColumnValue]) -> DataType {
+    match values.iter().find(|v| **v != ColumnValue::Null) {
+        Some(ColumnValue::Int(_)) => DataType::Int64,
+        Some(ColumnValue::Float(_)) => DataType::Float64,
+        Some(ColumnValue::Bool(_)) => DataType::Boolean,
+        _ => DataType::Utf8,
+    }
+}
+
+fn build_array(field: &Field, values: &[ColumnValue]) -> Result<ArrayRef, Error> {
+    let mismatch = |v: &ColumnValue| {
+        Error::Decode(
+            format!(
+                "column `{}` expected {}, found {v:?}",
+                field.name(),
+                field.data_type()
+            )
+            .into(),
+        )
+    };
+    let array: ArrayRef = match field.data_type() {
+        DataType::Int64 => {
+            let mut b = Int64Builder::with_capacity(values.len());
+            for v in values {
+                match v {
+                    ColumnValue::Null => b.append_null(),
+                    ColumnValue::Int(i) => b.append_value(*i),
+                    v => return Err(mismatch(v)),
+                }
+            }
+            Arc::new(b.finish())
+        }
+        DataType::Float64 => {
+            let mut b = Float64Builder::with_capacity(values.len());
+            for v in values {
+                match v {
+                    ColumnValue::Null => b.append_null(),
+                    ColumnValue::Float(f) => b.append_value(*f),
+                    ColumnValue::Int(i) => b.append_value(*i as f64),
+                    v => return Err(mismatch(v)),
+                }
+            }
+            Arc::new(b.finish())
+        }
+        DataType::Boolean => {
+            let mut b = BooleanBuilder::with_capacity(values.len());
+            for v in values {
+                match v {
+                    ColumnValue::Null => b.append_null(),
+                    ColumnValue::Bool(x) => b.append_value(*x),
+                    v => return Err(mismatch(v)),
+                }
+            }
+            Arc::new(b.finish())
+        }
+        _ => {
+            let mut b = StringBuilder::new();
+            let mut text = String::new();
+            for v in values {
+                if *v == ColumnValue::Null {
+                    b.append_null();
+                } else {
+                    text.clear();
+                    v.write_text(&mut text);
+                    b.append_value(&text);
+                }
+            }
+            Arc::new(b.finish())
+        }
+    };
+    Ok(array)
+}
+
+/// Writes a stream of record batches (e.g. from `fetch_arrow`) as a Parquet file
+///
+/// # Returns
+/// Number of rows written; nothing is written for an empty stream
+#[cfg(feature = "parquet")]
+pub async fn write_parquet<W, S>(writer: W, batches: S) -> Result<u64, crate::Error>
+where
+    W: std::io::Write + Send,
+    S: futures_core::Stream<Item = Result<RecordBatch, crate::Error>>,
+{
+    use futures_util::{TryStreamExt, pin_mut};
+    use parquet::arrow::ArrowWriter;
+
+    let to_io = |e: parquet::errors::ParquetError| crate::Error::from(std::io::Error::other(e));
+    pin_mut!(batches);
+    let Some(first) = batches.try_next().await? else {
+        return Ok(0);
+    };
+    let mut rows = first.num_rows() as u64;
+    let mut arrow_writer = ArrowWriter::try_new(writer, first.schema(), None).map_err(to_io)?;
+    arrow_writer.write(&first).map_err(to_io)?;
+    while let Some(batch) = batches.try_next().await? {
+        rows += batch.num_rows() as u64;
+        arrow_writer.write(&batch).map_err(to_io)?;
+    }
+    arrow_writer.close().map_err(to_io)?;
+    Ok(rows)
+}
@@ -0,0 +1,92 @@
+use sqlx_core::{connection::Connection, database::Database, executor::Executor};
+
+use crate::{
+    DatabaseDialect, Error, ErrorContext, QueryMeta, SqlTemplate, SqlTemplateExecute,
+    detect_backend_db, middleware::QueryMetaExt, with_identifier_quoting,
+};
+
+/// Executes one statement per template of `templates`, in order, on `conn`
+/// (e.g. `&mut *tx` or `&mut *pool.acquire().await?`)
+///
+/// Each template is rendered for its arguments; as long as the rendered SQL
+/// is the same, every execution reuses one prepared statement (unless the
+/// template is not [`SqlTemplate::PERSISTENT`]). The SQL of a
+/// [`SqlTemplate::STATIC_SQL`] template is rendered once, the following
+/// templates only encoding their arguments. A failing item
+/// does not stop the batch; inside a transaction it usually fails the
+/// following ones too. Middlewares and observers of [`crate::DBAdapter`] do
+/// not apply.
+///
+/// # Returns
+/// One result per template, or an error if the backend could not be detected
+///
+/// # Example
+/// ```no_run
+/// use sqlx::SqlitePool;
+/// use sqlx_askama_template::{SqlTemplate, execute_batch};
+///
+/// #[derive(SqlTemplate)]
+/// #[template(source = "insert into users (name) values ({{ e(name) }})")]
+/// struct InsertUser {
+///     name: String,
+/// }
+///
+/// # async fn run(pool: SqlitePool, names: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+/// let mut tx = pool.begin().await?;
+/// let inserts: Vec<InsertUser> = names.into_iter().map(|name| InsertUser { name }).collect();
+/// let results = execute_batch(&inserts, &mut *tx).await?;
+/// tx.commit().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn execute_batch<'q, DB, T, I, C>(
+    templates: I,
+    conn: &mut C,
+) -> Result<Vec<Result<DB::QueryResult, Error>>, Error>
+where
+    DB: Database,
+    T: SqlTemplate<'q, DB>,
+    I: IntoIterator<Item = T>,
+    C: Connection<Database = DB> + 'static,
+    for<'c> &'c mut C: Executor<'c, Database = DB>,
+{
+    let mut ctx = ErrorContext::new(QueryMeta::for_template::<DB, T>("execute_batch"));
+    let db_type = detect_backend_db(&mut *conn)
+        .await
+        .map_err(|e| ctx.wrap(e))?
+        .0;
+    ctx.set_backend(db_type.backend_name());
+    let f = db_type.placeholder_fn();
+    // context with the SQL of the last execution, kept while the SQL is the same
+    let mut executed = ctx.clone();
+    let mut last_sql: Option<String> = None;
+    let mut results = Vec::new();
+    for template in templates {
+        let rendered = match last_sql.as_ref().filter(|_| T::STATIC_SQL) {
+            Some(sql) => template.render_arguments().map(|arg| (sql.clone(), arg)),
+            None => {
+                let mut sql = String::new();
+                with_identifier_quoting(&db_type, || template.render_with_placeholder(f, &mut sql))
+                    .map(|arg| (sql, arg))
+            }
+        };
+        let (sql, arg) = match rendered {
+            Ok(rendered) => rendered,
+            Err(e) => {
+                results.push(Err(ctx.wrap(e)));
+                continue;
+            }
+        };
+        if last_sql.as_ref() != Some(&sql) {
+            executed.set_sql(&sql);
+            last_sql = Some(sql.clone());
+        }
+        let result = SqlTemplateExecute::<DB>::new(sql, arg)
+            .set_persistent(T::PERSISTENT)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| executed.wrap(e));
+        results.push(result);
+    }
+    Ok(results)
+}
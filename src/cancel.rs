@@ -0,0 +1,96 @@
+use std::{
+    io,
+    sync::{
+        Arc, OnceLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use futures_core::{future::BoxFuture, stream::BoxStream};
+use futures_util::{TryStreamExt, future};
+use sqlx_core::{database::Database, executor::Executor, pool::Pool};
+
+use crate::{DatabaseDialect, Error, SqlTemplateExecute};
+
+static NEXT_TAG: AtomicU64 = AtomicU64::new(0);
+
+/// Comment marking the SQL of a cancellable query, so that another connection
+/// can find and cancel it on the server
+pub(crate) struct CancelTag {
+    tag: String,
+    statement: OnceLock<String>,
+}
+
+impl CancelTag {
+    /// Unique tag made of letters, digits and dashes only, so it needs no
+    /// escaping in string literals or `LIKE` patterns
+    pub(crate) fn new() -> Self {
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let tag = format!(
+            "sqlx-askama-cancel-{:x}-{started:x}-{:x}",
+            std::process::id(),
+            NEXT_TAG.fetch_add(1, Ordering::Relaxed)
+        );
+        Self {
+            tag,
+            statement: OnceLock::new(),
+        }
+    }
+    /// Prefixes `sql` with the tag if the dialect can cancel a query by it,
+    /// returning whether it did
+    pub(crate) fn apply(&self, db_type: &impl DatabaseDialect, sql: &mut String) -> bool {
+        let Some(statement) = db_type.cancel_statement(&self.tag) else {
+            return false;
+        };
+        self.statement.get_or_init(|| statement);
+        sql.insert_str(0, &format!("/* {} */ ", self.tag));
+        true
+    }
+}
+
+/// Ends `inner` with an [`Error::Execute`] of kind
+/// [`io::ErrorKind::Interrupted`] as soon as `cancelled` completes
+///
+/// `inner` is dropped first, releasing its connection; then, if the query was
+/// tagged, its cancel statement is issued on `pool`. That is best effort: the
+/// query may have finished meanwhile, and its failure is ignored.
+pub(crate) fn cancellable<'e, DB, I>(
+    inner: BoxStream<'e, Result<I, Error>>,
+    cancelled: BoxFuture<'static, ()>,
+    pool: Option<Pool<DB>>,
+    tag: Arc<CancelTag>,
+) -> BoxStream<'e, Result<I, Error>>
+where
+    DB: Database,
+    I: Send + 'e,
+    for<'c1> &'c1 mut DB::Connection: Executor<'c1, Database = DB>,
+{
+    Box::pin(async_stream::try_stream! {
+        let mut inner = inner;
+        let mut cancelled = cancelled;
+        loop {
+            match future::select(&mut cancelled, inner.try_next()).await {
+                future::Either::Left(_) => break,
+                future::Either::Right((next, _)) => match next? {
+                    Some(item) => yield item,
+                    None => return,
+                },
+            }
+        }
+        drop(inner);
+        if let (Some(pool), Some(statement)) = (&pool, tag.statement.get()) {
+            let _ = SqlTemplateExecute::<DB>::new(statement.clone(), None)
+                .set_persistent(false)
+                .execute(pool)
+                .await;
+        }
+        Err(Error::from(io::Error::new(
+            io::ErrorKind::Interrupted,
+            "query cancelled",
+        )))?;
+    })
+}
@@ -0,0 +1,142 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, PoisonError, RwLock},
+};
+
+use futures_core::future::BoxFuture;
+use futures_util::FutureExt;
+use sqlx_core::{database::Database, executor::Executor, from_row::FromRow, pool::Pool};
+
+use crate::{DatabaseDialect, Error, TemplateContext, detect_backend_db};
+
+/// Server features SQL generation can branch on, see [`detect_capabilities`]
+///
+/// Exposed to templates by `DBAdapter::with_capabilities` as
+/// [`TemplateContext`] values: `ctx_flag("returning")` and
+/// `ctx("server_version")`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Version string of the server, only queried for MySQL-family backends
+    pub server_version: Option<String>,
+    /// Whether `INSERT ... RETURNING` is supported: PostgreSQL, SQLite and
+    /// MariaDB 10.5 or later, but not MySQL
+    pub returning: bool,
+}
+
+impl Capabilities {
+    /// Capabilities of a `backend_name` server (see
+    /// [`DatabaseDialect::backend_name`]) reporting `server_version`
+    pub fn new(backend_name: &str, server_version: Option<&str>) -> Self {
+        let returning = match backend_name {
+            "PostgreSQL" | "SQLite" => true,
+            "MySQL" => server_version.is_some_and(mariadb_returning),
+            _ => false,
+        };
+        Self {
+            server_version: server_version.map(str::to_string),
+            returning,
+        }
+    }
+    /// Sets the template context values of the capabilities
+    pub(crate) fn apply(&self, context: &mut TemplateContext) {
+        context.set("returning", self.returning.to_string());
+        if let Some(version) = &self.server_version {
+            context.set("server_version", version.as_str());
+        }
+    }
+}
+
+/// Whether a `version()` string is one of MariaDB 10.5 or later, e.g.
+/// `10.11.6-MariaDB-1:10.11.6+maria~ubu2204`
+fn mariadb_returning(version: &str) -> bool {
+    if !version.to_ascii_lowercase().contains("mariadb") {
+        return false;
+    }
+    // replication-compatible prefix of older clients
+    let version = version.strip_prefix("5.5.5-").unwrap_or(version);
+    let mut numbers = version
+        .split(|c: char| !c.is_ascii_digit())
+        .map(|n| n.parse::<u32>().unwrap_or_default());
+    let major = numbers.next().unwrap_or_default();
+    let minor = numbers.next().unwrap_or_default();
+    (major, minor) >= (10, 5)
+}
+
+/// Capabilities detected per pool, keyed by the address of its shared state
+static DETECTED: LazyLock<RwLock<HashMap<usize, Capabilities>>> = LazyLock::new(Default::default);
+
+/// Detects the [`Capabilities`] of the server behind `pool`, once per pool
+///
+/// MySQL-family backends are asked for `select version()`, telling MariaDB
+/// apart; other backends need no query beyond detecting the backend of an
+/// `Any` pool.
+///
+/// # Example
+/// ```no_run
+/// use sqlx::PgPool;
+/// use sqlx_askama_template::detect_capabilities;
+///
+/// # async fn run(pool: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+/// if detect_capabilities(&pool).await?.returning {
+///     // insert ... returning id
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn detect_capabilities<DB>(pool: &Pool<DB>) -> Result<Capabilities, Error>
+where
+    DB: Database,
+    (String,): for<'r> FromRow<'r, DB::Row>,
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+{
+    let key = pool.options() as *const _ as usize;
+    if let Some(capabilities) = DETECTED
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&key)
+    {
+        return Ok(capabilities.clone());
+    }
+    let (db_type, _) = detect_backend_db(pool).await?;
+    let server_version = match db_type.backend_name() {
+        "MySQL" => {
+            let row = pool.fetch_one("select version()").await?;
+            Some(<(String,)>::from_row(&row)?.0)
+        }
+        _ => None,
+    };
+    let capabilities = Capabilities::new(db_type.backend_name(), server_version.as_deref());
+    DETECTED
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(key, capabilities.clone());
+    Ok(capabilities)
+}
+
+/// Detection run by `DBAdapter::with_capabilities`, bound where the row
+/// decoding bounds are known
+pub(crate) type CapabilityProbe<DB> =
+    fn(Pool<DB>) -> BoxFuture<'static, Result<Capabilities, Error>>;
+
+/// [`CapabilityProbe`] of [`detect_capabilities`]
+pub(crate) fn probe<DB>(pool: Pool<DB>) -> BoxFuture<'static, Result<Capabilities, Error>>
+where
+    DB: Database,
+    (String,): for<'r> FromRow<'r, DB::Row>,
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+{
+    async move { detect_capabilities(&pool).await }.boxed()
+}
+
+/// `context` with the values of `capabilities` added, or of the capabilities
+/// derived from the backend name alone when they were not detected
+pub(crate) fn context_with_capabilities(
+    capabilities: Option<Capabilities>,
+    backend_name: &str,
+    context: Option<&Arc<TemplateContext>>,
+) -> Arc<TemplateContext> {
+    let capabilities = capabilities.unwrap_or_else(|| Capabilities::new(backend_name, None));
+    let mut context = context.map(|c| (**c).clone()).unwrap_or_default();
+    capabilities.apply(&mut context);
+    Arc::new(context)
+}
@@ -0,0 +1,162 @@
+use std::any::Any;
+
+use sqlx_core::any::AnyArguments;
+
+/// Cache for count query results
+///
+/// Installed on [`crate::DBAdapter`] with `set_count_cache`; keys combine the
+/// rendered count SQL, the [`CountCacheKey`] of the query and
+/// `SqlTemplate::TEMPLATE_FINGERPRINT`.
+pub trait CountCache: Send + Sync {
+    /// Returns the cached count for the key
+    fn get(&self, key: u64) -> Option<i64>;
+    /// Stores the count for the key
+    fn insert(&self, key: u64, count: i64);
+}
+
+/// What tells apart the count cache entries of queries with the same SQL,
+/// i.e. the values bound to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CountCacheKey {
+    /// Key given by the caller, e.g. a hash of the filter request; queries
+    /// with the same SQL and key share their count
+    Given(u64),
+    /// Key derived with [`arguments_fingerprint`] from the arguments bound to
+    /// the count query, including the values of `ctx()` and `TemplateContext`
+    ///
+    /// Supported for `Any`, and for PostgreSQL, MySQL and SQLite with the
+    /// `postgres`, `mysql` and `sqlite` features; the counts of other
+    /// databases are never cached.
+    Arguments,
+}
+
+/// Stable 64-bit FNV-1a fingerprint of a string
+///
+/// Unlike `std::hash::DefaultHasher` the result does not change between
+/// Rust releases, so it can be shared across processes and deploys.
+pub fn fingerprint(s: &str) -> u64 {
+    fingerprint_bytes(0xcbf2_9ce4_8422_2325, s.as_bytes())
+}
+
+pub(crate) fn fingerprint_bytes(mut hash: u64, bytes: &[u8]) -> u64 {
+    for b in bytes {
+        hash ^= u64::from(*b);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// Folds the values of the arguments bound to a query into `hash`, see
+/// [`CountCacheKey::Arguments`]
+///
+/// # Returns
+/// `None` for the arguments of a database whose values can not be read.
+pub fn arguments_fingerprint<A: Any>(hash: u64, arguments: Option<&A>) -> Option<u64> {
+    let Some(arguments) = arguments else {
+        return Some(hash);
+    };
+    let arguments = arguments as &dyn Any;
+    let debug = if let Some(arguments) = arguments.downcast_ref::<AnyArguments>() {
+        format!("{:?}", arguments.values.0)
+    } else {
+        debug_arguments(arguments)?
+    };
+    Some(fingerprint_bytes(hash, debug.as_bytes()))
+}
+
+/// `Debug` representation of the arguments of a driver, which holds every
+/// bound value
+#[allow(unused_variables)]
+fn debug_arguments(arguments: &dyn Any) -> Option<String> {
+    #[cfg(feature = "postgres")]
+    if let Some(arguments) = arguments.downcast_ref::<sqlx_postgres::PgArguments>() {
+        return Some(format!("{arguments:?}"));
+    }
+    #[cfg(feature = "mysql")]
+    if let Some(arguments) = arguments.downcast_ref::<sqlx_mysql::MySqlArguments>() {
+        return Some(format!("{arguments:?}"));
+    }
+    #[cfg(feature = "sqlite")]
+    if let Some(arguments) = arguments.downcast_ref::<sqlx_sqlite::SqliteArguments>() {
+        return Some(format!("{arguments:?}"));
+    }
+    None
+}
+
+#[cfg(feature = "moka")]
+impl CountCache for moka::sync::Cache<u64, i64> {
+    fn get(&self, key: u64) -> Option<i64> {
+        moka::sync::Cache::get(self, &key)
+    }
+    fn insert(&self, key: u64, count: i64) {
+        moka::sync::Cache::insert(self, key, count)
+    }
+}
+
+/// Any `cached` store behind a mutex, e.g. `Mutex<TimedSizedCache<u64, i64>>`
+#[cfg(feature = "cached")]
+impl<C> CountCache for std::sync::Mutex<C>
+where
+    C: cached::Cached<u64, i64> + Send,
+{
+    fn get(&self, key: u64) -> Option<i64> {
+        self.lock().ok()?.cache_get(&key).copied()
+    }
+    fn insert(&self, key: u64, count: i64) {
+        if let Ok(mut cache) = self.lock() {
+            cache.cache_set(key, count);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx_core::arguments::Arguments;
+
+    fn any_arguments(values: &[i64]) -> AnyArguments {
+        let mut arguments = AnyArguments::default();
+        for value in values {
+            arguments.add(*value).unwrap();
+        }
+        arguments
+    }
+
+    #[test]
+    fn fingerprint_is_stable() {
+        assert_eq!(fingerprint(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fingerprint("a"), 0xaf63_dc4c_8601_ec8c);
+        let hash = fingerprint("select count(1) from t");
+        assert_eq!(
+            arguments_fingerprint(hash, Some(&any_arguments(&[1, 2]))),
+            arguments_fingerprint(hash, Some(&any_arguments(&[1, 2])))
+        );
+        assert_eq!(
+            arguments_fingerprint::<AnyArguments>(hash, None),
+            Some(hash)
+        );
+    }
+
+    #[test]
+    fn keys_tell_values_apart() {
+        let hash = fingerprint("select count(1) from t where a = $1 and b = $2");
+        let keys = [
+            arguments_fingerprint(hash, Some(&any_arguments(&[1, 23]))),
+            arguments_fingerprint(hash, Some(&any_arguments(&[12, 3]))),
+            arguments_fingerprint(hash, Some(&any_arguments(&[1, 2]))),
+            arguments_fingerprint(hash, Some(&any_arguments(&[2, 1]))),
+            arguments_fingerprint(hash, Some(&any_arguments(&[1]))),
+        ];
+        for (i, a) in keys.iter().enumerate() {
+            assert!(a.is_some());
+            for b in &keys[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_arguments_are_not_cached() {
+        assert_eq!(arguments_fingerprint(0, Some(&vec![1u8])), None);
+    }
+}
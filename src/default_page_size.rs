@@ -0,0 +1,18 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+
+static DEFAULT_PAGE_SIZE: AtomicI64 = AtomicI64::new(20);
+
+/// Sets the global pagination size of [`crate::DBAdapter::fetch_page_as`]
+/// (default `20`)
+///
+/// Templates override it with `#[sql_template(default_page_size = 50)]`, see
+/// [`crate::SqlTemplate::DEFAULT_PAGE_SIZE`]. A size of 0 or less makes
+/// `fetch_page_as` return all rows.
+pub fn set_default_page_size(size: i64) {
+    DEFAULT_PAGE_SIZE.store(size, Ordering::Relaxed);
+}
+
+/// Current size set by [`set_default_page_size`]
+pub fn default_page_size() -> i64 {
+    DEFAULT_PAGE_SIZE.load(Ordering::Relaxed)
+}
@@ -0,0 +1,71 @@
+use sqlx_core::database::Database;
+
+use crate::{DebugParam, Error, SqlTemplate};
+
+/// Object-safe form of [`SqlTemplate`], to keep templates of different types
+/// together, e.g. `Vec<Box<dyn ErasedSqlTemplate<Postgres>>>`
+///
+/// Implemented by the `SqlTemplate` derive for structs and enums, for each
+/// database they support. `&dyn ErasedSqlTemplate<DB>` is a [`SqlTemplate`]
+/// again, running through the adapter like any template; it keeps the
+/// defaults of the associated constants, and can not run the dedicated count
+/// query of a `{% block count %}`.
+///
+/// # Example
+/// ```no_run
+/// use sqlx::{PgPool, Postgres};
+/// use sqlx_askama_template::{ErasedSqlTemplate, SqlTemplate};
+///
+/// #[derive(SqlTemplate)]
+/// #[template(source = "select * from users where id = {{ e(id) }}")]
+/// struct UserQuery {
+///     id: i64,
+/// }
+///
+/// #[derive(SqlTemplate)]
+/// #[template(source = "select * from orders where user_id = {{ e(user_id) }}")]
+/// struct OrderQuery {
+///     user_id: i64,
+/// }
+///
+/// # async fn run(pool: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+/// let plugins: Vec<Box<dyn ErasedSqlTemplate<Postgres>>> =
+///     vec![Box::new(UserQuery { id: 1 }), Box::new(OrderQuery { user_id: 1 })];
+/// for plugin in &plugins {
+///     let (sql, args) = plugin.render_dyn()?;
+///     let rows = (&**plugin).adapter().fetch_all(&pool).await?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub trait ErasedSqlTemplate<DB: Database>: Send + Sync {
+    /// See [`SqlTemplate::render_with_placeholder`]
+    fn render_dyn_with_placeholder(
+        &self,
+        format_placeholder: Option<fn(usize, &mut String)>,
+        sql_buffer: &mut String,
+    ) -> Result<Option<DB::Arguments>, Error>;
+    /// See [`SqlTemplate::render`]
+    fn render_dyn(&self) -> Result<(String, Option<DB::Arguments>), Error>;
+    /// See [`SqlTemplate::debug_params`]
+    fn debug_params_dyn(&self) -> Vec<DebugParam>;
+    /// [`SqlTemplate::QUERY_TAG`], or the type name when empty
+    fn query_tag(&self) -> &'static str;
+    /// See [`SqlTemplate::TEMPLATE_FINGERPRINT`]
+    fn template_fingerprint(&self) -> u64;
+    /// See [`SqlTemplate::PERSISTENT`]
+    fn persistent(&self) -> bool;
+}
+
+impl<'q, DB: Database> SqlTemplate<'q, DB> for &'q dyn ErasedSqlTemplate<DB> {
+    fn render_with_placeholder(
+        self,
+        format_placeholder: Option<fn(usize, &mut String)>,
+        sql_buffer: &mut String,
+    ) -> Result<Option<DB::Arguments>, Error> {
+        self.render_dyn_with_placeholder(format_placeholder, sql_buffer)
+    }
+    fn debug_params(&self) -> Vec<DebugParam> {
+        self.debug_params_dyn()
+    }
+}
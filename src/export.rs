@@ -0,0 +1,211 @@
+use std::fmt::Write;
+
+use sqlx_core::{
+    Error,
+    column::{Column, ColumnIndex},
+    decode::Decode,
+    row::Row,
+    types::Type,
+    value::ValueRef,
+};
+
+/// Column value decoded without knowing the result type in advance
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnValue {
+    /// SQL NULL
+    Null,
+    /// Boolean value
+    Bool(bool),
+    /// Integer value
+    Int(i64),
+    /// Floating point value
+    Float(f64),
+    /// Text value
+    Text(String),
+}
+
+impl ColumnValue {
+    /// Appends the value as plain text (NULL is empty)
+    pub fn write_text(&self, out: &mut String) {
+        let _ = match self {
+            ColumnValue::Null => Ok(()),
+            ColumnValue::Bool(v) => write!(out, "{v}"),
+            ColumnValue::Int(v) => write!(out, "{v}"),
+            ColumnValue::Float(v) => write!(out, "{v}"),
+            ColumnValue::Text(v) => out.write_str(v),
+        };
+    }
+    /// Appends the value as a JSON literal (non-finite floats become `null`)
+    pub fn write_json(&self, out: &mut String) {
+        match self {
+            ColumnValue::Null => out.push_str("null"),
+            ColumnValue::Float(v) if !v.is_finite() => out.push_str("null"),
+            ColumnValue::Text(v) => write_json_string(out, v),
+            v => v.write_text(out),
+        }
+    }
+}
+
+/// Appends `value` as a JSON string literal
+pub fn write_json_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Appends a row as one JSON object keyed by column name, followed by a newline
+pub fn write_json_row<R: RowValues>(out: &mut String, row: &R) -> Result<(), Error> {
+    out.push('{');
+    for (i, column) in row.columns().iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write_json_string(out, column.name());
+        out.push(':');
+        row.column_value(i)?.write_json(out);
+    }
+    out.push_str("}\n");
+    Ok(())
+}
+
+/// Rows whose columns can be decoded as [`ColumnValue`]s
+///
+/// Each column is decoded as the first compatible type of
+/// `i64`, `i32`, `i16`, `f64`, `f32`, `bool` and `String`.
+pub trait RowValues: Row {
+    /// Column names in order
+    fn column_names(&self) -> Vec<String> {
+        self.columns()
+            .iter()
+            .map(|c| c.name().to_string())
+            .collect()
+    }
+    /// Decodes the column at `index`
+    fn column_value(&self, index: usize) -> Result<ColumnValue, Error>;
+}
+
+impl<R> RowValues for R
+where
+    R: Row,
+    usize: ColumnIndex<R>,
+    for<'r> bool: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> i64: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> i32: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> i16: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> f64: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> f32: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> String: Decode<'r, R::Database> + Type<R::Database>,
+{
+    fn column_value(&self, index: usize) -> Result<ColumnValue, Error> {
+        let raw = self.try_get_raw(index)?;
+        if raw.is_null() {
+            return Ok(ColumnValue::Null);
+        }
+        let ty = raw.type_info().into_owned();
+        if <i64 as Type<R::Database>>::compatible(&ty) {
+            return self.try_get(index).map(ColumnValue::Int);
+        }
+        if <i32 as Type<R::Database>>::compatible(&ty) {
+            return self
+                .try_get::<i32, _>(index)
+                .map(|v| ColumnValue::Int(v.into()));
+        }
+        if <i16 as Type<R::Database>>::compatible(&ty) {
+            return self
+                .try_get::<i16, _>(index)
+                .map(|v| ColumnValue::Int(v.into()));
+        }
+        if <f64 as Type<R::Database>>::compatible(&ty) {
+            return self.try_get(index).map(ColumnValue::Float);
+        }
+        if <f32 as Type<R::Database>>::compatible(&ty) {
+            return self
+                .try_get::<f32, _>(index)
+                .map(|v| ColumnValue::Float(v.into()));
+        }
+        if <bool as Type<R::Database>>::compatible(&ty) {
+            return self.try_get(index).map(ColumnValue::Bool);
+        }
+        self.try_get(index).map(ColumnValue::Text)
+    }
+}
+
+/// CSV output options
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvOptions {
+    /// Field delimiter (default `,`)
+    pub delimiter: char,
+    /// Quote character (default `"`)
+    pub quote: char,
+    /// Quote every field instead of only those that need it
+    pub quote_all: bool,
+    /// Write a header line with the column names (default `true`)
+    pub header: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            quote: '"',
+            quote_all: false,
+            header: true,
+        }
+    }
+}
+
+impl CsvOptions {
+    /// Appends one CSV field, quoting it when required
+    pub fn write_field(&self, out: &mut String, value: &str) {
+        let needs_quote =
+            self.quote_all || value.contains([self.delimiter, self.quote, '\n', '\r']);
+        if !needs_quote {
+            out.push_str(value);
+            return;
+        }
+        out.push(self.quote);
+        for c in value.chars() {
+            if c == self.quote {
+                out.push(self.quote);
+            }
+            out.push(c);
+        }
+        out.push(self.quote);
+    }
+    /// Appends one CSV record followed by a newline
+    pub fn write_record<'a>(&self, out: &mut String, fields: impl IntoIterator<Item = &'a str>) {
+        for (i, field) in fields.into_iter().enumerate() {
+            if i > 0 {
+                out.push(self.delimiter);
+            }
+            self.write_field(out, field);
+        }
+        out.push('\n');
+    }
+    /// Appends a row as one CSV record
+    pub fn write_row<R: RowValues>(&self, out: &mut String, row: &R) -> Result<(), Error> {
+        let mut text = String::new();
+        for i in 0..row.columns().len() {
+            if i > 0 {
+                out.push(self.delimiter);
+            }
+            text.clear();
+            row.column_value(i)?.write_text(&mut text);
+            self.write_field(out, &text);
+        }
+        out.push('\n');
+        Ok(())
+    }
+}
@@ -0,0 +1,303 @@
+use std::{fmt, fmt::Write};
+
+use sqlx_core::{
+    arguments::Arguments, database::Database, encode::Encode, error::BoxDynError, types::Type,
+};
+
+use crate::{ColumnValue, SortDirection, SortSpec, UsageError, split_trailing};
+
+/// Continuation token of [`crate::DBAdapter::fetch_after_as`], made of the
+/// sort key values of the last row returned
+///
+/// Sent to clients as an opaque string with [`Cursor::token`] and read back
+/// with [`Cursor::from_token`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cursor {
+    pub(crate) values: Vec<ColumnValue>,
+}
+
+impl Cursor {
+    /// Opaque, URL-safe representation of the cursor
+    pub fn token(&self) -> String {
+        let mut raw = String::new();
+        for value in &self.values {
+            let _ = match value {
+                ColumnValue::Null => write!(raw, "n"),
+                ColumnValue::Bool(v) => write!(raw, "b{};", u8::from(*v)),
+                ColumnValue::Int(v) => write!(raw, "i{v};"),
+                ColumnValue::Float(v) => write!(raw, "f{:x};", v.to_bits()),
+                ColumnValue::Text(v) => write!(raw, "s{}:{v}", v.len()),
+            };
+        }
+        raw.bytes().fold(String::new(), |mut token, b| {
+            let _ = write!(token, "{b:02x}");
+            token
+        })
+    }
+    /// Parses a token returned by [`Cursor::token`], `None` if it is malformed
+    pub fn from_token(token: &str) -> Option<Self> {
+        if !token.len().is_multiple_of(2) {
+            return None;
+        }
+        let bytes = (0..token.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(token.get(i..i + 2)?, 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+        let raw = String::from_utf8(bytes).ok()?;
+        let mut rest = raw.as_str();
+        let mut values = Vec::new();
+        while let Some(tag) = rest.chars().next() {
+            rest = &rest[tag.len_utf8()..];
+            let value = if tag == 'n' {
+                ColumnValue::Null
+            } else if tag == 's' {
+                let (len, tail) = rest.split_once(':')?;
+                let len: usize = len.parse().ok()?;
+                let text = tail.get(..len)?;
+                rest = &tail[len..];
+                ColumnValue::Text(text.to_string())
+            } else {
+                let (payload, tail) = rest.split_once(';')?;
+                rest = tail;
+                match tag {
+                    'b' => match payload {
+                        "0" => ColumnValue::Bool(false),
+                        "1" => ColumnValue::Bool(true),
+                        _ => return None,
+                    },
+                    'i' => ColumnValue::Int(payload.parse().ok()?),
+                    'f' => {
+                        ColumnValue::Float(f64::from_bits(u64::from_str_radix(payload, 16).ok()?))
+                    }
+                    _ => return None,
+                }
+            };
+            values.push(value);
+        }
+        Some(Self { values })
+    }
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.token())
+    }
+}
+
+/// Binds one cursor value as a query argument
+pub(crate) type KeysetBind<DB> =
+    fn(&mut <DB as Database>::Arguments, &ColumnValue) -> Result<(), BoxDynError>;
+
+/// Keyset position of [`crate::DBAdapter::fetch_after_as`]: the cursor values,
+/// empty for the first rows
+pub(crate) struct Keyset<DB: Database> {
+    pub(crate) values: Vec<ColumnValue>,
+    pub(crate) bind: KeysetBind<DB>,
+}
+
+impl<DB: Database> Clone for Keyset<DB> {
+    fn clone(&self) -> Self {
+        Self {
+            values: self.values.clone(),
+            bind: self.bind,
+        }
+    }
+}
+
+/// [`KeysetBind`] for backends encoding the [`ColumnValue`] types
+pub(crate) fn bind_column_value<'q, DB>(
+    arg: &mut DB::Arguments,
+    value: &ColumnValue,
+) -> Result<(), BoxDynError>
+where
+    DB: Database,
+    bool: Encode<'q, DB> + Type<DB>,
+    i64: Encode<'q, DB> + Type<DB>,
+    f64: Encode<'q, DB> + Type<DB>,
+    String: Encode<'q, DB> + Type<DB>,
+{
+    match value {
+        ColumnValue::Null => Err("keyset values can not be NULL".into()),
+        ColumnValue::Bool(v) => arg.add(*v),
+        ColumnValue::Int(v) => arg.add(*v),
+        ColumnValue::Float(v) => arg.add(*v),
+        ColumnValue::Text(v) => arg.add(v.clone()),
+    }
+}
+
+/// Column name of a sort key in the result rows, without its table
+pub(crate) fn result_column(column: &str) -> &str {
+    column.rsplit('.').next().unwrap_or(column)
+}
+
+/// Wraps `sql` as `select * from (...) t` filtered to the rows after the
+/// keyset values, returning the sort keys by result column name
+///
+/// Each key is compared to the cursor after all the keys before it are
+/// equal: `(a > ?) or (a = ? and b > ?)`, descending keys using `<`.
+pub(crate) fn write_keyset<DB: Database>(
+    sql: &mut String,
+    sort: &[SortSpec],
+    keyset: &Keyset<DB>,
+    f: Option<fn(usize, &mut String)>,
+    arg: &mut DB::Arguments,
+) -> Result<Vec<SortSpec>, BoxDynError> {
+    if keyset.values.len() != sort.len() && !keyset.values.is_empty() {
+        return Err(Box::new(UsageError::Keyset(
+            "the cursor does not match the sort keys".into(),
+        )));
+    }
+    let trailing = split_trailing(sql);
+    *sql = format!("select * from ({sql}) t");
+    let columns: Vec<&str> = sort
+        .iter()
+        .map(|spec| result_column(&spec.column))
+        .collect();
+    let placeholder = |arg: &mut DB::Arguments, sql: &mut String, value: &ColumnValue| {
+        (keyset.bind)(arg, value)?;
+        match f {
+            Some(f) => f(arg.len(), sql),
+            None => arg.format_placeholder(sql)?,
+        }
+        Ok::<_, BoxDynError>(())
+    };
+    for (i, spec) in sort.iter().enumerate().take(keyset.values.len()) {
+        sql.push_str(if i == 0 { " where (" } else { " or (" });
+        for (column, value) in columns.iter().zip(&keyset.values).take(i) {
+            let _ = write!(sql, "{column} = ");
+            placeholder(arg, sql, value)?;
+            sql.push_str(" and ");
+        }
+        let op = match spec.direction {
+            SortDirection::Asc => ">",
+            SortDirection::Desc => "<",
+        };
+        let _ = write!(sql, "{} {op} ", columns[i]);
+        placeholder(arg, sql, &keyset.values[i])?;
+        sql.push(')');
+    }
+    sql.push_str(&trailing);
+    Ok(sort
+        .iter()
+        .zip(columns)
+        .map(|(spec, column)| SortSpec {
+            column: column.to_string(),
+            ..spec.clone()
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx_core::any::{Any, AnyArguments};
+
+    use super::*;
+
+    fn cursor(values: Vec<ColumnValue>) -> Cursor {
+        Cursor { values }
+    }
+
+    #[test]
+    fn token_round_trip() {
+        let cursor = cursor(vec![
+            ColumnValue::Null,
+            ColumnValue::Bool(true),
+            ColumnValue::Bool(false),
+            ColumnValue::Int(-42),
+            ColumnValue::Float(1.5),
+            ColumnValue::Text("a;b:c".to_string()),
+            ColumnValue::Text("日本".to_string()),
+            ColumnValue::Text(String::new()),
+        ]);
+        let token = cursor.token();
+        assert!(token.bytes().all(|b| b.is_ascii_hexdigit()));
+        assert_eq!(Cursor::from_token(&token), Some(cursor));
+    }
+
+    #[test]
+    fn empty_token() {
+        assert_eq!(Cursor::from_token(""), Some(cursor(Vec::new())));
+    }
+
+    fn hex(raw: &str) -> String {
+        raw.bytes().map(|b| format!("{b:02x}")).collect()
+    }
+
+    fn keyset(values: Vec<ColumnValue>) -> Keyset<Any> {
+        Keyset {
+            values,
+            bind: bind_column_value::<Any>,
+        }
+    }
+
+    fn dollar(i: usize, sql: &mut String) {
+        let _ = write!(sql, "${i}");
+    }
+
+    #[test]
+    fn keyset_filter() {
+        let sort = [SortSpec::asc("t.a"), SortSpec::desc("b")];
+        let mut sql = "select * from t order by a; -- page".to_string();
+        let mut args = AnyArguments::default();
+        let keyset = keyset(vec![ColumnValue::Int(1), ColumnValue::Text("x".into())]);
+        let sort = write_keyset(&mut sql, &sort, &keyset, Some(dollar), &mut args).unwrap();
+        assert_eq!(
+            sql,
+            "select * from (select * from t order by a) t where (a > $1) or (a = $2 and b < $3); -- page"
+        );
+        assert_eq!(args.len(), 3);
+        assert_eq!(sort, [SortSpec::asc("a"), SortSpec::desc("b")]);
+    }
+
+    #[test]
+    fn first_keyset_page() {
+        let mut sql = "select * from t".to_string();
+        let mut args = AnyArguments::default();
+        write_keyset(
+            &mut sql,
+            &[SortSpec::asc("a")],
+            &keyset(Vec::new()),
+            None,
+            &mut args,
+        )
+        .unwrap();
+        assert_eq!(sql, "select * from (select * from t) t");
+        assert_eq!(args.len(), 0);
+    }
+
+    #[test]
+    fn keyset_errors() {
+        let mut args = AnyArguments::default();
+        let sort = [SortSpec::asc("a"), SortSpec::asc("b")];
+        let err = write_keyset(
+            &mut "select 1".to_string(),
+            &sort,
+            &keyset(vec![ColumnValue::Int(1)]),
+            None,
+            &mut args,
+        )
+        .unwrap_err();
+        assert!(err.downcast_ref::<UsageError>().is_some());
+        let err = write_keyset(
+            &mut "select 1".to_string(),
+            &sort[..1],
+            &keyset(vec![ColumnValue::Null]),
+            None,
+            &mut args,
+        )
+        .unwrap_err();
+        assert!(err.downcast_ref::<UsageError>().is_none());
+    }
+
+    #[test]
+    fn malformed_tokens() {
+        for token in ["a", "zz", "é1", "ff", "c3a9"] {
+            assert_eq!(Cursor::from_token(token), None, "{token}");
+        }
+        for raw in [
+            "é1;", "x1;", "i1", "i1.5;", "b2;", "fzz;", "s5:ab", "s:ab", "s1:é",
+        ] {
+            assert_eq!(Cursor::from_token(&hex(raw)), None, "{raw}");
+        }
+    }
+}
@@ -1,26 +1,131 @@
 #![doc = include_str!("../README.md")]
 
-use sqlx_core::{Error, database::Database};
+use sqlx_core::{database::Database, error::Error as SqlxError};
 
 pub use askama;
+
+/// Names in scope of the filters declared with [`sql_filter`]
+#[doc(hidden)]
+pub mod filter_support {
+    pub use askama;
+}
+/// Names used by the `inventory` entries the derive submits
+#[cfg(feature = "registry")]
+#[doc(hidden)]
+pub mod registry_support {
+    pub use inventory;
+}
+pub use sqlx_askama_template_core::*;
 pub use sqlx_askama_template_macro::*;
-mod db_adapter;
-mod sql_template_execute;
+#[cfg(feature = "arrow")]
+mod arrow_export;
+mod batch;
+mod cancel;
+mod capabilities;
+mod count_cache;
+mod default_page_size;
+mod erased;
+mod export;
+mod keyset;
+mod lint;
+mod manifest;
+mod middleware;
+mod observer;
+mod read_write;
+mod redact;
+#[cfg(feature = "registry")]
+mod registry;
+mod safety_limit;
+mod script_runner;
 mod template_adapter;
-mod template_arg;
 
-pub use db_adapter::*;
-pub use sql_template_execute::*;
+#[cfg(feature = "arrow")]
+pub use arrow_export::*;
+pub use batch::*;
+pub use capabilities::{Capabilities, detect_capabilities};
+pub use count_cache::*;
+pub use default_page_size::*;
+pub use erased::*;
+pub use export::*;
+pub use keyset::Cursor;
+pub use lint::*;
+pub use manifest::ParamManifest;
+pub use middleware::*;
+pub use observer::*;
+pub use read_write::*;
+pub use redact::*;
+#[cfg(feature = "registry")]
+pub use registry::*;
+pub use safety_limit::*;
+pub use script_runner::*;
 pub use template_adapter::*;
-pub use template_arg::*;
 
 /// SQL template trait
 ///
 /// Defines basic operations for rendering SQL from templates
-pub trait SqlTemplate<'q, DB>: Sized + Clone + Send
+///
+/// Templates are rendered once and need not be `Clone`; only the adapter
+/// methods rendering the query again from a copy of it (negative pagination
+/// numbers, `fetch_page_as`, `stream_all_as`, `split_oversized_lists`)
+/// require `T: Clone`.
+pub trait SqlTemplate<'q, DB>: Sized + Send
 where
     DB: Database,
 {
+    /// Whether queries of this template are cached as prepared statements by
+    /// default, see [`DBAdapter::set_persistent`]
+    ///
+    /// Set to `false` by `#[sql_template(persistent = false)]`, for templates
+    /// rendering highly variable SQL.
+    const PERSISTENT: bool = true;
+    /// Stable statement name, e.g. `my_crate::queries::UserQuery`, reported as
+    /// [`QueryMeta::template`] and embedded by [`DBAdapter::with_query_tag`]
+    ///
+    /// The derive sets it to the module path of the struct and its name
+    /// (followed by `::query_name` for `SqlTemplateSet` queries); when empty,
+    /// the type name is used instead.
+    const QUERY_TAG: &'static str = "";
+    /// Stable hash of the template source, changing when its SQL does
+    ///
+    /// Set by the derive (0 otherwise) and carried by
+    /// [`QueryMeta::template_fingerprint`]: it is part of the count cache keys
+    /// and of the [`DBAdapter::with_query_tag`] comment, so caches keyed on it
+    /// invalidate when a deploy changes the template.
+    const TEMPLATE_FINGERPRINT: u64 = 0;
+    /// Columns a [`SortSpec`] of [`DBAdapter::with_sort`] may name
+    ///
+    /// Set by `#[sql_template(order_by = "id, name, u.created_at")]`; empty by
+    /// default, rejecting every runtime sort.
+    const SORT_COLUMNS: &'static [&'static str] = &[];
+    /// Pagination size of [`DBAdapter::fetch_page_as`]
+    ///
+    /// Set by `#[sql_template(default_page_size = 50)]`; `None` by default,
+    /// falling back to [`default_page_size`].
+    const DEFAULT_PAGE_SIZE: Option<i64> = None;
+    /// Whether the template defines a dedicated count query, rendered by
+    /// [`SqlTemplate::render_count_with_placeholder`]
+    ///
+    /// Set by the derive for templates with a `{% block count %}`; the
+    /// [`DBAdapter::count`] of such a template runs that query as it is instead
+    /// of wrapping the whole query in `select count(1) from (...)`.
+    const HAS_COUNT_QUERY: bool = false;
+    /// Whether every value of the template renders the same SQL, only the
+    /// bound arguments differing
+    ///
+    /// Set by the derive for inline templates made only of SQL text and
+    /// `{{ e(field) }}` / `{{ e_named("label", field) }}` binds, the ones with
+    /// a `PARAM_COUNT`. [`execute_batch`] then renders the SQL of the first
+    /// template only, and encodes the arguments of the following ones with
+    /// [`SqlTemplate::render_arguments`].
+    const STATIC_SQL: bool = false;
+    /// Placeholder syntax of [`SqlTemplate::render`] and
+    /// [`SqlTemplate::render_count_sql`]
+    ///
+    /// Set by `#[sql_template(placeholder = "dollar" | "question" | "at")]`;
+    /// `None` by default, leaving the placeholders to the arguments of `DB`
+    /// (`?` for `Any`). Adapters and [`SqlTemplate::render_for_dialect`] use
+    /// the placeholders of the actual backend instead.
+    const PLACEHOLDER_STYLE: Option<PlaceholderStyle> = None;
     /// Renders the SQL template using a custom placeholder encoding function
     ///
     /// Writes the rendered SQL to the provided buffer and handles parameter encoding.
@@ -32,19 +137,160 @@ where
     /// - `sql_buffer`: Mutable string buffer to store the rendered SQL
     ///
     /// # Returns
-    /// Encoded database arguments (None if no parameters) or an error if rendering fails
+    /// Encoded database arguments (None if no parameters), or an [`Error::Render`] /
+    /// [`Error::Encode`] error
     fn render_with_placeholder(
         self,
         format_placeholder: Option<fn(usize, &mut String)>,
         sql_buffer: &mut String,
     ) -> Result<Option<DB::Arguments>, Error>;
+    /// Encodes the arguments of the template, discarding its SQL
+    ///
+    /// The derive renders only the binds of [`SqlTemplate::STATIC_SQL`]
+    /// templates; by default the whole template is rendered.
+    fn render_arguments(self) -> Result<Option<DB::Arguments>, Error> {
+        self.render_with_placeholder(None, &mut String::new())
+    }
+    /// Returns the template fields for debug output
+    ///
+    /// Generated by the derive; values of `#[sensitive]` fields are masked by
+    /// [`redact_params`] according to the global [`Redaction`] mode.
+    fn debug_params(&self) -> Vec<DebugParam> {
+        Vec::new()
+    }
+    /// Renders the dedicated count query of the template, like
+    /// [`SqlTemplate::render_with_placeholder`]
+    ///
+    /// Fails with [`Error::Render`] unless [`SqlTemplate::HAS_COUNT_QUERY`].
+    fn render_count_with_placeholder(
+        self,
+        format_placeholder: Option<fn(usize, &mut String)>,
+        sql_buffer: &mut String,
+    ) -> Result<Option<DB::Arguments>, Error> {
+        let _ = (format_placeholder, sql_buffer);
+        Err(Error::Render(
+            SqlxError::from(UsageError::MissingCountBlock).into(),
+        ))
+    }
+    /// Renders the dedicated count query of the template and returns it with
+    /// its parameters, see [`SqlTemplate::HAS_COUNT_QUERY`]
+    fn render_count_sql(self) -> Result<(String, Option<DB::Arguments>), Error> {
+        let mut sql_buff = String::new();
+        let arg = self
+            .render_count_with_placeholder(
+                Self::PLACEHOLDER_STYLE.map(PlaceholderStyle::placeholder_fn),
+                &mut sql_buff,
+            )
+            .inspect_err(|e| report_error(e, &QueryMeta::for_template::<DB, Self>("render")))?;
+        Ok((sql_buff, arg))
+    }
     /// Renders SQL template and returns query string with parameters
+    ///
+    /// Errors are reported to the hook installed by [`set_error_hook`].
     fn render(self) -> Result<(String, Option<DB::Arguments>), Error> {
         let mut sql_buff = String::new();
-        let arg = self.render_with_placeholder(None, &mut sql_buff)?;
+        let arg = self
+            .render_with_placeholder(
+                Self::PLACEHOLDER_STYLE.map(PlaceholderStyle::placeholder_fn),
+                &mut sql_buff,
+            )
+            .inspect_err(|e| report_error(e, &QueryMeta::for_template::<DB, Self>("render")))?;
+        Ok((sql_buff, arg))
+    }
+
+    /// Renders SQL template with the placeholders of `db_type`
+    ///
+    /// For [`sqlx_core::any::Any`] templates, whose plain [`SqlTemplate::render`]
+    /// can not know the backend: the dialect comes from a backend name, e.g.
+    /// `DBType::new(conn.backend_name())`, without building an adapter, to log
+    /// the SQL or hand it to another client library.
+    fn render_for_dialect(
+        self,
+        db_type: &DBType,
+    ) -> Result<(String, Option<DB::Arguments>), Error> {
+        let mut sql_buff = String::new();
+        let arg = with_identifier_quoting(db_type, || {
+            self.render_with_placeholder(db_type.placeholder_fn(), &mut sql_buff)
+        })
+        .inspect_err(|e| report_error(e, &QueryMeta::for_template::<DB, Self>("render")))?;
         Ok((sql_buff, arg))
     }
 
+    /// Renders SQL template with the placeholders of `db_type` into `writer`,
+    /// returning the manifest of the bound parameters
+    ///
+    /// Needs no executor, for build pipelines pre-generating SQL files: the
+    /// manifest lists each placeholder with its `e_named()` label and Rust
+    /// type, and [`ParamManifest::to_json`] makes the sidecar file. The values
+    /// are not part of it.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use std::fs::File;
+    ///
+    /// use sqlx::Postgres;
+    /// use sqlx_askama_template::{DBType, SqlTemplate};
+    ///
+    /// #[derive(SqlTemplate)]
+    /// #[template(source = "select * from users where id = {{ e(id) }}")]
+    /// struct UserById {
+    ///     id: i64,
+    /// }
+    ///
+    /// let mut sql = File::create("user_by_id.sql")?;
+    /// let manifest = SqlTemplate::<Postgres>::render_to_writer(
+    ///     &UserById { id: 0 },
+    ///     &DBType::PostgreSQL,
+    ///     &mut sql,
+    /// )?;
+    /// std::fs::write("user_by_id.json", manifest.to_json())?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    fn render_to_writer(
+        self,
+        db_type: &DBType,
+        writer: &mut impl std::io::Write,
+    ) -> Result<ParamManifest, Error> {
+        let template = QueryMeta::for_template::<DB, Self>("render").template;
+        let (rendered, params) = record_bound_params(|| self.render_for_dialect(db_type));
+        let (sql, _) = rendered?;
+        writer.write_all(sql.as_bytes())?;
+        Ok(ParamManifest {
+            template,
+            dialect: db_type.backend_name().to_string(),
+            params,
+        })
+    }
+
+    /// Renders the SQL template and returns it with its arguments and the
+    /// manifest of the bound parameters
+    ///
+    /// Parameters bound from `#[param(secret)]` fields have
+    /// [`BoundParam::secret`] set, so a layer logging the arguments by index
+    /// can print [`REDACTED`] for them.
+    #[allow(clippy::type_complexity)]
+    fn render_with_params(self) -> Result<(String, Option<DB::Arguments>, Vec<BoundParam>), Error> {
+        let (rendered, params) = record_bound_params(|| self.render());
+        let (sql, arguments) = rendered?;
+        Ok((sql, arguments, params))
+    }
+
+    /// Renders the SQL template and formats it with [`format_sql`] for display,
+    /// e.g. in admin UIs or review bots; arguments are dropped
+    fn render_sql_pretty(self) -> Result<String, Error> {
+        let (sql, _) = self.render()?;
+        Ok(format_sql(&sql))
+    }
+
+    /// Renders the SQL template with a [`TemplateContext`] readable through `ctx()`
+    fn render_with_context(
+        self,
+        context: &TemplateContext,
+    ) -> Result<(String, Option<DB::Arguments>), Error> {
+        let context = std::sync::Arc::new(context.clone());
+        with_context(Some(&context), || self.render())
+    }
+
     /// Renders SQL template and returns executable query result
     fn render_executable(self) -> Result<SqlTemplateExecute<DB>, Error> {
         let (sql, arguments) = self.render()?;
@@ -52,10 +298,36 @@ where
         Ok(SqlTemplateExecute {
             sql,
             arguments,
-            persistent: true,
+            persistent: Self::PERSISTENT,
+            statement: None,
         })
     }
 
+    /// Renders SQL template into a [`RenderedQuery`] owning its SQL and
+    /// arguments, `Send + 'static`, e.g. to build a query in one function and
+    /// execute it in a spawned task
+    ///
+    /// # Example
+    /// ```no_run
+    /// use sqlx::{Postgres, PgPool};
+    /// use sqlx_askama_template::SqlTemplate;
+    ///
+    /// #[derive(SqlTemplate)]
+    /// #[template(source = "select * from users where id = {{ e(id) }}")]
+    /// struct UserQuery {
+    ///     id: i64,
+    /// }
+    ///
+    /// # async fn run(pool: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    /// let query = SqlTemplate::<Postgres>::render_owned(&UserQuery { id: 1 })?;
+    /// tokio::spawn(async move { query.fetch_all(&pool).await });
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn render_owned(self) -> Result<RenderedQuery<DB>, Error> {
+        self.render_executable()
+    }
+
     /// Creates a database adapter  for the template
     ///
     /// Provides an adapter pattern interface for managing template rendering
@@ -67,3 +339,19 @@ where
         DBAdapter::new(self)
     }
 }
+
+/// Parameter block shared by several templates, embedded with `#[flatten]`
+///
+/// Derived with `#[derive(SqlParams)]`. The fields of a `#[flatten]` field are
+/// read in the template through it, e.g. `{{ e(tenant.tenant_id) }}`, and show
+/// up under their own names in [`SqlTemplate::debug_params`].
+pub trait SqlParams {
+    /// Returns the fields for debug output, see [`SqlTemplate::debug_params`]
+    fn debug_params(&self) -> Vec<DebugParam>;
+}
+
+impl<T: SqlParams + ?Sized> SqlParams for &T {
+    fn debug_params(&self) -> Vec<DebugParam> {
+        (**self).debug_params()
+    }
+}
@@ -0,0 +1,120 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use sqlx_core::Error as SqlxError;
+
+use crate::{Error, UsageError, redact::DebugParam};
+
+/// What to do when a string field's value appears verbatim in rendered SQL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterpolationLint {
+    /// No check (default)
+    Off,
+    /// Log a warning through `tracing`
+    Warn,
+    /// Fail the render with [`Error::Render`]
+    Deny,
+}
+
+static INTERPOLATION_LINT: AtomicU8 = AtomicU8::new(0);
+
+/// Sets the global post-render check for unbound string values
+///
+/// After rendering, the SQL is scanned for the values of string fields. A
+/// value bound with `e()` only shows up as a placeholder, so finding it
+/// verbatim means it was interpolated with `{{ field }}`, a SQL injection
+/// hazard. Fields interpolated on purpose (e.g. sort columns) are marked
+/// `#[raw]` to skip them.
+pub fn set_interpolation_lint(lint: InterpolationLint) {
+    let v = match lint {
+        InterpolationLint::Off => 0,
+        InterpolationLint::Warn => 1,
+        InterpolationLint::Deny => 2,
+    };
+    INTERPOLATION_LINT.store(v, Ordering::Relaxed);
+}
+
+/// Returns the global interpolation lint mode
+pub fn interpolation_lint() -> InterpolationLint {
+    match INTERPOLATION_LINT.load(Ordering::Relaxed) {
+        1 => InterpolationLint::Warn,
+        2 => InterpolationLint::Deny,
+        _ => InterpolationLint::Off,
+    }
+}
+
+/// Names of the non-`#[raw]` string fields whose value appears verbatim in `sql`
+///
+/// A match must not be part of a longer word, so a value such as `id` does not
+/// match inside `user_id`. This is a heuristic: a bound value that happens to
+/// equal a word of the template text (e.g. `select`) is reported as well.
+pub fn unbound_string_fields(sql: &str, params: &[DebugParam]) -> Vec<&'static str> {
+    params
+        .iter()
+        .filter(|p| p.is_string && !p.raw)
+        .filter(|p| string_value(&p.value).is_some_and(|v| contains_word(sql, &v)))
+        .map(|p| p.name)
+        .collect()
+}
+
+/// Runs the interpolation lint on a rendered template, used by the derive
+#[doc(hidden)]
+pub fn lint_rendered_sql(
+    template: &'static str,
+    sql: &str,
+    params: impl FnOnce() -> Vec<DebugParam>,
+) -> Result<(), Error> {
+    let lint = interpolation_lint();
+    if lint == InterpolationLint::Off {
+        return Ok(());
+    }
+    let fields = unbound_string_fields(sql, &params());
+    if fields.is_empty() {
+        return Ok(());
+    }
+    let template = template.trim_start_matches('&');
+    let message = format!(
+        "template `{template}` interpolates string fields without binding: {}",
+        fields.join(", ")
+    );
+    if lint == InterpolationLint::Warn {
+        tracing::warn!("{message}");
+        return Ok(());
+    }
+    Err(Error::Render(
+        SqlxError::from(UsageError::Interpolation(message)).into(),
+    ))
+}
+
+/// Extracts the string from a `Debug` representation such as `"a"` or `Some("a")`
+fn string_value(debug: &str) -> Option<String> {
+    let start = debug.find('"')?;
+    let end = debug.rfind('"')?;
+    if end <= start + 1 {
+        return None;
+    }
+    let mut value = String::new();
+    let mut chars = debug[start + 1..end].chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next()? {
+            c @ ('"' | '\\' | '\'') => value.push(c),
+            'n' => value.push('\n'),
+            't' => value.push('\t'),
+            'r' => value.push('\r'),
+            _ => return None,
+        }
+    }
+    Some(value)
+}
+
+fn contains_word(sql: &str, value: &str) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    sql.match_indices(value).any(|(i, _)| {
+        let before = sql[..i].chars().next_back();
+        let after = sql[i + value.len()..].chars().next();
+        !before.is_some_and(is_word) && !after.is_some_and(is_word)
+    })
+}
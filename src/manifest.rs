@@ -0,0 +1,44 @@
+use std::fmt::Write;
+
+use crate::{BoundParam, export::write_json_string};
+
+/// Parameters of SQL rendered by [`crate::SqlTemplate::render_to_writer`],
+/// written next to the SQL file for the services or DBAs running it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParamManifest {
+    /// Template name, see [`crate::QueryMeta::template`]
+    pub template: &'static str,
+    /// Backend the SQL was rendered for, e.g. `PostgreSQL`
+    pub dialect: String,
+    /// Bound parameters in placeholder order
+    pub params: Vec<BoundParam>,
+}
+
+impl ParamManifest {
+    /// The manifest as one JSON object, e.g.
+    /// `{"template":"app::UserById","dialect":"PostgreSQL","params":[{"index":1,"placeholder":"$1","name":"id","type":"i64","secret":false}]}`
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\"template\":");
+        write_json_string(&mut out, self.template);
+        out.push_str(",\"dialect\":");
+        write_json_string(&mut out, &self.dialect);
+        out.push_str(",\"params\":[");
+        for (i, param) in self.params.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            let _ = write!(out, "{{\"index\":{},\"placeholder\":", param.index);
+            write_json_string(&mut out, &param.placeholder);
+            out.push_str(",\"name\":");
+            match &param.name {
+                Some(name) => write_json_string(&mut out, name),
+                None => out.push_str("null"),
+            }
+            out.push_str(",\"type\":");
+            write_json_string(&mut out, param.type_name);
+            let _ = write!(out, ",\"secret\":{}}}", param.secret);
+        }
+        out.push_str("]}");
+        out
+    }
+}
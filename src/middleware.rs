@@ -0,0 +1,171 @@
+use std::{
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use sqlx_core::{Error, database::Database};
+
+use crate::{
+    QueryMeta, SqlTemplate,
+    count_cache::fingerprint,
+    observer::{QueryEvent, QueryObserver},
+};
+
+/// Constructors of [`QueryMeta`] needing the [`SqlTemplate`] trait
+pub(crate) trait QueryMetaExt {
+    /// Creates query metadata named after [`SqlTemplate::QUERY_TAG`] of `T`,
+    /// with its [`SqlTemplate::TEMPLATE_FINGERPRINT`]
+    fn for_template<'q, DB, T>(operation: &'static str) -> Self
+    where
+        DB: Database,
+        T: SqlTemplate<'q, DB>;
+}
+
+impl QueryMetaExt for QueryMeta {
+    fn for_template<'q, DB, T>(operation: &'static str) -> Self
+    where
+        DB: Database,
+        T: SqlTemplate<'q, DB>,
+    {
+        let mut meta = Self::new::<T>("", operation);
+        meta.template_fingerprint = T::TEMPLATE_FINGERPRINT;
+        if !T::QUERY_TAG.is_empty() {
+            meta.template = T::QUERY_TAG;
+        }
+        meta
+    }
+}
+
+/// Hooks executed around every query of a [`crate::DBAdapter`]
+///
+/// Middlewares are stackable: `before` runs in installation order, `after`
+/// in reverse order.
+pub trait QueryMiddleware: Send + Sync {
+    /// Called before execution; may rewrite the rendered SQL
+    fn before(&self, _sql: &mut String, _meta: &QueryMeta) {}
+    /// Called once the query finished or its stream was dropped
+    ///
+    /// # Arguments
+    /// * `result` - Number of rows returned, or the execution error
+    /// * `elapsed` - Time spent executing
+    fn after(&self, _meta: &QueryMeta, _result: Result<u64, &Error>, _elapsed: Duration) {}
+}
+
+/// Tracks one execution and calls `after` on completion, failure or drop
+pub(crate) struct MiddlewareScope {
+    middlewares: Vec<Arc<dyn QueryMiddleware>>,
+    observers: Vec<Arc<dyn QueryObserver>>,
+    sql_fingerprint: u64,
+    bind_count: usize,
+    meta: QueryMeta,
+    start: Instant,
+    rows: u64,
+    done: bool,
+}
+
+impl MiddlewareScope {
+    /// Runs `before` hooks and starts timing
+    pub(crate) fn before(
+        middlewares: Vec<Arc<dyn QueryMiddleware>>,
+        meta: QueryMeta,
+        sql: &mut String,
+    ) -> Self {
+        for m in &middlewares {
+            m.before(sql, &meta);
+        }
+        Self {
+            middlewares,
+            observers: Vec::new(),
+            sql_fingerprint: 0,
+            bind_count: 0,
+            meta,
+            start: Instant::now(),
+            rows: 0,
+            done: false,
+        }
+    }
+    /// Notifies `observers` with a [`QueryEvent`] on completion
+    pub(crate) fn observe(
+        &mut self,
+        observers: Vec<Arc<dyn QueryObserver>>,
+        sql: &str,
+        bind_count: usize,
+    ) {
+        if !observers.is_empty() {
+            self.sql_fingerprint = fingerprint(sql);
+        }
+        self.observers = observers;
+        self.bind_count = bind_count;
+    }
+    /// Counts a returned row
+    pub(crate) fn row(&mut self) {
+        self.rows += 1;
+    }
+    /// Runs `after` hooks with the error
+    pub(crate) fn fail(&mut self, e: &Error) {
+        self.finish(Err(e));
+    }
+    fn finish(&mut self, result: Result<u64, &Error>) {
+        if self.done {
+            return;
+        }
+        self.done = true;
+        let elapsed = self.start.elapsed();
+        for m in self.middlewares.iter().rev() {
+            m.after(&self.meta, result, elapsed);
+        }
+        if self.observers.is_empty() {
+            return;
+        }
+        let event = QueryEvent {
+            meta: &self.meta,
+            sql_fingerprint: self.sql_fingerprint,
+            bind_count: self.bind_count,
+            duration: elapsed,
+            rows: self.rows,
+            outcome: result.map(|_| ()),
+        };
+        for o in &self.observers {
+            o.on_query(&event);
+        }
+    }
+}
+
+impl Drop for MiddlewareScope {
+    fn drop(&mut self) {
+        let rows = self.rows;
+        self.finish(Ok(rows));
+    }
+}
+
+/// Callback of [`crate::DBAdapter::with_slow_query_threshold`]
+pub(crate) type SlowQueryCallback = Arc<dyn Fn(&str, &QueryMeta, Duration) + Send + Sync>;
+
+/// Middleware of a single adapter calling back when its query exceeds a threshold
+pub(crate) struct SlowQuery {
+    threshold: Duration,
+    callback: SlowQueryCallback,
+    sql: Mutex<String>,
+}
+
+impl SlowQuery {
+    pub(crate) fn new(threshold: Duration, callback: SlowQueryCallback) -> Self {
+        Self {
+            threshold,
+            callback,
+            sql: Mutex::new(String::new()),
+        }
+    }
+}
+
+impl QueryMiddleware for SlowQuery {
+    fn before(&self, sql: &mut String, _meta: &QueryMeta) {
+        sql.clone_into(&mut self.sql.lock().unwrap_or_else(PoisonError::into_inner));
+    }
+    fn after(&self, meta: &QueryMeta, _result: Result<u64, &Error>, elapsed: Duration) {
+        if elapsed >= self.threshold {
+            let sql = self.sql.lock().unwrap_or_else(PoisonError::into_inner);
+            (self.callback)(&sql, meta, elapsed);
+        }
+    }
+}
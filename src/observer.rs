@@ -0,0 +1,71 @@
+use std::{
+    sync::{Arc, PoisonError, RwLock},
+    time::Duration,
+};
+
+use sqlx_core::Error;
+
+use crate::QueryMeta;
+
+/// Structured record of one query executed through [`crate::DBAdapter`]
+#[derive(Debug, Clone, Copy)]
+pub struct QueryEvent<'a> {
+    /// Template name, backend, operation and pagination
+    pub meta: &'a QueryMeta,
+    /// [`crate::fingerprint`] of the executed SQL, stable across processes
+    pub sql_fingerprint: u64,
+    /// Number of bound arguments
+    pub bind_count: usize,
+    /// Time spent executing (for streams, until the stream ended or was dropped)
+    pub duration: Duration,
+    /// Number of rows returned
+    pub rows: u64,
+    /// Whether the query succeeded
+    pub outcome: Result<(), &'a Error>,
+}
+
+/// Sink receiving a [`QueryEvent`] after each execution
+///
+/// Installed globally with [`add_query_observer`] or per adapter with
+/// `DBAdapter::add_observer`, so metrics and logging stay independent of any
+/// specific framework.
+pub trait QueryObserver: Send + Sync {
+    /// Called once per executed query
+    fn on_query(&self, event: &QueryEvent<'_>);
+}
+
+impl<F> QueryObserver for F
+where
+    F: Fn(&QueryEvent<'_>) + Send + Sync,
+{
+    fn on_query(&self, event: &QueryEvent<'_>) {
+        self(event)
+    }
+}
+
+static OBSERVERS: RwLock<Vec<Arc<dyn QueryObserver>>> = RwLock::new(Vec::new());
+
+/// Installs an observer notified of every query of every adapter
+pub fn add_query_observer(observer: Arc<dyn QueryObserver>) {
+    OBSERVERS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(observer);
+}
+
+/// Removes all observers installed with [`add_query_observer`]
+pub fn clear_query_observers() {
+    OBSERVERS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clear();
+}
+
+/// Global observers followed by `local` ones
+pub(crate) fn observers(mut local: Vec<Arc<dyn QueryObserver>>) -> Vec<Arc<dyn QueryObserver>> {
+    let global = OBSERVERS.read().unwrap_or_else(PoisonError::into_inner);
+    if !global.is_empty() {
+        local.splice(0..0, global.iter().cloned());
+    }
+    local
+}
@@ -0,0 +1,114 @@
+use sqlx_core::{database::Database, executor::Executor, pool::Pool};
+
+use crate::{
+    AdapterExecutor, BackendDB, DBType, Error, PinnedConnection, QueryAccess, SessionSetup,
+    detect_backend_db,
+};
+
+/// Adapter splitting queries between a replica and a primary pool
+///
+/// `fetch*` and `count` run on `read`, `execute*` on `write`. For
+/// read-your-writes, pass [`Self::primary`] instead of the adapter so reads
+/// also go to the primary.
+///
+/// # Example
+/// ```no_run
+/// use sqlx::{FromRow, SqlitePool};
+/// use sqlx_askama_template::{ReadWriteAdapter, SqlTemplate};
+///
+/// #[derive(SqlTemplate)]
+/// #[template(source = "insert into users (name) values ({{ e(name) }})")]
+/// struct InsertUser {
+///     name: String,
+/// }
+///
+/// #[derive(SqlTemplate)]
+/// #[template(source = "select id, name from users where name = {{ e(name) }}")]
+/// struct UserQuery {
+///     name: String,
+/// }
+///
+/// #[derive(FromRow)]
+/// struct User {
+///     id: i64,
+///     name: String,
+/// }
+///
+/// # async fn run(replica_pool: SqlitePool, primary_pool: SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+/// let db = ReadWriteAdapter::new(replica_pool, primary_pool);
+/// let insert = InsertUser { name: "ann".into() };
+/// let query = UserQuery { name: "ann".into() };
+///
+/// insert.adapter().execute(&db).await?; // primary
+/// let users = (&query).adapter().fetch_all_as::<_, User>(&db).await?; // replica
+/// let user = (&query).adapter().fetch_one_as::<_, User>(db.primary()).await?; // primary
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReadWriteAdapter<DB: Database> {
+    /// Pool for read queries (replica)
+    pub read: Pool<DB>,
+    /// Pool for write queries (primary)
+    pub write: Pool<DB>,
+}
+
+impl<DB: Database> ReadWriteAdapter<DB> {
+    /// Creates an adapter from a replica and a primary pool
+    pub fn new(read: Pool<DB>, write: Pool<DB>) -> Self {
+        Self { read, write }
+    }
+    /// Primary pool, for queries that must see the latest writes
+    pub fn primary(&self) -> &Pool<DB> {
+        &self.write
+    }
+    /// Pool used for the given access
+    pub fn pool_for(&self, access: QueryAccess) -> &Pool<DB> {
+        match access {
+            QueryAccess::Read => &self.read,
+            QueryAccess::Write => &self.write,
+        }
+    }
+}
+
+impl<'c, DB> BackendDB<'c, DB> for &'c ReadWriteAdapter<DB>
+where
+    DB: Database,
+    for<'c1> &'c1 mut DB::Connection: Executor<'c1, Database = DB>,
+{
+    type DatabaseDialect = DBType;
+    type Executor = AdapterExecutor<'c, DB, &'c Pool<DB>>;
+    async fn backend_db(self) -> Result<(Self::DatabaseDialect, Self::Executor), Error> {
+        detect_backend_db(&self.read).await
+    }
+    async fn backend_db_for(
+        self,
+        access: QueryAccess,
+    ) -> Result<(Self::DatabaseDialect, Self::Executor), Error> {
+        detect_backend_db(self.pool_for(access)).await
+    }
+    async fn backend_db_with_session(
+        self,
+        access: QueryAccess,
+        session: &SessionSetup<DB>,
+    ) -> Result<(Self::DatabaseDialect, Self::Executor), Error> {
+        self.pool_for(access)
+            .backend_db_with_session(access, session)
+            .await
+    }
+    fn cancel_pool(&self, access: QueryAccess) -> Option<Pool<DB>> {
+        Some(self.pool_for(access).clone())
+    }
+    fn known_dialect(&self, access: QueryAccess) -> Option<Self::DatabaseDialect> {
+        self.pool_for(access).known_dialect(access)
+    }
+    async fn backend_db_pinned(
+        self,
+        access: QueryAccess,
+        session: &SessionSetup<DB>,
+    ) -> Result<(Self::DatabaseDialect, PinnedConnection<DB>), Error> {
+        self.pool_for(access)
+            .backend_db_pinned(access, session)
+            .await
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    fmt::{Debug, Write},
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+/// Placeholder printed instead of a masked value
+pub const REDACTED: &str = "<redacted>";
+
+/// Controls which template values are masked in debug output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Redaction {
+    /// Mask only fields marked with `#[sensitive]` (default)
+    Sensitive,
+    /// Mask `#[sensitive]` fields and every string-typed field
+    AllStrings,
+    /// Print every value as is
+    Off,
+}
+
+static REDACTION: AtomicU8 = AtomicU8::new(0);
+
+/// Sets the global redaction mode used by debug logging
+pub fn set_redaction(redaction: Redaction) {
+    let v = match redaction {
+        Redaction::Sensitive => 0,
+        Redaction::AllStrings => 1,
+        Redaction::Off => 2,
+    };
+    REDACTION.store(v, Ordering::Relaxed);
+}
+
+/// Returns the global redaction mode
+pub fn redaction() -> Redaction {
+    match REDACTION.load(Ordering::Relaxed) {
+        1 => Redaction::AllStrings,
+        2 => Redaction::Off,
+        _ => Redaction::Sensitive,
+    }
+}
+
+/// Debug view of one template field, generated by the `SqlTemplate` derive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DebugParam {
+    /// Field name
+    pub name: &'static str,
+    /// `Debug` representation of the value (`<?>` if the type is not `Debug`)
+    pub value: String,
+    /// Field is marked with `#[sensitive]` or `#[param(secret)]`
+    pub sensitive: bool,
+    /// Field holds a string type (`String`, `&str`, `Option<String>`...)
+    pub is_string: bool,
+    /// Field is marked with `#[raw]`, i.e. interpolated into the SQL on purpose
+    pub raw: bool,
+}
+
+impl DebugParam {
+    /// Creates a debug entry
+    ///
+    /// The derive decides `is_string` with [`StringValue`].
+    pub fn new(name: &'static str, debug: String, sensitive: bool, is_string: bool) -> Self {
+        Self {
+            name,
+            value: debug,
+            sensitive,
+            is_string,
+            raw: false,
+        }
+    }
+    /// Marks the field as interpolated on purpose (`#[raw]`)
+    pub fn with_raw(mut self, raw: bool) -> Self {
+        self.raw = raw;
+        self
+    }
+    /// Returns the value to print under the given redaction mode
+    pub fn display_value(&self, redaction: Redaction) -> &str {
+        let masked = match redaction {
+            Redaction::Sensitive => self.sensitive,
+            Redaction::AllStrings => self.sensitive || self.is_string,
+            Redaction::Off => false,
+        };
+        if masked { REDACTED } else { &self.value }
+    }
+}
+
+/// Formats template fields as `name=value, ...` applying the global redaction mode
+pub fn redact_params(params: &[DebugParam]) -> String {
+    let redaction = redaction();
+    let mut s = String::new();
+    for (i, p) in params.iter().enumerate() {
+        if i > 0 {
+            s.push_str(", ");
+        }
+        let _ = write!(s, "{}={}", p.name, p.display_value(redaction));
+    }
+    s
+}
+
+/// Wrapper used by the derive to print a field with `Debug` when available
+///
+/// Resolution goes through [`DebugValue`] for `Debug` types and falls back to
+/// [`DebugFallback`] (printing `<?>`) otherwise. Likewise [`StringValue`]
+/// answers `is_string` for [`StrLike`] types and [`StringFallback`] for the
+/// others.
+pub struct DebugProbe<'a, T: ?Sized>(pub &'a T);
+
+/// Prints a value with its `Debug` implementation
+pub trait DebugValue {
+    fn debug_value(&self) -> String;
+}
+impl<T: Debug + ?Sized> DebugValue for DebugProbe<'_, T> {
+    fn debug_value(&self) -> String {
+        format!("{:?}", self.0)
+    }
+}
+/// Fallback for values without a `Debug` implementation
+pub trait DebugFallback {
+    fn debug_value(&self) -> String;
+}
+impl<T: ?Sized> DebugFallback for &DebugProbe<'_, T> {
+    fn debug_value(&self) -> String {
+        "<?>".to_string()
+    }
+}
+
+/// String types, masked by [`Redaction::AllStrings`]
+///
+/// Implemented for `str`, `String`, the smart pointers and `Cow` of `str`,
+/// and references, `Option`s and lists of string types.
+pub trait StrLike {}
+impl StrLike for str {}
+impl StrLike for String {}
+impl StrLike for std::borrow::Cow<'_, str> {}
+impl StrLike for Box<str> {}
+impl StrLike for std::rc::Rc<str> {}
+impl StrLike for std::sync::Arc<str> {}
+impl<T: StrLike + ?Sized> StrLike for &T {}
+impl<T: StrLike + ?Sized> StrLike for &mut T {}
+impl<T: StrLike> StrLike for Option<T> {}
+impl<T: StrLike> StrLike for Vec<T> {}
+impl<T: StrLike> StrLike for [T] {}
+impl<T: StrLike, const N: usize> StrLike for [T; N] {}
+
+/// Tells a [`StrLike`] value apart
+pub trait StringValue {
+    fn is_string(&self) -> bool;
+}
+impl<T: StrLike + ?Sized> StringValue for DebugProbe<'_, T> {
+    fn is_string(&self) -> bool {
+        true
+    }
+}
+/// Fallback for values that are not [`StrLike`]
+pub trait StringFallback {
+    fn is_string(&self) -> bool;
+}
+impl<T: ?Sized> StringFallback for &DebugProbe<'_, T> {
+    fn is_string(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the explicit borrows pick the autoref candidate, as in the derive
+    #[allow(clippy::needless_borrow)]
+    #[test]
+    fn string_probe() {
+        #[allow(dead_code)]
+        struct Instruction(String);
+
+        let name = String::from("a");
+        assert!((&DebugProbe(&name)).is_string());
+        assert!((&DebugProbe(&"a")).is_string());
+        assert!((&DebugProbe(&Some(std::borrow::Cow::Borrowed("a")))).is_string());
+        assert!((&DebugProbe(&vec![name.clone()])).is_string());
+        assert!(!(&DebugProbe(&1i64)).is_string());
+        assert!(!(&DebugProbe(&Some(1u8))).is_string());
+        assert!(!(&DebugProbe(&Instruction(name))).is_string());
+    }
+}
@@ -0,0 +1,31 @@
+/// A template derived with `SqlTemplate`, `SqlTemplateSet` (one per query) or
+/// on an enum (one per variant), listed by [`registry`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisteredTemplate {
+    /// Statement name, e.g. `my_crate::queries::UserQuery`, see
+    /// [`crate::SqlTemplate::QUERY_TAG`]; enum variants are named
+    /// `my_crate::queries::UserQuery::ById`
+    pub name: &'static str,
+    /// Template source, read from the file for `path` templates (empty when
+    /// it is not found under `templates/`)
+    pub source: &'static str,
+    /// See [`crate::SqlTemplate::TEMPLATE_FINGERPRINT`]
+    pub fingerprint: u64,
+}
+
+inventory::collect!(RegisteredTemplate);
+
+/// Every template of the program derived with the `registry` feature, in no
+/// particular order
+///
+/// Templates opt out with `#[sql_template(registry = false)]`.
+///
+/// # Example
+/// ```
+/// for template in sqlx_askama_template::registry() {
+///     tracing::info!(name = template.name, fingerprint = template.fingerprint, "template");
+/// }
+/// ```
+pub fn registry() -> impl Iterator<Item = &'static RegisteredTemplate> {
+    inventory::iter::<RegisteredTemplate>.into_iter()
+}
@@ -0,0 +1,19 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static SAFETY_LIMIT: AtomicU64 = AtomicU64::new(0);
+
+/// Sets the global row limit appended to unbounded `SELECT` statements
+/// (default `0`, disabled)
+///
+/// When set, [`crate::DBAdapter`] fetches of a statement starting with `SELECT`
+/// that is not paginated and has no top-level `LIMIT` get one, so an ad-hoc
+/// template can not dump a whole table by accident. Adapters override it with
+/// `DBAdapter::with_safety_limit`.
+pub fn set_safety_limit(limit: u64) {
+    SAFETY_LIMIT.store(limit, Ordering::Relaxed);
+}
+
+/// Current limit set by [`set_safety_limit`]
+pub fn safety_limit() -> u64 {
+    SAFETY_LIMIT.load(Ordering::Relaxed)
+}
@@ -0,0 +1,195 @@
+use std::{any::Any, collections::HashSet, fmt};
+
+use sqlx_core::{
+    arguments::Arguments, database::Database, encode::Encode, executor::Executor,
+    from_row::FromRow, pool::Pool, types::Type,
+};
+
+use crate::{
+    DBType, DatabaseDialect, Error, SqlTemplate, SqlTemplateExecute, detect_backend_db,
+    with_identifier_quoting,
+};
+
+type RenderFn<'s, DB> = Box<
+    dyn FnOnce(
+            Option<fn(usize, &mut String)>,
+            &mut String,
+        ) -> Result<Option<<DB as Database>::Arguments>, Error>
+        + Send
+        + 's,
+>;
+
+/// One named step of a [`ScriptRunner`]
+struct Script<'s, DB: Database> {
+    name: String,
+    only_for: Option<DBType>,
+    render: RenderFn<'s, DB>,
+}
+
+/// Runs a sequence of SQL templates in order, e.g. seed or maintenance scripts
+///
+/// All scripts run inside one transaction (databases with implicit DDL commits,
+/// like MySQL, commit earlier). With a tracking table, applied script names are
+/// recorded and skipped on later runs.
+///
+/// # Example
+/// ```no_run
+/// use sqlx::PgPool;
+/// use sqlx_askama_template::{DBType, ScriptRunner, SqlTemplate};
+///
+/// #[derive(SqlTemplate)]
+/// #[template(source = "create table if not exists users (id bigserial primary key, name text)")]
+/// struct CreateUsers;
+///
+/// #[derive(SqlTemplate)]
+/// #[template(source = "create index if not exists users_name on users (name)")]
+/// struct UsersIndexPg;
+///
+/// # async fn run(pool: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+/// let applied = ScriptRunner::new()
+///     .with_tracking_table("_seed_scripts")
+///     .add("create_users", &CreateUsers)
+///     .add_for(DBType::PostgreSQL, "users_index", &UsersIndexPg)
+///     .add_sql("seed_admin", "insert into users(name) values ('admin')")
+///     .run(&pool)
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ScriptRunner<'s, DB: Database> {
+    scripts: Vec<Script<'s, DB>>,
+    tracking_table: Option<String>,
+}
+
+/// Lists the script names and the tracking table
+impl<DB: Database> fmt::Debug for ScriptRunner<'_, DB> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = self.scripts.iter().map(|s| s.name.as_str()).collect();
+        f.debug_struct("ScriptRunner")
+            .field("scripts", &names)
+            .field("tracking_table", &self.tracking_table)
+            .finish()
+    }
+}
+
+impl<DB: Database> Default for ScriptRunner<'_, DB> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'s, DB: Database> ScriptRunner<'s, DB> {
+    /// Creates an empty runner
+    pub fn new() -> Self {
+        Self {
+            scripts: Vec::new(),
+            tracking_table: None,
+        }
+    }
+    /// Records applied script names in `table` and skips them on later runs
+    pub fn with_tracking_table(mut self, table: impl Into<String>) -> Self {
+        self.tracking_table = Some(table.into());
+        self
+    }
+    /// Adds a template script
+    pub fn add<'q, T>(self, name: impl Into<String>, template: T) -> Self
+    where
+        T: SqlTemplate<'q, DB> + 's,
+    {
+        self.push(name.into(), None, template)
+    }
+    /// Adds a template script that only runs on the given database
+    pub fn add_for<'q, T>(self, db_type: DBType, name: impl Into<String>, template: T) -> Self
+    where
+        T: SqlTemplate<'q, DB> + 's,
+    {
+        self.push(name.into(), Some(db_type), template)
+    }
+    /// Adds a plain SQL script without parameters
+    pub fn add_sql(mut self, name: impl Into<String>, sql: impl Into<String>) -> Self {
+        let sql = sql.into();
+        self.scripts.push(Script {
+            name: name.into(),
+            only_for: None,
+            render: Box::new(move |_, buf: &mut String| {
+                buf.push_str(&sql);
+                Ok(None)
+            }),
+        });
+        self
+    }
+    fn push<'q, T>(mut self, name: String, only_for: Option<DBType>, template: T) -> Self
+    where
+        T: SqlTemplate<'q, DB> + 's,
+    {
+        self.scripts.push(Script {
+            name,
+            only_for,
+            render: Box::new(move |f, buf: &mut String| template.render_with_placeholder(f, buf)),
+        });
+        self
+    }
+
+    /// Executes all pending scripts in one transaction
+    ///
+    /// # Returns
+    /// Names of the scripts applied by this run
+    pub async fn run(self, pool: &Pool<DB>) -> Result<Vec<String>, Error>
+    where
+        DB::Connection: Any,
+        for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+        for<'r> (String,): FromRow<'r, DB::Row>,
+        String: for<'q> Encode<'q, DB> + Type<DB>,
+    {
+        let mut tx = pool.begin().await?;
+        let (db_type, _) = detect_backend_db(&mut *tx).await?;
+
+        let mut applied = HashSet::new();
+        if let Some(table) = &self.tracking_table {
+            let create = format!(
+                "create table if not exists {table} (name varchar(255) not null primary key)"
+            );
+            SqlTemplateExecute::<DB>::new(create, None)
+                .execute(&mut *tx)
+                .await?;
+            let select = format!("select name from {table}");
+            let names: Vec<(String,)> = SqlTemplateExecute::<DB>::new(select, None)
+                .fetch_all_as(&mut *tx)
+                .await?;
+            applied.extend(names.into_iter().map(|(name,)| name));
+        }
+
+        let mut done = Vec::new();
+        for script in self.scripts {
+            if applied.contains(&script.name)
+                || script.only_for.as_ref().is_some_and(|db| *db != db_type)
+            {
+                continue;
+            }
+            let mut sql = String::new();
+            let arg = with_identifier_quoting(&db_type, || {
+                (script.render)(db_type.placeholder_fn(), &mut sql)
+            })?;
+            SqlTemplateExecute::<DB>::new(sql, arg)
+                .execute(&mut *tx)
+                .await?;
+
+            if let Some(table) = &self.tracking_table {
+                let mut sql = format!("insert into {table} (name) values (");
+                let mut arg = DB::Arguments::default();
+                arg.add(script.name.clone())
+                    .map_err(|e| Error::Encode(sqlx_core::Error::Encode(e).into()))?;
+                if let Some(f) = db_type.placeholder_fn() {
+                    f(arg.len(), &mut sql);
+                }
+                sql.push(')');
+                SqlTemplateExecute::<DB>::new(sql, Some(arg))
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            done.push(script.name);
+        }
+        tx.commit().await?;
+        Ok(done)
+    }
+}
@@ -1,18 +1,52 @@
-use std::marker::PhantomData;
+use std::{fmt, marker::PhantomData, sync::Arc, time::Duration};
 
 use crate::SqlTemplate;
 use askama::Result;
 use futures_core::{Stream, future::BoxFuture, stream::BoxStream};
 
-use futures_util::{FutureExt, StreamExt, TryFutureExt, TryStreamExt, future};
+use futures_util::{
+    AsyncWrite, AsyncWriteExt, FutureExt, StreamExt, TryFutureExt, TryStreamExt,
+    future::{self, Shared},
+    pin_mut,
+};
 use sqlx_core::{
-    Either, Error, database::Database, encode::Encode, from_row::FromRow, types::Type,
+    Either, Error as SqlxError,
+    arguments::Arguments,
+    column::{Column, ColumnIndex},
+    connection::Connection,
+    database::Database,
+    decode::Decode,
+    encode::Encode,
+    executor::Executor,
+    from_row::FromRow,
+    row::Row,
+    types::Type,
 };
 
-use crate::{DatabaseDialect, db_adapter::BackendDB, sql_template_execute::SqlTemplateExecute};
+use crate::{
+    BackendDB, COUNTED_ROW_COLUMN, COUNTED_TOTAL_COLUMN, CountOver, DatabaseDialect, Error,
+    ErrorContext, ListWindow, LocalSettingBind, PageRequest, PageRequestError, QueryAccess,
+    QueryMeta, QueryOptions, SessionSetup, SortSpec, SqlTemplateExecute, StatementKind,
+    TemplateContext, UsageError, begin_savepoint,
+    cancel::{CancelTag, cancellable},
+    capabilities::{self, CapabilityProbe, context_with_capabilities},
+    check_sort_columns,
+    count_cache::{
+        CountCache, CountCacheKey, arguments_fingerprint, fingerprint, fingerprint_bytes,
+    },
+    default_page_size::default_page_size,
+    end_savepoint,
+    export::{ColumnValue, CsvOptions, RowValues, write_json_row},
+    keyset::{Cursor, Keyset, bind_column_value, result_column, write_keyset},
+    middleware::{MiddlewareScope, QueryMetaExt, QueryMiddleware, SlowQuery, SlowQueryCallback},
+    observer::{QueryObserver, observers},
+    redact::redact_params,
+    safety_limit::safety_limit,
+    split_plan, with_context, with_identifier_quoting, with_list_window, write_ungrouped_sql,
+};
 
 /// Pagination metadata container
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct PaginationInfo {
     /// Total number of records
     pub total: i64,
@@ -25,14 +59,19 @@ pub struct PaginationInfo {
 impl PaginationInfo {
     /// Constructs new PaginationInfo with automatic pagination count calculation
     ///
+    /// A `pagination_size` of 0 or less means no pagination: all records are
+    /// on a single pagination. A negative `total` counts as 0.
+    ///
     /// # Arguments
     /// * `total` - Total records in dataset
     /// * `pagination_size` - Desired records per pagination
     pub fn new(total: i64, pagination_size: i64) -> PaginationInfo {
-        let mut pagination_count = total / pagination_size;
-        if total % pagination_size > 0 {
-            pagination_count += 1;
-        }
+        let total = total.max(0);
+        let pagination_count = if pagination_size < 1 {
+            1
+        } else {
+            total / pagination_size + i64::from(total % pagination_size > 0)
+        };
         Self {
             total,
             pagination_size,
@@ -40,13 +79,155 @@ impl PaginationInfo {
         }
     }
 }
+/// Rows of one pagination, returned by [`DBAdapter::fetch_pagination_as`] and
+/// [`DBAdapter::fetch_counted_pagination_as`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaginatedRows<O> {
+    /// Rows of the pagination
+    pub rows: Vec<O>,
+    /// Pagination number the rows belong to, resolved from a negative number
+    pub pagination_no: i64,
+    /// Pagination metadata, if a count was needed to resolve the number or
+    /// requested with the rows
+    pub info: Option<PaginationInfo>,
+}
+/// Logs the template fields at debug level with redaction applied
+fn log_template_params<'q, DB, T>(template: &T)
+where
+    DB: Database,
+    T: SqlTemplate<'q, DB>,
+{
+    if tracing::enabled!(tracing::Level::DEBUG) {
+        tracing::debug!(
+            "Template params: {}",
+            redact_params(&template.debug_params())
+        );
+    }
+}
+/// Fails with [`Error::Render`] unless `sql` is a statement of `kind`
+fn check_statement(kind: StatementKind, sql: &str) -> Result<(), Error> {
+    if kind.matches(sql) {
+        return Ok(());
+    }
+    Err(Error::Render(
+        SqlxError::from(UsageError::StatementKind {
+            expected: kind,
+            found: StatementKind::of(sql),
+        })
+        .into(),
+    ))
+}
+/// Executor of a read query, with the session of the adapter prepared
+async fn count_backend<'c, DB, Adapter>(
+    db_adapter: Adapter,
+    session: &SessionSetup<DB>,
+) -> Result<(Adapter::DatabaseDialect, Adapter::Executor), Error>
+where
+    DB: Database,
+    Adapter: BackendDB<'c, DB>,
+{
+    if session.is_empty() {
+        db_adapter.backend_db_for(QueryAccess::Read).await
+    } else {
+        db_adapter
+            .backend_db_with_session(QueryAccess::Read, session)
+            .await
+    }
+}
+/// Whether `column` is a plain column name, `name` or `table.name`
+fn is_column_name(column: &str) -> bool {
+    column.split('.').all(|part| {
+        part.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+/// Appends the `ORDER BY` of [`DBAdapter::with_sort`], failing with
+/// [`Error::Render`] on a column outside `allowed`
+fn write_sort(
+    db_type: &impl DatabaseDialect,
+    sql: &mut String,
+    sort: &[SortSpec],
+    allowed: &[&str],
+) -> Result<(), Error> {
+    if sort.is_empty() {
+        return Ok(());
+    }
+    check_sort_columns(sort, allowed).map_err(|e| Error::Render(SqlxError::from(e).into()))?;
+    db_type.write_sort_sql(sql, sort);
+    Ok(())
+}
+/// Applies the schema, the safety limit and the timeout hint to the rendered
+/// SQL of an unpaginated query
+fn finish_sql(
+    db_type: &impl DatabaseDialect,
+    sql: &mut String,
+    schema: &Option<String>,
+    safety_limit_override: Option<u64>,
+    timeout: Option<Duration>,
+) {
+    if let Some(schema) = schema {
+        db_type.write_schema(sql, schema);
+    }
+    let limit = safety_limit_override.unwrap_or_else(safety_limit);
+    if limit > 0 {
+        db_type.write_safety_limit(sql, limit);
+    }
+    if let Some(timeout) = timeout {
+        db_type.write_timeout_sql(sql, timeout);
+    }
+}
+/// Prefixes `sql` with the comment `/* {tag} */` for [`DBAdapter::with_query_tag`],
+/// followed by the template fingerprint in hex when it is known
+fn write_query_tag(sql: &mut String, meta: &QueryMeta) {
+    let tag = meta.template.replace("*/", "* /");
+    let comment = match meta.template_fingerprint {
+        0 => format!("/* {tag} */ "),
+        fingerprint => format!("/* {tag} {fingerprint:016x} */ "),
+    };
+    sql.insert_str(0, &comment);
+}
+/// Installed middlewares, plus the slow query check as the innermost one
+fn middleware_stack(
+    mut middlewares: Vec<Arc<dyn QueryMiddleware>>,
+    slow_query: Option<(Duration, SlowQueryCallback)>,
+) -> Vec<Arc<dyn QueryMiddleware>> {
+    if let Some((threshold, callback)) = slow_query {
+        middlewares.push(Arc::new(SlowQuery::new(threshold, callback)));
+    }
+    middlewares
+}
+/// Pagination state of a [`DBAdapter`] created by [`SqlTemplate::adapter`]
+///
+/// Counting and setting the pagination are only available in this state.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Unpaged;
+
+/// Pagination state of a [`DBAdapter`] after [`DBAdapter::set_pagination`]
+/// or [`DBAdapter::set_page`]
+///
+/// The pagination can not be set again, and the adapter can not count: a
+/// count is always of all the rows, see [`PaginationInfo`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Paged;
+
 /// Database adapter manager handling SQL rendering and execution
 ///
 /// # Generic Parameters
 /// - `'q`: Query lifetime
 /// - `DB`: Database type
 /// - `T`: SQL template type
-pub struct DBAdapter<'q, DB, T>
+/// - `P`: Pagination state, [`Unpaged`] or [`Paged`]
+///
+/// The state turns mixing up counting and pagination into compile errors:
+/// ```compile_fail
+/// # use sqlx_askama_template::SqlTemplate;
+/// # use sqlx_core::{any::Any, pool::Pool};
+/// # async fn f<'q, T: SqlTemplate<'q, Any> + 'q>(query: T, pool: Pool<Any>) {
+/// // counts all the rows, not the page: count before paging instead
+/// let total = query.adapter().set_pagination(20, 1).count(&pool).await;
+/// # }
+/// ```
+pub struct DBAdapter<'q, DB, T, P = Unpaged>
 where
     DB: Database,
     T: SqlTemplate<'q, DB>,
@@ -56,6 +237,24 @@ where
     _p: PhantomData<&'q DB>,
     pagination_size: Option<i64>,
     pagination_no: Option<i64>,
+    count_cache: Option<(Arc<dyn CountCache>, CountCacheKey)>,
+    middlewares: Vec<Arc<dyn QueryMiddleware>>,
+    schema: Option<String>,
+    slow_query: Option<(Duration, SlowQueryCallback)>,
+    observers: Vec<Arc<dyn QueryObserver>>,
+    context: Option<Arc<TemplateContext>>,
+    session: SessionSetup<DB>,
+    safety_limit: Option<u64>,
+    statement_kind: StatementKind,
+    list_copies: Option<Arc<dyn Fn() -> T + Send + Sync + 'q>>,
+    cancellation: Option<Shared<BoxFuture<'static, ()>>>,
+    query_tag: bool,
+    sort: Vec<SortSpec>,
+    counted: bool,
+    keyset: Option<Keyset<DB>>,
+    count_over: CountOver,
+    capabilities: Option<CapabilityProbe<DB>>,
+    _state: PhantomData<P>,
 }
 
 impl<'q, DB, T> DBAdapter<'q, DB, T>
@@ -70,77 +269,667 @@ where
     pub fn new(template: T) -> Self {
         Self {
             template,
-            persistent: true,
+            persistent: T::PERSISTENT,
             pagination_no: None,
             pagination_size: None,
+            count_cache: None,
+            middlewares: Vec::new(),
+            schema: None,
+            slow_query: None,
+            observers: Vec::new(),
+            context: None,
+            session: SessionSetup::default(),
+            safety_limit: None,
+            statement_kind: StatementKind::Any,
+            list_copies: None,
+            cancellation: None,
+            query_tag: false,
+            sort: Vec::new(),
+            counted: false,
+            keyset: None,
+            count_over: CountOver::Groups,
+            capabilities: None,
             _p: PhantomData,
+            _state: PhantomData,
         }
     }
 }
-impl<'q, 'c, 'e, DB, T> DBAdapter<'q, DB, T>
+/// Clones the whole configuration, including pagination and a pending
+/// [`DBAdapter::with_cancellation`] shared by both adapters
+impl<'q, DB, T, P> Clone for DBAdapter<'q, DB, T, P>
+where
+    DB: Database,
+    T: SqlTemplate<'q, DB> + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            template: self.template.clone(),
+            persistent: self.persistent,
+            _p: PhantomData,
+            pagination_size: self.pagination_size,
+            pagination_no: self.pagination_no,
+            count_cache: self.count_cache.clone(),
+            middlewares: self.middlewares.clone(),
+            schema: self.schema.clone(),
+            slow_query: self.slow_query.clone(),
+            observers: self.observers.clone(),
+            context: self.context.clone(),
+            session: self.session.clone(),
+            safety_limit: self.safety_limit,
+            statement_kind: self.statement_kind,
+            list_copies: self.list_copies.clone(),
+            cancellation: self.cancellation.clone(),
+            query_tag: self.query_tag,
+            sort: self.sort.clone(),
+            counted: self.counted,
+            keyset: self.keyset.clone(),
+            count_over: self.count_over,
+            capabilities: self.capabilities,
+            _state: PhantomData,
+        }
+    }
+}
+/// Summarizes the configuration; template values are left out, see
+/// [`SqlTemplate::debug_params`]
+impl<'q, DB, T, P> fmt::Debug for DBAdapter<'q, DB, T, P>
+where
+    DB: Database,
+    T: SqlTemplate<'q, DB>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DBAdapter")
+            .field("template", &QueryMeta::for_template::<DB, T>("").template)
+            .field("persistent", &self.persistent)
+            .field("pagination", &self.pagination_size.zip(self.pagination_no))
+            .field("schema", &self.schema)
+            .field("sort", &self.sort)
+            .field("statement_kind", &self.statement_kind)
+            .field("session", &self.session)
+            .field("safety_limit", &self.safety_limit)
+            .field("middlewares", &self.middlewares.len())
+            .field("observers", &self.observers.len())
+            .field("count_cache", &self.count_cache.is_some())
+            .field("count_over", &self.count_over)
+            .field("capabilities", &self.capabilities.is_some())
+            .field("split_oversized_lists", &self.list_copies.is_some())
+            .field("cancellable", &self.cancellation.is_some())
+            .field("query_tag", &self.query_tag)
+            .finish_non_exhaustive()
+    }
+}
+impl<'q, 'c, 'e, DB, T, P> DBAdapter<'q, DB, T, P>
 where
     DB: Database + Sync,
     T: SqlTemplate<'q, DB> + Send + 'q,
     i64: Encode<'q, DB> + Type<DB>,
     DB::Arguments: 'q,
+    for<'c1> &'c1 mut DB::Connection: Executor<'c1, Database = DB>,
     'q: 'e,
     'c: 'e,
 {
-    /// Configures query persistence (default: true)
+    /// Configures query persistence (default: [`SqlTemplate::PERSISTENT`],
+    /// `true` unless the template sets `#[sql_template(persistent = false)]`)
     pub fn set_persistent(mut self, persistent: bool) -> Self {
         self.persistent = persistent;
         self
     }
-    /// Executes count query for pagination
+    /// Adds a middleware wrapping every query of this adapter
     ///
-    /// # Arguments
-    /// * `db_adapter` - Database connection adapter
-    #[inline]
-    pub fn count<Adapter>(self, db_adapter: Adapter) -> BoxFuture<'e, Result<i64, Error>>
+    /// Can be called repeatedly to stack middlewares.
+    pub fn add_middleware(mut self, middleware: Arc<dyn QueryMiddleware>) -> Self {
+        self.middlewares.push(middleware);
+        self
+    }
+    /// Calls `callback` with the SQL, query metadata and elapsed time whenever a
+    /// query of this adapter takes at least `threshold`
+    ///
+    /// Runs as the innermost middleware, so the SQL is the one sent to the
+    /// database. For streams, the time until the stream ends or is dropped counts.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use sqlx::{FromRow, SqlitePool};
+    /// # use sqlx_askama_template::SqlTemplate;
+    /// #
+    /// # #[derive(SqlTemplate, Clone)]
+    /// # #[template(source = "select id, name from users")]
+    /// # struct UserQuery;
+    /// #
+    /// # #[derive(FromRow)]
+    /// # struct User {
+    /// #     id: i64,
+    /// #     name: String,
+    /// # }
+    /// use std::time::Duration;
+    ///
+    /// # async fn run(pool: SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+    /// # let query = UserQuery;
+    /// query
+    ///     .adapter()
+    ///     .with_slow_query_threshold(Duration::from_millis(500), |sql, meta, elapsed| {
+    ///         tracing::warn!(template = meta.template, ?elapsed, "slow query: {sql}");
+    ///     })
+    ///     .fetch_all(&pool)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_slow_query_threshold<F>(mut self, threshold: Duration, callback: F) -> Self
+    where
+        F: Fn(&str, &QueryMeta, Duration) + Send + Sync + 'static,
+    {
+        self.slow_query = Some((threshold, Arc::new(callback)));
+        self
+    }
+    /// Replaces the `{schema}` token in the rendered SQL with the quoted schema name
+    ///
+    /// Lets schema-per-tenant deployments share templates such as
+    /// `select * from {schema}.users`.
+    pub fn with_schema(mut self, schema: impl Into<String>) -> Self {
+        self.schema = Some(schema.into());
+        self
+    }
+    /// Runs `statements` on the connection before the query, e.g. to set the
+    /// `search_path` or role for multi-tenancy
+    ///
+    /// The query is guaranteed to run on the same connection: a pool executor
+    /// hands out a dedicated connection, other executors are rejected with
+    /// [`Error::Dialect`]. The session state cannot be reset reliably, so the
+    /// connection is closed after the query instead of returned to the pool.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use sqlx::PgPool;
+    /// # use sqlx_askama_template::SqlTemplate;
+    /// #
+    /// # #[derive(SqlTemplate)]
+    /// # #[template(source = "select * from users")]
+    /// # struct UserQuery;
+    /// # async fn run(pool: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    /// # let query = UserQuery;
+    /// query
+    ///     .adapter()
+    ///     .with_session_setup(["SET search_path TO tenant_7", "SET ROLE app_ro"])
+    ///     .fetch_all(&pool)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_session_setup<I, S>(mut self, statements: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.session.statements = statements.into_iter().map(Into::into).collect();
+        self
+    }
+    /// Applies `timeout` on the database side, independently of any client-side
+    /// future timeout
+    ///
+    /// - PostgreSQL: `SET LOCAL statement_timeout` in a transaction around the
+    ///   query (needs a pool executor, like [`Self::with_session_setup`])
+    /// - MySQL: `/*+ MAX_EXECUTION_TIME */` hint, `SELECT` statements only:
+    ///   other statements run without timeout
+    /// - SQLite: `PRAGMA busy_timeout` (needs a pool executor); the setting
+    ///   would outlive the query, so the connection is closed afterwards
+    pub fn with_db_timeout(mut self, timeout: Duration) -> Self {
+        self.session.timeout = Some(timeout);
+        self
+    }
+    /// Refuses to execute the rendered SQL unless it is a statement of `kind`,
+    /// failing with [`Error::Render`]
+    ///
+    /// The leading keyword decides, skipping comments and the common table
+    /// expressions of a `WITH` statement. Guards templates that are partly
+    /// supplied by operators against DDL or unexpected DML.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use sqlx_askama_template::{SqlTemplate, StatementKind};
+    /// # use sqlx::SqlitePool;
+    ///
+    /// #[derive(SqlTemplate)]
+    /// #[template(source = "{{ operator_sql }}")]
+    /// #[sql_template(validate_sql = false)]
+    /// struct Report {
+    ///     #[raw]
+    ///     operator_sql: String,
+    /// }
+    ///
+    /// # async fn run(pool: SqlitePool, report: Report) -> Result<(), Box<dyn std::error::Error>> {
+    /// let rows = report
+    ///     .adapter()
+    ///     .expect_statement(StatementKind::Select)
+    ///     .fetch_all(&pool)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn expect_statement(mut self, kind: StatementKind) -> Self {
+        self.statement_kind = kind;
+        self
+    }
+    /// Prefixes the SQL with the comment `/* {tag} */`, where the tag is the
+    /// [`SqlTemplate::QUERY_TAG`] of the template, so that server-side logs
+    /// and statistics (e.g. `pg_stat_activity`, slow query logs) name queries
+    /// like middlewares and observers do through [`QueryMeta::template`]
+    ///
+    /// A derived template adds its [`SqlTemplate::TEMPLATE_FINGERPRINT`] in
+    /// hex, e.g. `/* app::UserQuery 9f2c4e1a0b7d3c55 */`, telling deploys
+    /// apart. The tag is the same for every execution, so prepared statements
+    /// are still reused.
+    pub fn with_query_tag(mut self) -> Self {
+        self.query_tag = true;
+        self
+    }
+    /// Appends `ORDER BY` for `sort`, before pagination
+    ///
+    /// Every column must be listed in `#[sql_template(order_by = "...")]`
+    /// (see [`SqlTemplate::SORT_COLUMNS`]); otherwise the query fails with
+    /// [`Error::Render`]. The template must not end with its own `ORDER BY`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use sqlx_askama_template::{SortSpec, SqlTemplate};
+    /// # use sqlx::{FromRow, SqlitePool};
+    ///
+    /// #[derive(SqlTemplate)]
+    /// #[template(source = "select * from users")]
+    /// #[sql_template(order_by = "name, created_at")]
+    /// struct UserQuery;
+    /// # #[derive(FromRow)]
+    /// # struct User {
+    /// #     name: String,
+    /// # }
+    ///
+    /// # async fn run(pool: SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+    /// # let query = UserQuery;
+    /// let users: Vec<User> = query
+    ///     .adapter()
+    ///     .with_sort([SortSpec::desc("created_at").nulls_last(), SortSpec::asc("name")])
+    ///     .set_pagination(20, 1)
+    ///     .fetch_all_as(&pool)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_sort(mut self, sort: impl IntoIterator<Item = SortSpec>) -> Self {
+        self.sort = sort.into_iter().collect();
+        self
+    }
+    /// Splits an `el()` list that would exceed the bind parameter limit of the
+    /// dialect, executing the query once per chunk of the list and merging the
+    /// results
+    ///
+    /// The chunks run one after another on one connection acquired from the
+    /// pool executor; other executors fail with [`Error::Dialect`]. Only the
+    /// longest list is split, so the mode suits `IN (...)` filters, where the
+    /// union of the chunk results is the full result, but not `NOT IN`,
+    /// ordering or limits across the whole list. Pagination is rejected.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use sqlx_askama_template::SqlTemplate;
+    /// # use sqlx::{FromRow, SqlitePool};
+    ///
+    /// #[derive(SqlTemplate)]
+    /// #[template(source = "select * from users where id in {{ el(ids) }}")]
+    /// #[add_type(i64)]
+    /// struct UserQuery {
+    ///     #[ignore_type]
+    ///     ids: Vec<i64>,
+    /// }
+    /// # #[derive(FromRow)]
+    /// # struct User {
+    /// #     id: i64,
+    /// # }
+    ///
+    /// # async fn run(pool: SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+    /// let query = UserQuery { ids: (0..100_000).collect() };
+    /// let users: Vec<User> = query
+    ///     .adapter()
+    ///     .split_oversized_lists()
+    ///     .fetch_all_as(&pool)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn split_oversized_lists(mut self) -> Self
+    where
+        T: Clone + Sync,
+    {
+        let template = self.template.clone();
+        self.list_copies = Some(Arc::new(move || template.clone()));
+        self
+    }
+    /// Abandons the query as soon as `cancelled` completes, e.g. the
+    /// `cancelled_owned()` future of a `tokio_util` `CancellationToken`
+    ///
+    /// The fetch and execute methods then fail with [`Error::Execute`] of kind
+    /// [`std::io::ErrorKind::Interrupted`], dropping their stream and its
+    /// connection. On PostgreSQL with a pool executor, the query is also
+    /// cancelled on the server, best effort, with `pg_cancel_backend` issued
+    /// from another pool connection; the SQL is tagged with a comment for this
+    /// and is not cached as a prepared statement.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use sqlx::PgPool;
+    /// # use sqlx_askama_template::SqlTemplate;
+    /// #
+    /// # #[derive(SqlTemplate)]
+    /// # #[template(source = "select * from report")]
+    /// # struct Report;
+    /// # async fn run(pool: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    /// # let report = Report;
+    /// let (cancel, cancelled) = tokio::sync::oneshot::channel::<()>();
+    /// let rows = report
+    ///     .adapter()
+    ///     .with_cancellation(async move {
+    ///         let _ = cancelled.await;
+    ///     })
+    ///     .fetch_all(&pool)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_cancellation<F>(mut self, cancelled: F) -> Self
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.cancellation = Some(cancelled.boxed().shared());
+        self
+    }
+    /// Overrides the global [`crate::set_safety_limit`] for this adapter;
+    /// `0` disables the limit, e.g. for intentional bulk exports
+    pub fn with_safety_limit(mut self, limit: u64) -> Self {
+        self.safety_limit = Some(limit);
+        self
+    }
+    /// Applies several statement-level options at once, e.g. from a per
+    /// endpoint configuration
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use sqlx::SqlitePool;
+    /// # use sqlx_askama_template::SqlTemplate;
+    /// #
+    /// # #[derive(SqlTemplate)]
+    /// # #[template(source = "select * from report")]
+    /// # struct Report;
+    /// use std::time::Duration;
+    ///
+    /// use sqlx_askama_template::QueryOptions;
+    ///
+    /// # async fn run(pool: SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+    /// # let report = Report;
+    /// let options = QueryOptions {
+    ///     persistent: Some(false),
+    ///     timeout: Some(Duration::from_secs(5)),
+    ///     max_rows: Some(10_000),
+    ///     ..Default::default()
+    /// };
+    /// let rows = report.adapter().with_options(options).fetch_all(&pool).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_options(mut self, options: QueryOptions) -> Self {
+        if let Some(persistent) = options.persistent {
+            self.persistent = persistent;
+        }
+        if let Some(timeout) = options.timeout {
+            self.session.timeout = Some(timeout);
+        }
+        if let Some(limit) = options.max_rows {
+            self.safety_limit = Some(limit);
+        }
+        self.query_tag |= options.query_tag;
+        self
+    }
+    /// Sets a transaction-local configuration parameter before the query, e.g.
+    /// the current user for PostgreSQL row-level security policies
+    ///
+    /// The query runs in a transaction on a dedicated pool connection, with
+    /// `SELECT set_config($1, $2::text, true)` issued first, the name and the
+    /// value bound as its arguments, so the setting never outlives the query.
+    /// Other backends fail with [`Error::Dialect`].
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use sqlx::{FromRow, PgPool};
+    /// # use sqlx_askama_template::SqlTemplate;
+    /// #
+    /// # #[derive(SqlTemplate)]
+    /// # #[template(source = "select id from orders")]
+    /// # struct OrderQuery;
+    /// #
+    /// # #[derive(FromRow)]
+    /// # struct Order {
+    /// #     id: i64,
+    /// # }
+    /// # async fn run(pool: PgPool, user_id: i64) -> Result<(), Box<dyn std::error::Error>> {
+    /// # let query = OrderQuery;
+    /// // CREATE POLICY own_orders ON orders
+    /// //     USING (user_id = current_setting('app.current_user_id')::bigint);
+    /// let orders: Vec<Order> = query
+    ///     .adapter()
+    ///     .with_local_setting("app.current_user_id", user_id)
+    ///     .fetch_all_as(&pool)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_local_setting<V>(mut self, name: impl Into<String>, value: V) -> Self
+    where
+        V: for<'v> Encode<'v, DB> + Type<DB> + Clone + Send + Sync + 'static,
+        String: for<'v> Encode<'v, DB> + Type<DB>,
+    {
+        let name = name.into();
+        let bound = name.clone();
+        let bind: LocalSettingBind<DB> = Arc::new(move |args| {
+            args.add(bound.clone())?;
+            args.add(value.clone())
+        });
+        self.session.local_settings.push((name, bind));
+        self
+    }
+    /// Attaches a [`TemplateContext`] readable in the template through `ctx()`
+    pub fn with_context(mut self, context: TemplateContext) -> Self {
+        self.context = Some(Arc::new(context));
+        self
+    }
+    /// Exposes the [`crate::Capabilities`] of the server to the template as context
+    /// values, e.g. `{% if ctx_flag("returning") %} returning id {% endif %}`
+    ///
+    /// They are detected with [`crate::detect_capabilities`] on first use of
+    /// each pool, telling MariaDB (with `RETURNING`) apart from MySQL. For
+    /// executors other than a pool, they are derived from the backend name.
+    pub fn with_capabilities(mut self) -> Self
+    where
+        (String,): for<'r> FromRow<'r, DB::Row>,
+    {
+        self.capabilities = Some(capabilities::probe::<DB>);
+        self
+    }
+    /// Adds an observer notified after each query of this adapter, in addition
+    /// to the global ones installed with [`crate::add_query_observer`]
+    pub fn add_observer(mut self, observer: Arc<dyn QueryObserver>) -> Self {
+        self.observers.push(observer);
+        self
+    }
+    /// Sets a cache consulted by [`Self::count`] before querying the database,
+    /// with entries told apart by `key`, see [`CountCacheKey`]
+    pub fn set_count_cache(mut self, cache: Arc<dyn CountCache>, key: CountCacheKey) -> Self {
+        self.count_cache = Some((cache, key));
+        self
+    }
+    /// Chooses what [`Self::count`] and the pagination counts of a `GROUP BY`
+    /// query count (default: [`CountOver::Groups`])
+    ///
+    /// With [`CountOver::Rows`], the select list of the query is replaced by
+    /// the count and its top-level `GROUP BY` is dropped, counting the rows
+    /// before grouping; queries without `GROUP BY` are counted as usual.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use sqlx_askama_template::{CountOver, SqlTemplate};
+    /// # use sqlx::SqlitePool;
+    ///
+    /// #[derive(SqlTemplate)]
+    /// #[template(source = "select customer_id, count(*) from orders group by customer_id")]
+    /// struct PerCustomer;
+    ///
+    /// # async fn run(pool: SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+    /// // select count(1) from orders (not one per customer)
+    /// let orders = PerCustomer.adapter().with_count_over(CountOver::Rows).count(&pool).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_count_over(mut self, count_over: CountOver) -> Self {
+        self.count_over = count_over;
+        self
+    }
+    /// [`Self::count`], or [`Self::count_distinct`] with a `distinct` column
+    fn count_column<Adapter>(
+        self,
+        distinct: Option<String>,
+        db_adapter: Adapter,
+    ) -> BoxFuture<'e, Result<i64, Error>>
     where
         Adapter: BackendDB<'c, DB> + 'c,
         (i64,): for<'r> FromRow<'r, DB::Row>,
     {
         let template = self.template;
+        let middlewares = middleware_stack(self.middlewares, self.slow_query);
+        let observers = observers(self.observers);
+        let count_cache = self.count_cache;
 
         async move {
-            let (db_type, executor) = db_adapter.backend_db().await?;
+            let operation = if distinct.is_some() {
+                "count_distinct"
+            } else {
+                "count"
+            };
+            let mut ctx = ErrorContext::new(QueryMeta::for_template::<DB, T>(operation));
+            if let Some(column) = distinct.as_deref().filter(|column| !is_column_name(column)) {
+                Err(ctx.wrap(Error::Render(
+                    SqlxError::from(UsageError::NotAColumn(column.to_string())).into(),
+                )))?;
+            }
+            let detected = match self
+                .capabilities
+                .and_then(|probe| Some((probe, db_adapter.cancel_pool(QueryAccess::Read)?)))
+            {
+                Some((probe, pool)) => Some(probe(pool).await.map_err(|e| ctx.wrap(e))?),
+                None => None,
+            };
+            // rendered before acquiring a connection when the dialect is
+            // known, so that a cached count touches no connection
+            let (db_type, backend) = match db_adapter.known_dialect(QueryAccess::Read) {
+                Some(db_type) => (db_type, Either::Left(db_adapter)),
+                None => {
+                    let (db_type, executor) = count_backend(db_adapter, &self.session)
+                        .await
+                        .map_err(|e| ctx.wrap(e))?;
+                    (db_type, Either::Right(executor))
+                }
+            };
+            ctx.set_backend(db_type.backend_name());
+            let context = match self.capabilities {
+                Some(_) => Some(context_with_capabilities(
+                    detected,
+                    db_type.backend_name(),
+                    self.context.as_ref(),
+                )),
+                None => self.context.clone(),
+            };
             let f = db_type.placeholder_fn();
             let mut sql = String::new();
-            let arg = template.render_with_placeholder(f, &mut sql)?;
+            log_template_params(&template);
+            // `{% block count %}` of the template, run as it is
+            let dedicated = distinct.is_none() && T::HAS_COUNT_QUERY;
+            let arg = with_identifier_quoting(&db_type, || {
+                with_context(context.as_ref(), || {
+                    if dedicated {
+                        template.render_count_with_placeholder(f, &mut sql)
+                    } else {
+                        template.render_with_placeholder(f, &mut sql)
+                    }
+                })
+            })
+            .map_err(|e| ctx.wrap(e))?;
+            check_statement(self.statement_kind, &sql).map_err(|e| {
+                ctx.set_sql(&sql);
+                ctx.wrap(e)
+            })?;
+            if let Some(schema) = &self.schema {
+                db_type.write_schema(&mut sql, schema);
+            }
 
-            db_type.write_count_sql(&mut sql);
+            let count = match &distinct {
+                Some(column) => format!("count(distinct {column})"),
+                None => "count(1)".to_string(),
+            };
+            let ungrouped = match self.count_over {
+                _ if dedicated => false,
+                CountOver::Groups => false,
+                CountOver::Rows => write_ungrouped_sql(&mut sql, &count).map_err(|e| {
+                    ctx.set_sql(&sql);
+                    ctx.dialect(UsageError::Unsupported(e).into())
+                })?,
+            };
+            match &distinct {
+                _ if ungrouped || dedicated => {}
+                Some(column) => db_type.write_count_distinct_sql(&mut sql, column),
+                None => db_type.write_count_sql(&mut sql),
+            }
+            if let Some(timeout) = self.session.timeout {
+                db_type.write_timeout_sql(&mut sql, timeout);
+            }
+            let hash = fingerprint_bytes(fingerprint(&sql), &T::TEMPLATE_FINGERPRINT.to_le_bytes());
+            let cache_key = match &count_cache {
+                Some((_, CountCacheKey::Arguments)) => arguments_fingerprint(hash, arg.as_ref()),
+                Some((_, CountCacheKey::Given(key))) => {
+                    Some(fingerprint_bytes(hash, &key.to_le_bytes()))
+                }
+                None => None,
+            };
+            if let (Some((cache, _)), Some(key)) = (&count_cache, cache_key)
+                && let Some(count) = cache.get(key)
+            {
+                return Ok(count);
+            }
+            let executor = match backend {
+                Either::Left(db_adapter) => {
+                    count_backend(db_adapter, &self.session)
+                        .await
+                        .map_err(|e| ctx.wrap(e))?
+                        .1
+                }
+                Either::Right(executor) => executor,
+            };
+            if self.query_tag {
+                write_query_tag(&mut sql, ctx.meta());
+            }
+            let meta = ctx.meta().clone();
+            let mut scope = MiddlewareScope::before(middlewares, meta, &mut sql);
+            scope.observe(observers, &sql, arg.as_ref().map_or(0, Arguments::len));
+            ctx.set_sql(&sql);
             let execute = SqlTemplateExecute::new(sql, arg).set_persistent(self.persistent);
-            let (count,): (i64,) = execute.fetch_one_as(executor).await?;
+            let (count,): (i64,) = execute
+                .fetch_one_as(executor)
+                .await
+                .inspect_err(|e| scope.fail(e))
+                .map_err(|e| ctx.wrap(e))?;
+            scope.row();
+            drop(scope);
+            if let (Some((cache, _)), Some(key)) = (&count_cache, cache_key) {
+                cache.insert(key, count);
+            }
             Ok(count)
         }
         .boxed()
     }
-    /// Calculates complete pagination metadata
-    ///
-    /// # Arguments
-    /// * `pagination_size` - Records per pagination
-    /// * `db_adapter` - Database connection adapter
-    #[inline]
-    pub async fn pagination_info<Adapter>(
-        self,
-        pagination_size: i64,
-        db_adapter: Adapter,
-    ) -> Result<PaginationInfo, Error>
-    where
-        Adapter: BackendDB<'c, DB> + 'c,
-        (i64,): for<'r> FromRow<'r, DB::Row>,
-    {
-        let count = self.count(db_adapter).await?;
-        Ok(PaginationInfo::new(count, pagination_size))
-    }
-    /// Sets pagination parameters
-    pub fn set_pagination(mut self, pagination_size: i64, pagination_no: i64) -> Self {
-        self.pagination_no = Some(pagination_no);
-        self.pagination_size = Some(pagination_size);
-        self
-    }
-
     /// like sqlx::Query::execute
     /// Execute the query and return the number of rows affected.
     #[inline]
@@ -150,6 +939,53 @@ where
     {
         self.execute_many(db_adapter).try_collect().await
     }
+    /// Executes the query inside a savepoint of the transaction on `conn`
+    ///
+    /// On failure the transaction is rolled back to the savepoint and stays
+    /// usable; on success the savepoint is released. See [`crate::with_savepoint`]
+    /// for running several statements in one savepoint.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use sqlx::SqlitePool;
+    /// # use sqlx_askama_template::SqlTemplate;
+    /// #
+    /// # #[derive(SqlTemplate)]
+    /// # #[template(source = "insert into orders (id) values (1)")]
+    /// # struct CreateOrder;
+    /// #
+    /// # #[derive(SqlTemplate)]
+    /// # #[template(source = "update loyalty set points = points + 10")]
+    /// # struct AddPoints;
+    /// # async fn run(pool: SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+    /// # let (create_order, add_points) = (CreateOrder, AddPoints);
+    /// let mut tx = pool.begin().await?;
+    /// create_order.adapter().execute(&mut *tx).await?;
+    /// // optional step: a failure does not abort the transaction
+    /// let _ = add_points
+    ///     .adapter()
+    ///     .execute_with_savepoint("loyalty", &mut *tx)
+    ///     .await;
+    /// tx.commit().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn execute_with_savepoint<C>(
+        self,
+        name: &str,
+        conn: &mut C,
+    ) -> Result<DB::QueryResult, Error>
+    where
+        C: Connection<Database = DB> + 'static,
+        for<'c1> &'c1 mut C: Executor<'c1, Database = DB>,
+    {
+        let savepoint = begin_savepoint(conn, name).await?;
+        let result = self.execute(&mut *conn).await;
+        let end = end_savepoint(conn, &savepoint, result.is_ok()).await;
+        let done = result?;
+        end?;
+        Ok(done)
+    }
     /// like    sqlx::Query::execute_many
     /// Execute multiple queries and return the rows affected from each query, in a stream.
     #[inline]
@@ -161,7 +997,7 @@ where
     where
         Adapter: BackendDB<'c, DB> + 'c,
     {
-        self.fetch_many(db_adapter)
+        self.fetch_many_for(QueryAccess::Write, db_adapter)
             .try_filter_map(|step| async move {
                 Ok(match step {
                     Either::Left(rows) => Some(rows),
@@ -198,29 +1034,271 @@ where
     where
         Adapter: BackendDB<'c, DB> + 'c,
     {
+        self.fetch_many_for(QueryAccess::Read, db_adapter)
+    }
+    #[allow(clippy::type_complexity)]
+    fn fetch_many_for<Adapter>(
+        mut self,
+        access: QueryAccess,
+        db_adapter: Adapter,
+    ) -> BoxStream<'e, Result<Either<DB::QueryResult, DB::Row>, Error>>
+    where
+        Adapter: BackendDB<'c, DB> + 'c,
+    {
+        let Some(cancelled) = self.cancellation.take() else {
+            return self.fetch_many_tagged(access, db_adapter, None);
+        };
+        let pool = db_adapter.cancel_pool(access);
+        let tag = Arc::new(CancelTag::new());
+        let stream = self.fetch_many_tagged(access, db_adapter, Some(tag.clone()));
+        cancellable(stream, cancelled.boxed(), pool, tag)
+    }
+    /// [`Self::fetch_many_for`], tagging the SQL for [`Self::with_cancellation`]
+    #[allow(clippy::type_complexity)]
+    fn fetch_many_tagged<Adapter>(
+        mut self,
+        access: QueryAccess,
+        db_adapter: Adapter,
+        tag: Option<Arc<CancelTag>>,
+    ) -> BoxStream<'e, Result<Either<DB::QueryResult, DB::Row>, Error>>
+    where
+        Adapter: BackendDB<'c, DB> + 'c,
+    {
+        if let Some(copies) = self.list_copies.take() {
+            return self.fetch_many_split(access, db_adapter, copies, tag);
+        }
         let template = self.template;
+        let middlewares = middleware_stack(self.middlewares, self.slow_query);
+        let observers = observers(self.observers);
         let pagination_no = self.pagination_no;
         let pagination_size = self.pagination_size;
         Box::pin(async_stream::try_stream! {
-            let (db_type, executor) = db_adapter.backend_db().await?;
+            let operation = match access {
+                QueryAccess::Read => "fetch",
+                QueryAccess::Write => "execute",
+            };
+            let mut meta = QueryMeta::for_template::<DB, T>(operation);
+            meta.pagination = pagination_size.zip(pagination_no);
+            let mut ctx = ErrorContext::new(meta);
+            let detected = match self
+                .capabilities
+                .and_then(|probe| Some((probe, db_adapter.cancel_pool(access)?)))
+            {
+                Some((probe, pool)) => Some(probe(pool).await.map_err(|e| ctx.wrap(e))?),
+                None => None,
+            };
+            let backend = if self.session.is_empty() {
+                db_adapter.backend_db_for(access).await
+            } else {
+                db_adapter.backend_db_with_session(access, &self.session).await
+            };
+            let (db_type, executor) = backend.map_err(|e| ctx.wrap(e))?;
+            ctx.set_backend(db_type.backend_name());
+            let context = match self.capabilities {
+                Some(_) => Some(context_with_capabilities(
+                    detected,
+                    db_type.backend_name(),
+                    self.context.as_ref(),
+                )),
+                None => self.context.clone(),
+            };
             let f = db_type.placeholder_fn();
             let mut sql = String::new();
-            let mut arg = template.render_with_placeholder(f, &mut sql)?;
-
+            log_template_params(&template);
+            let mut arg = with_identifier_quoting(&db_type, || {
+                with_context(context.as_ref(), || {
+                    template.render_with_placeholder(f, &mut sql)
+                })
+            })
+            .map_err(|e| ctx.wrap(e))?;
+            check_statement(self.statement_kind, &sql)
+                .and_then(|_| match &self.keyset {
+                    Some(_) => check_sort_columns(&self.sort, T::SORT_COLUMNS)
+                        .map_err(|e| Error::Render(SqlxError::from(e).into())),
+                    None => write_sort(&db_type, &mut sql, &self.sort, T::SORT_COLUMNS),
+                })
+                .map_err(|e| {
+                    ctx.set_sql(&sql);
+                    ctx.wrap(e)
+                })?;
+            if let Some(keyset) = &self.keyset {
+                let mut args = arg.unwrap_or_default();
+                let sort = write_keyset(&mut sql, &self.sort, keyset, f, &mut args).map_err(|e| {
+                    ctx.set_sql(&sql);
+                    ctx.wrap(match e.downcast::<UsageError>() {
+                        Ok(e) => Error::Render(SqlxError::from(*e).into()),
+                        Err(e) => Error::Encode(SqlxError::Encode(e).into()),
+                    })
+                })?;
+                db_type.write_sort_sql(&mut sql, &sort);
+                arg = Some(args);
+            }
             if let (Some(pagination_no), Some(pagination_size)) = (pagination_no, pagination_size) {
+                if let Some(schema) = &self.schema {
+                    db_type.write_schema(&mut sql, schema);
+                }
                 let mut args = arg.unwrap_or_default();
-                db_type.write_pagination_sql(&mut sql, pagination_size, pagination_no, &mut args)?;
+                if self.counted {
+                    let written = db_type
+                        .write_counted_pagination_sql(&mut sql, pagination_size, pagination_no, &mut args)
+                        .map_err(|e| ctx.dialect(e))?;
+                    if !written {
+                        Err(ctx.dialect(
+                            UsageError::Unsupported(format!(
+                                "{} can not count and paginate in one statement",
+                                db_type.backend_name()
+                            ))
+                            .into(),
+                        ))?;
+                    }
+                } else {
+                    db_type
+                        .write_pagination_sql(&mut sql, pagination_size, pagination_no, &mut args)
+                        .map_err(|e| ctx.dialect(e))?;
+                }
                 arg = Some(args);
+                if let Some(timeout) = self.session.timeout {
+                    db_type.write_timeout_sql(&mut sql, timeout);
+                }
+            } else {
+                finish_sql(&db_type, &mut sql, &self.schema, self.safety_limit, self.session.timeout);
             }
 
-            let execute = SqlTemplateExecute::new(sql, arg).set_persistent(self.persistent);
+            if self.query_tag {
+                write_query_tag(&mut sql, ctx.meta());
+            }
+            let meta = ctx.meta().clone();
+            let mut scope = MiddlewareScope::before(middlewares, meta, &mut sql);
+            scope.observe(observers, &sql, arg.as_ref().map_or(0, Arguments::len));
+            ctx.set_sql(&sql);
+            let tagged = tag.as_ref().is_some_and(|tag| tag.apply(&db_type, &mut sql));
+            let execute =
+                SqlTemplateExecute::new(sql, arg).set_persistent(self.persistent && !tagged);
             let mut stream = execute.fetch_many(executor);
-            while let Some(item) = stream.try_next().await? {
+            while let Some(item) = stream
+                .try_next()
+                .await
+                .inspect_err(|e| scope.fail(e))
+                .map_err(|e| ctx.wrap(e))?
+            {
+                if item.is_right() {
+                    scope.row();
+                }
                 yield item;
             }
         })
     }
+    /// [`Self::fetch_many_tagged`] in [`Self::split_oversized_lists`] mode
+    #[allow(clippy::type_complexity)]
+    fn fetch_many_split<Adapter>(
+        self,
+        access: QueryAccess,
+        db_adapter: Adapter,
+        copies: Arc<dyn Fn() -> T + Send + Sync + 'q>,
+        tag: Option<Arc<CancelTag>>,
+    ) -> BoxStream<'e, Result<Either<DB::QueryResult, DB::Row>, Error>>
+    where
+        Adapter: BackendDB<'c, DB> + 'c,
+    {
+        let middlewares = middleware_stack(self.middlewares.clone(), self.slow_query.clone());
+        let observers = observers(self.observers.clone());
+        Box::pin(async_stream::try_stream! {
+            let operation = match access {
+                QueryAccess::Read => "fetch",
+                QueryAccess::Write => "execute",
+            };
+            let mut ctx = ErrorContext::new(QueryMeta::for_template::<DB, T>(operation));
+            if self.pagination_no.is_some() && self.pagination_size.is_some() {
+                Err(ctx.dialect(
+                    UsageError::Unsupported(
+                        "oversized lists can not be split in paginated queries".into(),
+                    )
+                    .into(),
+                ))?;
+            }
+            let detected = match self
+                .capabilities
+                .and_then(|probe| Some((probe, db_adapter.cancel_pool(access)?)))
+            {
+                Some((probe, pool)) => Some(probe(pool).await.map_err(|e| ctx.wrap(e))?),
+                None => None,
+            };
+            let (db_type, mut pinned) = db_adapter
+                .backend_db_pinned(access, &self.session)
+                .await
+                .map_err(|e| ctx.wrap(e))?;
+            ctx.set_backend(db_type.backend_name());
+            let context = match self.capabilities {
+                Some(_) => Some(context_with_capabilities(
+                    detected,
+                    db_type.backend_name(),
+                    self.context.as_ref(),
+                )),
+                None => self.context.clone(),
+            };
+            let f = db_type.placeholder_fn();
+            log_template_params(&self.template);
+            let render = |window: Option<ListWindow>, sql: &mut String| {
+                let template = copies();
+                with_list_window(window, || {
+                    with_identifier_quoting(&db_type, || {
+                        with_context(context.as_ref(), || template.render_with_placeholder(f, sql))
+                    })
+                })
+            };
+            let mut sql = String::new();
+            let (arg, lengths) = render(None, &mut sql);
+            let arg = arg.map_err(|e| ctx.wrap(e))?;
+            check_statement(self.statement_kind, &sql)
+                .and_then(|_| write_sort(&db_type, &mut sql, &self.sort, T::SORT_COLUMNS))
+                .map_err(|e| {
+                    ctx.set_sql(&sql);
+                    ctx.wrap(e)
+                })?;
+            let bind_count = arg.as_ref().map_or(0, Arguments::len);
+            let plan = split_plan(bind_count, &lengths, db_type.max_bind_params())
+                .map_err(|e| ctx.dialect(UsageError::Unsupported(e).into()))?;
+            let mut first = Some((sql, arg));
+            for index in 0..plan.map_or(1, |(_, chunks)| chunks) {
+                let (mut sql, arg) = match (first.take(), plan) {
+                    (Some(rendered), None) => rendered,
+                    (_, plan) => {
+                        let window = plan.map(|(size, _)| ListWindow { size, index });
+                        let mut sql = String::new();
+                        let (arg, _) = render(window, &mut sql);
+                        let arg = arg.map_err(|e| ctx.wrap(e))?;
+                        db_type.write_sort_sql(&mut sql, &self.sort);
+                        (sql, arg)
+                    }
+                };
+                finish_sql(&db_type, &mut sql, &self.schema, self.safety_limit, self.session.timeout);
 
+                if self.query_tag {
+                    write_query_tag(&mut sql, ctx.meta());
+                }
+                let meta = ctx.meta().clone();
+                let mut scope = MiddlewareScope::before(middlewares.clone(), meta, &mut sql);
+                scope.observe(observers.clone(), &sql, arg.as_ref().map_or(0, Arguments::len));
+                ctx.set_sql(&sql);
+                let tagged = tag.as_ref().is_some_and(|tag| tag.apply(&db_type, &mut sql));
+                let execute =
+                    SqlTemplateExecute::new(sql, arg).set_persistent(self.persistent && !tagged);
+                let mut stream = execute.fetch_many(&mut *pinned.conn);
+                while let Some(item) = stream
+                    .try_next()
+                    .await
+                    .inspect_err(|e| scope.fail(e))
+                    .map_err(|e| ctx.wrap(e))?
+                {
+                    if item.is_right() {
+                        scope.row();
+                    }
+                    yield item;
+                }
+            }
+            pinned.finish().await.map_err(|e| ctx.wrap(e))?;
+        })
+    }
     /// like sqlx::Query::fetch_all
     /// Execute the query and return all the resulting rows collected into a [`Vec`].
     ///
@@ -237,7 +1315,7 @@ where
         self.fetch(db_adapter).try_collect().await
     }
     /// like sqlx::Query::fetch_one
-    /// Execute the query, returning the first row or [`Error::RowNotFound`] otherwise.
+    /// Execute the query, returning the first row or an [`Error::Execute`] wrapping `RowNotFound` otherwise.
     ///
     /// ### Note: for best performance, ensure the query returns at most one row.
     /// Depending on the driver implementation, if your query can return more than one row,
@@ -257,7 +1335,7 @@ where
         self.fetch_optional(db_adapter)
             .and_then(|row| match row {
                 Some(row) => future::ok(row),
-                None => future::err(Error::RowNotFound),
+                None => future::err(SqlxError::RowNotFound.into()),
             })
             .await
     }
@@ -317,31 +1395,13 @@ where
         Adapter: BackendDB<'c, DB> + 'c,
         O: Send + Unpin + for<'r> FromRow<'r, DB::Row> + 'e,
     {
-        let template = self.template;
-        let pagination_no = self.pagination_no;
-        let pagination_size = self.pagination_size;
-        Box::pin(async_stream::try_stream! {
-        let (db_type, executor) = db_adapter.backend_db().await?;
-        let f = db_type.placeholder_fn();
-        let mut sql = String::new();
-        let mut arg = template.render_with_placeholder(f, &mut sql)?;
-
-        if let (Some(pagination_no), Some(pagination_size)) = (pagination_no, pagination_size) {
-            let mut args = arg.unwrap_or_default();
-            db_type.write_pagination_sql(&mut sql, pagination_size, pagination_no, &mut args)?;
-            arg = Some(args);
-        }
-
-        let execute = SqlTemplateExecute::new(sql, arg).set_persistent(self.persistent);
-        let mut stream = execute.fetch_many(executor).map(|v| match v {
-            Ok(Either::Right(row)) => O::from_row(&row).map(Either::Right),
-            Ok(Either::Left(v)) => Ok(Either::Left(v)),
-            Err(e) => Err(e),
-        });
-        while let Some(item) = stream.try_next().await? {
-            yield item;
-        }
-          })
+        self.fetch_many(db_adapter)
+            .map(|v| match v {
+                Ok(Either::Right(row)) => O::from_row(&row).map(Either::Right).map_err(Into::into),
+                Ok(Either::Left(v)) => Ok(Either::Left(v)),
+                Err(e) => Err(e),
+            })
+            .boxed()
     }
     /// like sqlx::QueryAs::fetch_all
     /// Execute the query and return all the resulting rows collected into a [`Vec`].
@@ -359,8 +1419,266 @@ where
     {
         self.fetch_as(db_adapter).await.try_collect().await
     }
+    /// Execute the query and collect the resulting rows into any container,
+    /// e.g. a `HashSet`, a `BTreeMap` of `(key, value)` rows or a `SmallVec`
+    ///
+    /// Rows are added as they are decoded from the stream, without an
+    /// intermediate [`Vec`]. A paginated query adds them in batches of up to the
+    /// pagination size (at most 1024 rows), so that containers reserving from
+    /// the iterator size hint allocate once per batch.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use sqlx::{FromRow, SqlitePool};
+    /// # use sqlx_askama_template::SqlTemplate;
+    /// #
+    /// # #[derive(SqlTemplate, Clone)]
+    /// # #[template(source = "select id, name from users")]
+    /// # struct UserQuery;
+    /// #
+    /// # #[derive(FromRow)]
+    /// # struct User {
+    /// #     id: i64,
+    /// #     name: String,
+    /// # }
+    /// use std::collections::{BTreeMap, HashSet};
+    ///
+    /// # async fn run(pool: SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+    /// # let query = UserQuery;
+    /// let ids = (&query).adapter().fetch_collect::<(i64,), HashSet<_>>(&pool).await?;
+    /// let names = (&query).adapter().fetch_collect::<(i64, String), BTreeMap<_, _>>(&pool).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn fetch_collect<O, C>(
+        self,
+        db_adapter: impl BackendDB<'c, DB> + 'c,
+    ) -> Result<C, Error>
+    where
+        O: Send + Unpin + for<'r> FromRow<'r, DB::Row> + 'e,
+        C: Default + Extend<O>,
+    {
+        let batch = self
+            .pagination_size
+            .filter(|size| *size > 1)
+            .map(|size| size.min(1024) as usize);
+        let rows = self.fetch_as(db_adapter).await;
+        match batch {
+            Some(batch) => {
+                let batches = rows.try_chunks(batch).map_err(|e| e.1);
+                pin_mut!(batches);
+                let mut container = C::default();
+                while let Some(rows) = batches.try_next().await? {
+                    container.extend(rows);
+                }
+                Ok(container)
+            }
+            None => rows.try_collect().await,
+        }
+    }
+    /// Fetches the pagination set by [`DBAdapter::set_pagination`], resolving a
+    /// negative number from the end with a count query run first
+    ///
+    /// Both queries need the executor, hence the `Clone` bound (e.g. `&Pool`).
+    /// Without pagination, all rows are returned as pagination 1.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use sqlx::{FromRow, SqlitePool};
+    /// # use sqlx_askama_template::SqlTemplate;
+    /// #
+    /// # #[derive(SqlTemplate, Clone)]
+    /// # #[template(source = "select id, name from users")]
+    /// # struct UserQuery;
+    /// #
+    /// # #[derive(FromRow)]
+    /// # struct User {
+    /// #     id: i64,
+    /// #     name: String,
+    /// # }
+    /// use sqlx_askama_template::PaginatedRows;
+    ///
+    /// # async fn run(pool: SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+    /// # let query = UserQuery;
+    /// let last: PaginatedRows<User> = query
+    ///     .adapter()
+    ///     .set_pagination(20, -1)
+    ///     .fetch_pagination_as(&pool)
+    ///     .await?;
+    /// println!("pagination {} of {:?}", last.pagination_no, last.info);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn fetch_pagination_as<Adapter, O>(
+        mut self,
+        db_adapter: Adapter,
+    ) -> Result<PaginatedRows<O>, Error>
+    where
+        T: Clone,
+        Adapter: BackendDB<'c, DB> + Clone + 'c,
+        O: Send + Unpin + for<'r> FromRow<'r, DB::Row> + 'e,
+        (i64,): for<'r> FromRow<'r, DB::Row>,
+    {
+        let (Some(pagination_size), Some(pagination_no)) =
+            (self.pagination_size, self.pagination_no)
+        else {
+            let rows = self.fetch_all_as(db_adapter).await?;
+            return Ok(PaginatedRows {
+                rows,
+                pagination_no: 1,
+                info: None,
+            });
+        };
+        let (pagination_no, info) = if pagination_no < 0 {
+            let count = self.count_copy().count(db_adapter.clone()).await?;
+            let info = PaginationInfo::new(count, pagination_size);
+            let from_end = info.pagination_count.saturating_add(pagination_no + 1);
+            (from_end.max(1), Some(info))
+        } else {
+            (pagination_no.max(1), None)
+        };
+        self.pagination_no = Some(pagination_no);
+        let rows = self.fetch_all_as(db_adapter).await?;
+        Ok(PaginatedRows {
+            rows,
+            pagination_no,
+            info,
+        })
+    }
+    /// Fetches the pagination set by [`DBAdapter::set_pagination`] together with
+    /// its [`PaginationInfo`], in a single statement and round trip
+    ///
+    /// The count is joined to the pagination query in one statement (not a
+    /// pipelined batch of two), halving the latency of paginated endpoints
+    /// compared to [`Self::pagination_info`] followed by a fetch. The query
+    /// needs a top-level `ORDER BY` (or [`Self::with_sort`]) on output columns,
+    /// which orders the rows. Only PostgreSQL supports it, other backends fail with
+    /// [`Error::Dialect`]. The rows carry the
+    /// extra trailing columns [`crate::COUNTED_ROW_COLUMN`] and
+    /// [`crate::COUNTED_TOTAL_COLUMN`], ignored by `FromRow` types reading
+    /// columns by name or by leading position. A negative pagination number is
+    /// corrected to 1; without pagination, all rows are returned as pagination
+    /// 1 with no `info`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use sqlx::{FromRow, PgConnection};
+    /// # use sqlx_askama_template::SqlTemplate;
+    /// #
+    /// # #[derive(SqlTemplate)]
+    /// # #[template(source = "select id, name from users order by id")]
+    /// # struct UserQuery;
+    /// #
+    /// # #[derive(FromRow)]
+    /// # struct User {
+    /// #     id: i64,
+    /// #     name: String,
+    /// # }
+    /// use sqlx_askama_template::PaginatedRows;
+    ///
+    /// # async fn run(conn: &mut PgConnection) -> Result<(), Box<dyn std::error::Error>> {
+    /// # let query = UserQuery;
+    /// let page: PaginatedRows<User> = query
+    ///     .adapter()
+    ///     .set_pagination(20, 3)
+    ///     .fetch_counted_pagination_as(&mut *conn)
+    ///     .await?;
+    /// println!("{} rows of {:?}", page.rows.len(), page.info);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn fetch_counted_pagination_as<Adapter, O>(
+        mut self,
+        db_adapter: Adapter,
+    ) -> Result<PaginatedRows<O>, Error>
+    where
+        Adapter: BackendDB<'c, DB> + 'c,
+        O: Send + Unpin + for<'r> FromRow<'r, DB::Row> + 'e,
+        i64: for<'r> Decode<'r, DB>,
+        &'static str: ColumnIndex<DB::Row>,
+    {
+        let (Some(pagination_size), Some(pagination_no)) =
+            (self.pagination_size, self.pagination_no)
+        else {
+            let rows = self.fetch_all_as(db_adapter).await?;
+            return Ok(PaginatedRows {
+                rows,
+                pagination_no: 1,
+                info: None,
+            });
+        };
+        let pagination_no = pagination_no.max(1);
+        self.pagination_no = Some(pagination_no);
+        self.counted = true;
+        let stream = self.fetch(db_adapter);
+        pin_mut!(stream);
+        let mut total = 0;
+        let mut rows = Vec::new();
+        while let Some(row) = stream.try_next().await? {
+            total = row.try_get::<i64, _>(COUNTED_TOTAL_COLUMN)?;
+            if row.try_get::<Option<i64>, _>(COUNTED_ROW_COLUMN)?.is_some() {
+                rows.push(O::from_row(&row)?);
+            }
+        }
+        Ok(PaginatedRows {
+            rows,
+            pagination_no,
+            info: Some(PaginationInfo::new(total, pagination_size)),
+        })
+    }
+    /// Copy of the adapter for the count query of [`Self::fetch_pagination_as`]
+    fn count_copy(&self) -> DBAdapter<'q, DB, T, Unpaged>
+    where
+        T: Clone,
+    {
+        let mut copy = self.query_copy();
+        copy.sort.clear();
+        copy
+    }
+    /// Copy of the adapter configuration, without pagination or list
+    /// splitting
+    fn query_copy(&self) -> DBAdapter<'q, DB, T, Unpaged>
+    where
+        T: Clone,
+    {
+        let mut copy = self.clone().into_state();
+        copy.pagination_size = None;
+        copy.pagination_no = None;
+        copy.list_copies = None;
+        copy.counted = false;
+        copy.keyset = None;
+        copy
+    }
+    /// The adapter with its configuration in pagination state `P2`
+    fn into_state<P2>(self) -> DBAdapter<'q, DB, T, P2> {
+        DBAdapter {
+            template: self.template,
+            persistent: self.persistent,
+            _p: PhantomData,
+            pagination_size: self.pagination_size,
+            pagination_no: self.pagination_no,
+            count_cache: self.count_cache,
+            middlewares: self.middlewares,
+            schema: self.schema,
+            slow_query: self.slow_query,
+            observers: self.observers,
+            context: self.context,
+            session: self.session,
+            safety_limit: self.safety_limit,
+            statement_kind: self.statement_kind,
+            list_copies: self.list_copies,
+            cancellation: self.cancellation,
+            query_tag: self.query_tag,
+            sort: self.sort,
+            counted: self.counted,
+            keyset: self.keyset,
+            count_over: self.count_over,
+            capabilities: self.capabilities,
+            _state: PhantomData,
+        }
+    }
     /// like sqlx::QueryAs::fetch_one
-    /// Execute the query, returning the first row or [`Error::RowNotFound`] otherwise.
+    /// Execute the query, returning the first row or an [`Error::Execute`] wrapping `RowNotFound` otherwise.
     ///
     /// ### Note: for best performance, ensure the query returns at most one row.
     /// Depending on the driver implementation, if your query can return more than one row,
@@ -380,7 +1698,7 @@ where
         self.fetch_optional_as(db_adapter)
             .and_then(|o| match o {
                 Some(o) => future::ok(o),
-                None => future::err(Error::RowNotFound),
+                None => future::err(SqlxError::RowNotFound.into()),
             })
             .await
     }
@@ -413,4 +1731,498 @@ where
             None => Ok(None),
         }
     }
+
+    /// Streams the result rows as CSV into `writer`
+    ///
+    /// The header is taken from the column names of the first row, so an empty
+    /// result writes nothing.
+    ///
+    /// # Returns
+    /// Number of rows written
+    pub async fn export_csv<Adapter, W>(
+        self,
+        writer: &mut W,
+        options: &CsvOptions,
+        db_adapter: Adapter,
+    ) -> Result<u64, Error>
+    where
+        Adapter: BackendDB<'c, DB> + 'c,
+        W: AsyncWrite + Unpin + ?Sized,
+        DB::Row: RowValues,
+    {
+        let stream = self.fetch(db_adapter);
+        pin_mut!(stream);
+        let mut buf = String::new();
+        let mut rows = 0;
+        while let Some(row) = stream.try_next().await? {
+            buf.clear();
+            if rows == 0 && options.header {
+                let names = row.column_names();
+                options.write_record(&mut buf, names.iter().map(String::as_str));
+            }
+            options.write_row(&mut buf, &row)?;
+            writer.write_all(buf.as_bytes()).await?;
+            rows += 1;
+        }
+        writer.flush().await?;
+        Ok(rows)
+    }
+    /// Streams the result rows into `writer` as JSON Lines
+    ///
+    /// Each row is written as one JSON object keyed by column name.
+    ///
+    /// # Returns
+    /// Number of rows written
+    pub async fn export_jsonl<Adapter, W>(
+        self,
+        writer: &mut W,
+        db_adapter: Adapter,
+    ) -> Result<u64, Error>
+    where
+        Adapter: BackendDB<'c, DB> + 'c,
+        W: AsyncWrite + Unpin + ?Sized,
+        DB::Row: RowValues,
+    {
+        let stream = self.fetch(db_adapter);
+        pin_mut!(stream);
+        let mut buf = String::new();
+        let mut rows = 0;
+        while let Some(row) = stream.try_next().await? {
+            buf.clear();
+            write_json_row(&mut buf, &row)?;
+            writer.write_all(buf.as_bytes()).await?;
+            rows += 1;
+        }
+        writer.flush().await?;
+        Ok(rows)
+    }
+
+    /// Streams the result rows as Arrow record batches of at most `batch_size` rows
+    ///
+    /// The schema is inferred from the first batch, see [`crate::rows_to_record_batch`].
+    #[cfg(feature = "arrow")]
+    pub fn fetch_arrow<Adapter>(
+        self,
+        batch_size: usize,
+        db_adapter: Adapter,
+    ) -> BoxStream<'e, Result<arrow_array::RecordBatch, Error>>
+    where
+        Adapter: BackendDB<'c, DB> + 'c,
+        DB::Row: RowValues,
+    {
+        let batch_size = batch_size.max(1);
+        let stream = self
+            .fetch_many(db_adapter)
+            .try_filter_map(|step| async move { Ok(step.right()) });
+        Box::pin(async_stream::try_stream! {
+            pin_mut!(stream);
+            let mut schema = None;
+            let mut rows = Vec::with_capacity(batch_size);
+            while let Some(row) = stream.try_next().await? {
+                rows.push(row);
+                if rows.len() >= batch_size {
+                    yield crate::arrow_export::rows_to_record_batch(&rows, &mut schema)?;
+                    rows.clear();
+                }
+            }
+            if !rows.is_empty() {
+                yield crate::arrow_export::rows_to_record_batch(&rows, &mut schema)?;
+            }
+        })
+    }
+}
+/// Methods that count or set the pagination themselves, only available before
+/// [`DBAdapter::set_pagination`]
+impl<'q, 'c, 'e, DB, T> DBAdapter<'q, DB, T, Unpaged>
+where
+    DB: Database + Sync,
+    T: SqlTemplate<'q, DB> + Send + 'q,
+    i64: Encode<'q, DB> + Type<DB>,
+    DB::Arguments: 'q,
+    for<'c1> &'c1 mut DB::Connection: Executor<'c1, Database = DB>,
+    'q: 'e,
+    'c: 'e,
+{
+    /// Executes count query for pagination
+    ///
+    /// Templates with a `{% block count %}` run that block as the count query,
+    /// see [`SqlTemplate::HAS_COUNT_QUERY`].
+    ///
+    /// # Arguments
+    /// * `db_adapter` - Database connection adapter
+    #[inline]
+    pub fn count<Adapter>(self, db_adapter: Adapter) -> BoxFuture<'e, Result<i64, Error>>
+    where
+        Adapter: BackendDB<'c, DB> + 'c,
+        (i64,): for<'r> FromRow<'r, DB::Row>,
+    {
+        self.count_column(None, db_adapter)
+    }
+    /// Counts the distinct non-null values of `column` in the rows of the
+    /// query, e.g. the users matching a filter on their orders
+    ///
+    /// `column` must be a plain column name (`user_id` or `o.user_id`);
+    /// anything else fails with [`Error::Render`].
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use sqlx::SqlitePool;
+    /// # use sqlx_askama_template::SqlTemplate;
+    /// #
+    /// # #[derive(SqlTemplate)]
+    /// # #[template(source = "select * from orders where total > 100")]
+    /// # struct OrderQuery;
+    /// # async fn run(pool: SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+    /// # let orders = OrderQuery;
+    /// // select count(distinct user_id) from (select * from orders where ...) t
+    /// let buyers = orders.adapter().count_distinct("user_id", &pool).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn count_distinct<Adapter>(
+        self,
+        column: &str,
+        db_adapter: Adapter,
+    ) -> BoxFuture<'e, Result<i64, Error>>
+    where
+        Adapter: BackendDB<'c, DB> + 'c,
+        (i64,): for<'r> FromRow<'r, DB::Row>,
+    {
+        self.count_column(Some(column.to_string()), db_adapter)
+    }
+    /// Calculates complete pagination metadata
+    ///
+    /// # Arguments
+    /// * `pagination_size` - Records per pagination
+    /// * `db_adapter` - Database connection adapter
+    #[inline]
+    pub async fn pagination_info<Adapter>(
+        self,
+        pagination_size: i64,
+        db_adapter: Adapter,
+    ) -> Result<PaginationInfo, Error>
+    where
+        Adapter: BackendDB<'c, DB> + 'c,
+        (i64,): for<'r> FromRow<'r, DB::Row>,
+    {
+        let count = self.count(db_adapter).await?;
+        Ok(PaginationInfo::new(count, pagination_size))
+    }
+    /// Sets pagination parameters
+    ///
+    /// A `pagination_size` of 0 or less disables pagination, returning all rows;
+    /// a `pagination_no` below 1 is corrected to 1, except by
+    /// [`Self::fetch_pagination_as`] where `-1` is the last pagination, `-2`
+    /// the one before...
+    ///
+    /// Returns the adapter in the [`Paged`] state, so the pagination can not be
+    /// set twice and the count must come from an adapter of its own.
+    pub fn set_pagination(
+        mut self,
+        pagination_size: i64,
+        pagination_no: i64,
+    ) -> DBAdapter<'q, DB, T, Paged> {
+        if pagination_size >= 1 {
+            self.pagination_no = Some(pagination_no);
+            self.pagination_size = Some(pagination_size);
+        }
+        self.into_state()
+    }
+    /// Sets pagination from an unsigned [`PageRequest`], rejecting a zero or
+    /// out-of-range size or number instead of correcting it
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use sqlx::{FromRow, SqlitePool};
+    /// # use sqlx_askama_template::SqlTemplate;
+    /// #
+    /// # #[derive(SqlTemplate, Clone)]
+    /// # #[template(source = "select id, name from users")]
+    /// # struct UserQuery;
+    /// #
+    /// # #[derive(FromRow)]
+    /// # struct User {
+    /// #     id: i64,
+    /// #     name: String,
+    /// # }
+    /// use sqlx_askama_template::PageRequest;
+    ///
+    /// # struct Params {
+    /// #     size: u64,
+    /// #     page: u64,
+    /// # }
+    /// # async fn run(pool: SqlitePool, params: Params) -> Result<(), Box<dyn std::error::Error>> {
+    /// # let query = UserQuery;
+    /// let rows = query
+    ///     .adapter()
+    ///     .set_page(PageRequest::new(params.size, params.page))?
+    ///     .fetch_all_as::<_, User>(&pool)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_page(
+        self,
+        request: PageRequest,
+    ) -> Result<DBAdapter<'q, DB, T, Paged>, PageRequestError> {
+        let (pagination_size, pagination_no) = request.validate()?;
+        Ok(self.set_pagination(pagination_size, pagination_no))
+    }
+
+    /// Fetches pagination `pagination_no` with the default size of the
+    /// template, see [`SqlTemplate::DEFAULT_PAGE_SIZE`] and
+    /// [`crate::set_default_page_size`]
+    ///
+    /// Behaves like [`Self::fetch_pagination_as`], including negative numbers
+    /// counted from the end; use `set_pagination` with it to choose the size.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use sqlx::{FromRow, SqlitePool};
+    /// # use sqlx_askama_template::SqlTemplate;
+    /// #
+    /// # #[derive(SqlTemplate, Clone)]
+    /// # #[template(source = "select id, name from users")]
+    /// # struct UserQuery;
+    /// #
+    /// # #[derive(FromRow)]
+    /// # struct User {
+    /// #     id: i64,
+    /// #     name: String,
+    /// # }
+    /// use sqlx_askama_template::PaginatedRows;
+    ///
+    /// # async fn run(pool: SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+    /// # let query = UserQuery;
+    /// let page: PaginatedRows<User> = query.adapter().fetch_page_as(2, &pool).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn fetch_page_as<Adapter, O>(
+        self,
+        pagination_no: i64,
+        db_adapter: Adapter,
+    ) -> Result<PaginatedRows<O>, Error>
+    where
+        T: Clone,
+        Adapter: BackendDB<'c, DB> + Clone + 'c,
+        O: Send + Unpin + for<'r> FromRow<'r, DB::Row> + 'e,
+        (i64,): for<'r> FromRow<'r, DB::Row>,
+    {
+        let pagination_size = T::DEFAULT_PAGE_SIZE.unwrap_or_else(default_page_size);
+        self.set_pagination(pagination_size, pagination_no)
+            .fetch_pagination_as(db_adapter)
+            .await
+    }
+    /// Streams every row of the query, fetching successive paginations of
+    /// `pagination_size` rows under the hood
+    ///
+    /// Only one pagination is held in memory at a time; the stream ends after
+    /// the first pagination shorter than `pagination_size`. With
+    /// [`Self::with_sort`], each pagination follows the last row of the
+    /// previous one as in [`Self::fetch_after_as`] (same requirements on the
+    /// sort keys), so rows written meanwhile are neither skipped nor repeated
+    /// and late paginations cost no more than the first. Otherwise paginations
+    /// are `LIMIT`/`OFFSET` queries, which need a stable `ORDER BY` in the
+    /// template. Each query needs the executor, hence the `Clone` bound (e.g.
+    /// `&Pool`); [`Self::with_cancellation`] applies to every one. A
+    /// `pagination_size` of 0 or less fetches all rows with one query.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use sqlx::{FromRow, SqlitePool};
+    /// # use sqlx_askama_template::SqlTemplate;
+    /// #
+    /// # #[derive(SqlTemplate, Clone)]
+    /// # #[template(source = "select id, name from users")]
+    /// # struct UserQuery;
+    /// #
+    /// # #[derive(FromRow)]
+    /// # struct User {
+    /// #     id: i64,
+    /// #     name: String,
+    /// # }
+    /// use futures_util::TryStreamExt;
+    ///
+    /// # async fn export(user: User) -> Result<(), Box<dyn std::error::Error>> {
+    /// #     Ok(())
+    /// # }
+    /// # async fn run(pool: SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+    /// # let query = UserQuery;
+    /// let mut users = query.adapter().stream_all_as::<_, User>(500, &pool);
+    /// while let Some(user) = users.try_next().await? {
+    ///     export(user).await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stream_all_as<Adapter, O>(
+        self,
+        pagination_size: i64,
+        db_adapter: Adapter,
+    ) -> BoxStream<'e, Result<O, Error>>
+    where
+        T: Clone,
+        Adapter: BackendDB<'c, DB> + Clone + 'c,
+        O: Send + Unpin + for<'r> FromRow<'r, DB::Row> + 'e,
+        DB::Row: RowValues,
+        bool: Encode<'q, DB> + Type<DB>,
+        f64: Encode<'q, DB> + Type<DB>,
+        String: Encode<'q, DB> + Type<DB>,
+    {
+        if pagination_size < 1 {
+            return self
+                .fetch_many_as(db_adapter)
+                .try_filter_map(|step| async move { Ok(step.right()) })
+                .boxed();
+        }
+        if !self.sort.is_empty() {
+            return Box::pin(async_stream::try_stream! {
+                let mut cursor = None;
+                loop {
+                    let (rows, next): (Vec<O>, _) = self
+                        .query_copy()
+                        .fetch_after_as(cursor.as_ref(), pagination_size, db_adapter.clone())
+                        .await?;
+                    for row in rows {
+                        yield row;
+                    }
+                    match next {
+                        Some(next) => cursor = Some(next),
+                        None => break,
+                    }
+                }
+            });
+        }
+        Box::pin(async_stream::try_stream! {
+            let mut pagination_no = 1;
+            loop {
+                let rows: Vec<O> = self
+                    .query_copy()
+                    .set_pagination(pagination_size, pagination_no)
+                    .fetch_all_as(db_adapter.clone())
+                    .await?;
+                let last = (rows.len() as i64) < pagination_size;
+                for row in rows {
+                    yield row;
+                }
+                if last {
+                    break;
+                }
+                pagination_no += 1;
+            }
+        })
+    }
+    /// Fetches up to `limit` rows following `cursor` (the first rows if
+    /// `None`) in the order of [`Self::with_sort`], for infinite scrolling
+    ///
+    /// Keyset pagination: instead of an offset, the query keeps the rows
+    /// sorting after the sort key values of the last row already returned, so
+    /// every call costs the same and no count is run. Returns the rows and the
+    /// [`Cursor`] of the next call, `None` after the last rows. The sort keys
+    /// must be non-null integer, float, boolean or text columns of the result
+    /// rows, and together unique (end with the primary key); other keys fail
+    /// with [`Error::Render`]. A `limit` below 1 is corrected to 1.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use sqlx::{FromRow, SqlitePool};
+    /// # use sqlx_askama_template::SqlTemplate;
+    /// #
+    /// # #[derive(SqlTemplate)]
+    /// # #[template(source = "select id, created_at, title from posts")]
+    /// # #[sql_template(order_by = "created_at, id")]
+    /// # struct PostQuery;
+    /// #
+    /// # #[derive(FromRow)]
+    /// # struct Post {
+    /// #     id: i64,
+    /// #     title: String,
+    /// # }
+    /// #
+    /// # struct Params {
+    /// #     after: Option<String>,
+    /// # }
+    /// use sqlx_askama_template::{Cursor, SortSpec};
+    ///
+    /// # async fn run(pool: SqlitePool, params: Params) -> Result<(), Box<dyn std::error::Error>> {
+    /// # let query = PostQuery;
+    /// let cursor = params.after.as_deref().and_then(Cursor::from_token);
+    /// let (rows, next) = query
+    ///     .adapter()
+    ///     .with_sort([SortSpec::desc("created_at"), SortSpec::desc("id")])
+    ///     .fetch_after_as::<_, Post>(cursor.as_ref(), 20, &pool)
+    ///     .await?;
+    /// let next_token = next.map(|cursor| cursor.token());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn fetch_after_as<Adapter, O>(
+        mut self,
+        cursor: Option<&Cursor>,
+        limit: i64,
+        db_adapter: Adapter,
+    ) -> Result<(Vec<O>, Option<Cursor>), Error>
+    where
+        Adapter: BackendDB<'c, DB> + 'c,
+        O: Send + Unpin + for<'r> FromRow<'r, DB::Row> + 'e,
+        DB::Row: RowValues,
+        bool: Encode<'q, DB> + Type<DB>,
+        f64: Encode<'q, DB> + Type<DB>,
+        String: Encode<'q, DB> + Type<DB>,
+    {
+        if self.sort.is_empty() {
+            return Err(Error::Render(
+                SqlxError::from(UsageError::MissingSortKeys).into(),
+            ));
+        }
+        let limit = limit.max(1);
+        let columns: Vec<String> = self
+            .sort
+            .iter()
+            .map(|spec| result_column(&spec.column).to_string())
+            .collect();
+        self.keyset = Some(Keyset {
+            values: cursor
+                .map(|cursor| cursor.values.clone())
+                .unwrap_or_default(),
+            bind: bind_column_value::<DB>,
+        });
+        // one more row tells whether there is a next call
+        self.pagination_size = Some(limit.saturating_add(1));
+        self.pagination_no = Some(1);
+        let mut rows: Vec<DB::Row> = self.fetch_all(db_adapter).await?;
+        let more = rows.len() as i64 > limit;
+        rows.truncate(limit as usize);
+        let next = match rows.last().filter(|_| more) {
+            Some(last) => {
+                let mut values = Vec::with_capacity(columns.len());
+                for column in &columns {
+                    let index = last
+                        .columns()
+                        .iter()
+                        .position(|c| c.name() == column)
+                        .ok_or_else(|| SqlxError::ColumnNotFound(column.clone()))?;
+                    let value = last.column_value(index)?;
+                    if value == ColumnValue::Null {
+                        Err(Error::Render(
+                            SqlxError::from(UsageError::Keyset(format!(
+                                "keyset column `{column}` is NULL"
+                            )))
+                            .into(),
+                        ))?;
+                    }
+                    values.push(value);
+                }
+                Some(Cursor { values })
+            }
+            None => None,
+        };
+        let rows = rows
+            .iter()
+            .map(O::from_row)
+            .collect::<Result<Vec<O>, SqlxError>>()?;
+        Ok((rows, next))
+    }
 }
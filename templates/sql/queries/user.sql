@@ -0,0 +1 @@
+select * from users where id = {{ e(id) }}
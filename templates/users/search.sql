@@ -0,0 +1,5 @@
+select * from users
+where tenant_id = {{ e(tenant_id) }}
+  and ({{ e(name) }} is null or name = {{ e(name) }})
+  and ({{ e(min_age) }} is null or age >= {{ e(min_age) }})
+  and ({{ e(active) }} is null or active = {{ e(active) }})
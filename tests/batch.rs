@@ -0,0 +1,93 @@
+use sqlx::{Row, Sqlite, SqlitePool};
+use sqlx_askama_template::{SqlTemplate, execute_batch};
+
+#[derive(SqlTemplate, Clone)]
+#[template(
+    source = "insert into users (id, name) values ({{ e(id) }}, {{ e_named(\"name\", name) }})"
+)]
+struct InsertUser {
+    id: i64,
+    #[param(secret)]
+    name: String,
+}
+
+#[derive(SqlTemplate)]
+#[template(source = "insert into {{ table }} (id) values ({{ e(id) }})")]
+struct InsertInto {
+    #[raw]
+    table: String,
+    id: i64,
+}
+
+#[derive(SqlTemplate)]
+#[template(source = "update users set name = 'x' {% if all %}where id > {{ e(id) }}{% endif %}")]
+struct UpdateUsers {
+    all: bool,
+    id: i64,
+}
+
+const _: () = {
+    assert!(<&InsertUser as SqlTemplate<Sqlite>>::STATIC_SQL);
+    assert!(<InsertUser as SqlTemplate<Sqlite>>::STATIC_SQL);
+    assert!(!<&InsertInto as SqlTemplate<Sqlite>>::STATIC_SQL);
+    assert!(!<&UpdateUsers as SqlTemplate<Sqlite>>::STATIC_SQL);
+    assert!(<&SetToken as SqlTemplate<Sqlite>>::STATIC_SQL);
+};
+
+#[test]
+fn render_arguments_matches_render() {
+    let user = InsertUser {
+        id: 7,
+        name: "ann".to_string(),
+    };
+    let (_, rendered) = SqlTemplate::<Sqlite>::render(&user).unwrap();
+    let bound = SqlTemplate::<Sqlite>::render_arguments(&user).unwrap();
+    assert_eq!(format!("{rendered:?}"), format!("{bound:?}"));
+}
+
+#[tokio::test]
+async fn static_batch_binds_every_item() {
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    let mut conn = pool.acquire().await.unwrap();
+    sqlx::query("create table users (id integer primary key, name text)")
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+    let users: Vec<InsertUser> = (1..=3)
+        .map(|id| InsertUser {
+            id,
+            name: format!("user{id}"),
+        })
+        .collect();
+    let results = execute_batch(&users, &mut *conn).await.unwrap();
+    assert!(results.iter().all(Result::is_ok), "{results:?}");
+
+    let rows = sqlx::query("select id, name from users order by id")
+        .fetch_all(&mut *conn)
+        .await
+        .unwrap();
+    let rows: Vec<(i64, String)> = rows.iter().map(|row| (row.get(0), row.get(1))).collect();
+    assert_eq!(
+        rows,
+        [
+            (1, "user1".to_string()),
+            (2, "user2".to_string()),
+            (3, "user3".to_string())
+        ]
+    );
+}
+
+#[derive(SqlTemplate)]
+#[template(source = "update users set token = {{ sql_e(token) }} where id = {{ e(id) }}")]
+#[sql_template(helper_prefix = "sql_")]
+struct SetToken {
+    #[param(secret)]
+    token: String,
+    #[sql_cast("::int8")]
+    id: i64,
+}
+
+#[test]
+fn param_names_of_rewritten_binds() {
+    assert_eq!(SetToken::PARAM_NAMES, ["token", "id"]);
+}
@@ -0,0 +1,73 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use sqlx::{
+    AnyPool,
+    any::{AnyPoolOptions, install_default_drivers},
+};
+use sqlx_askama_template::{CountCache, CountCacheKey, SqlTemplate, TemplateContext};
+
+#[derive(SqlTemplate)]
+#[template(source = r#"select 1 as n where {{ e(ctx("app")) }} = {{ e(app) }}"#)]
+#[add_type(String)]
+struct AppQuery {
+    app: String,
+}
+
+#[derive(Default)]
+struct MapCache(Mutex<HashMap<u64, i64>>);
+
+impl CountCache for MapCache {
+    fn get(&self, key: u64) -> Option<i64> {
+        self.0.lock().unwrap().get(&key).copied()
+    }
+    fn insert(&self, key: u64, count: i64) {
+        self.0.lock().unwrap().insert(key, count);
+    }
+}
+
+async fn single_connection_pool() -> AnyPool {
+    install_default_drivers();
+    AnyPoolOptions::new()
+        .max_connections(1)
+        .acquire_timeout(Duration::from_millis(200))
+        .connect("sqlite::memory:")
+        .await
+        .unwrap()
+}
+
+async fn count(pool: &AnyPool, cache: &Arc<MapCache>, ctx_app: &str) -> i64 {
+    AppQuery {
+        app: "billing".to_string(),
+    }
+    .adapter()
+    .with_context(TemplateContext::new().with("app", ctx_app))
+    .set_count_cache(cache.clone(), CountCacheKey::Arguments)
+    .count(pool)
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn arguments_key_tells_context_values_apart() {
+    let pool = single_connection_pool().await;
+    let cache = Arc::new(MapCache::default());
+
+    assert_eq!(count(&pool, &cache, "billing").await, 1);
+    assert_eq!(count(&pool, &cache, "shop").await, 0);
+    assert_eq!(cache.0.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn cached_count_acquires_no_connection() {
+    let pool = single_connection_pool().await;
+    let cache = Arc::new(MapCache::default());
+    assert_eq!(count(&pool, &cache, "billing").await, 1);
+
+    // the only connection is taken: a query would time out acquiring one
+    let _conn = pool.acquire().await.unwrap();
+    assert_eq!(count(&pool, &cache, "billing").await, 1);
+}
@@ -0,0 +1,100 @@
+use sqlx::{
+    Any, Postgres, Row, Sqlite, SqlitePool,
+    any::{AnyPoolOptions, install_default_drivers},
+};
+use sqlx_askama_template::{DBType, SqlTemplate, UsageError, register_array_type};
+
+#[derive(SqlTemplate)]
+#[template(source = "select * from users where id {{ e(ids) }} and age > {{ e(age) }}")]
+struct UsersQuery {
+    ids: Vec<i64>,
+    age: i64,
+}
+
+#[derive(SqlTemplate)]
+#[template(source = "select * from users where score {{ e(scores) }}")]
+struct ScoresQuery<'a> {
+    scores: &'a [i32],
+}
+
+#[derive(SqlTemplate)]
+#[template(source = "select * from users where id = ANY({{ e(ids) }})")]
+struct ArrayQuery {
+    #[bind_as(Vec<i64>)]
+    ids: Vec<i64>,
+}
+
+#[derive(SqlTemplate)]
+#[template(
+    source = "select count(*) as n from (select 1 as id union all select 2 union all select 3) \
+              where id {{ e(ids) }}"
+)]
+struct CountQuery {
+    ids: Vec<i64>,
+}
+
+#[test]
+fn list_by_dialect() {
+    let query = UsersQuery {
+        ids: vec![1, 2],
+        age: 30,
+    };
+    let (sql, _) = SqlTemplate::<Sqlite>::render(&query).unwrap();
+    assert_eq!(sql, "select * from users where id IN (?,?) and age > ?");
+
+    register_array_type::<Postgres, i64>();
+    let (sql, _) = SqlTemplate::<Postgres>::render(&query).unwrap();
+    assert_eq!(sql, "select * from users where id = ANY($1) and age > $2");
+
+    let (sql, _) = SqlTemplate::<Any>::render_for_dialect(&query, &DBType::PostgreSQL).unwrap();
+    assert_eq!(
+        sql,
+        "select * from users where id = ANY($1::int8[]) and age > $2"
+    );
+}
+
+#[test]
+fn bind_as_keeps_one_value() {
+    let (sql, _) = SqlTemplate::<Postgres>::render(&ArrayQuery { ids: vec![1, 2] }).unwrap();
+    assert_eq!(sql, "select * from users where id = ANY($1)");
+}
+
+#[test]
+fn empty_list_matches_no_row() {
+    let query = UsersQuery {
+        ids: Vec::new(),
+        age: 30,
+    };
+    let (sql, _) = SqlTemplate::<Sqlite>::render(&query).unwrap();
+    assert_eq!(sql, "select * from users where id IN (NULL) and age > ?");
+}
+
+#[test]
+fn unregistered_array_fails() {
+    let err = SqlTemplate::<Postgres>::render(&ScoresQuery { scores: &[1, 2] }).unwrap_err();
+    assert!(
+        matches!(err.usage(), Some(UsageError::Unsupported(_))),
+        "{err:?}"
+    );
+}
+
+#[tokio::test]
+async fn list_filters_rows() {
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    for (ids, count) in [(vec![1, 3, 5], 2), (Vec::new(), 0)] {
+        let row = CountQuery { ids }.adapter().fetch_one(&pool).await.unwrap();
+        assert_eq!(row.get::<i64, _>("n"), count);
+    }
+
+    install_default_drivers();
+    let pool = AnyPoolOptions::new()
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    let row = CountQuery { ids: vec![2] }
+        .adapter()
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(row.get::<i64, _>("n"), 1);
+}
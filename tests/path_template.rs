@@ -0,0 +1,15 @@
+use sqlx::Sqlite;
+use sqlx_askama_template::SqlTemplate;
+
+#[derive(SqlTemplate)]
+#[template(path = "id_query.sql", config = "tests/askama.toml")]
+struct IdQuery {
+    id: i64,
+}
+
+#[test]
+fn path_template_follows_askama_dirs() {
+    assert_eq!(IdQuery::SQL_TEMPLATE_SOURCE, "select {{e(id)}} as id\n");
+    let (sql, _) = SqlTemplate::<Sqlite>::render(&IdQuery { id: 1 }).unwrap();
+    assert_eq!(sql.trim(), "select ? as id");
+}
@@ -0,0 +1,166 @@
+//! Checks that the public futures and streams are `Send`, so they can be handed
+//! to `tokio::spawn` and other multi-threaded executors
+//!
+//! The checks are generic, so a bound missing on some database fails the build,
+//! and instantiated for every driver by `public_futures_are_send`. Nothing is
+//! awaited: the test only has to compile.
+
+use sqlx::{
+    Any, AnyPool, Database, Encode, Executor, FromRow, MySql, Pool, Postgres, Sqlite, Type,
+};
+use sqlx_askama_template::{
+    CsvOptions, Cursor, ReadWriteAdapter, RowValues, ScriptRunner, SortSpec, SqlTemplate,
+    detect_backend_db, detect_capabilities, execute_batch, with_savepoint,
+};
+
+#[derive(SqlTemplate, Clone)]
+#[template(source = "select id from users where id > {{ e(id) }} order by id")]
+struct IdQuery {
+    id: i64,
+}
+
+fn assert_send<T: Send>(_: &T) {}
+
+fn adapter_futures<'q, DB, T, O>(template: T, pool: &'q Pool<DB>)
+where
+    DB: Database + Sync,
+    DB::Row: RowValues,
+    T: SqlTemplate<'q, DB> + Clone + Send + Sync + 'q,
+    O: Send + Unpin + for<'r> FromRow<'r, DB::Row> + 'q,
+    i64: Encode<'q, DB> + Type<DB>,
+    bool: Encode<'q, DB> + Type<DB>,
+    f64: Encode<'q, DB> + Type<DB>,
+    String: Encode<'q, DB> + Type<DB>,
+    (i64,): for<'r> FromRow<'r, DB::Row>,
+    (String,): for<'r> FromRow<'r, DB::Row>,
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+{
+    let adapter = || template.clone().adapter();
+    let sorted = || adapter().with_sort([SortSpec::asc("id")]);
+    let mut out = Vec::new();
+    assert_send(&adapter());
+    assert_send(&adapter().count(pool));
+    assert_send(&adapter().count_distinct("id", pool));
+    assert_send(&adapter().pagination_info(20, pool));
+    assert_send(&adapter().execute(pool));
+    assert_send(&adapter().execute_many(pool));
+    assert_send(&adapter().fetch(pool));
+    assert_send(&adapter().fetch_many(pool));
+    assert_send(&adapter().fetch_all(pool));
+    assert_send(&adapter().fetch_one(pool));
+    assert_send(&adapter().fetch_optional(pool));
+    assert_send(&adapter().fetch_as::<_, O>(pool));
+    assert_send(&adapter().fetch_many_as::<_, O>(pool));
+    assert_send(&adapter().fetch_all_as::<_, O>(pool));
+    assert_send(&adapter().fetch_one_as::<_, O>(pool));
+    assert_send(&adapter().fetch_optional_as::<_, O>(pool));
+    assert_send(&adapter().fetch_collect::<O, Vec<O>>(pool));
+    assert_send(&adapter().fetch_page_as::<_, O>(1, pool));
+    assert_send(&adapter().stream_all_as::<_, O>(500, pool));
+    assert_send(&sorted().stream_all_as::<_, O>(500, pool));
+    assert_send(&sorted().fetch_after_as::<_, O>(None, 20, pool));
+    if let Some(cursor) = &Cursor::from_token("") {
+        assert_send(&sorted().fetch_after_as::<_, O>(Some(cursor), 20, pool));
+    }
+    assert_send(&adapter().export_csv(&mut out, &CsvOptions::default(), pool));
+    assert_send(&adapter().export_jsonl(&mut out, pool));
+    #[cfg(feature = "arrow")]
+    assert_send(&adapter().fetch_arrow(1024, pool));
+    #[cfg(feature = "parquet")]
+    assert_send(&sqlx_askama_template::write_parquet(
+        Vec::new(),
+        adapter().fetch_arrow(1024, pool),
+    ));
+    assert_send(&adapter().set_pagination(20, 1));
+    assert_send(
+        &adapter()
+            .set_pagination(20, 1)
+            .fetch_pagination_as::<_, O>(pool),
+    );
+    assert_send(&adapter().with_capabilities().fetch_all(pool));
+    assert_send(&adapter().with_session_setup(["select 1"]).fetch_all(pool));
+    assert_send(&adapter().with_cancellation(async {}).fetch_all(pool));
+    assert_send(&adapter().split_oversized_lists().fetch_all(pool));
+    assert_send(&detect_capabilities(pool));
+    if let Ok(query) = template.clone().render_owned() {
+        assert_send(&query);
+        assert_send(&query.prepare(pool));
+    }
+}
+
+fn counted_pagination<'q, DB, T, O>(template: T, pool: &'q Pool<DB>)
+where
+    DB: Database + Sync,
+    T: SqlTemplate<'q, DB> + Send + 'q,
+    O: Send + Unpin + for<'r> FromRow<'r, DB::Row> + 'q,
+    i64: Encode<'q, DB> + Type<DB> + for<'r> sqlx::Decode<'r, DB>,
+    &'static str: sqlx::ColumnIndex<DB::Row>,
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+{
+    assert_send(
+        &template
+            .adapter()
+            .set_pagination(20, 1)
+            .fetch_counted_pagination_as::<_, O>(pool),
+    );
+}
+
+fn adapter_executors<'q, DB, T>(
+    template: T,
+    conn: &'q mut DB::Connection,
+    read_write: &'q ReadWriteAdapter<DB>,
+) where
+    DB: Database + Sync,
+    T: SqlTemplate<'q, DB> + Clone + Send + 'q,
+    i64: Encode<'q, DB> + Type<DB>,
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+{
+    assert_send(&detect_backend_db(&mut *conn));
+    assert_send(&template.clone().adapter().fetch_all(read_write));
+    assert_send(
+        &template
+            .clone()
+            .adapter()
+            .execute_with_savepoint("sp", conn),
+    );
+}
+
+fn connections<'q, DB, T>(template: T, conn: &mut DB::Connection, pool: &Pool<DB>)
+where
+    DB: Database,
+    T: SqlTemplate<'q, DB> + Clone + Send,
+    (String,): for<'r> FromRow<'r, DB::Row>,
+    String: for<'e> Encode<'e, DB> + Type<DB>,
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+{
+    assert_send(&with_savepoint(conn, "sp", |_| Box::pin(async { Ok(()) })));
+    assert_send(&execute_batch(vec![template], conn));
+    assert_send(&ScriptRunner::<DB>::new().run(pool));
+}
+
+fn any_pool<'q, T>(template: T, pool: &'q AnyPool)
+where
+    T: SqlTemplate<'q, Any> + Clone + Send + 'q,
+{
+    // the `Any` pool detects the backend by downcasting
+    assert_send(&detect_backend_db(pool));
+    assert_send(&template.clone().adapter().count(pool));
+    assert_send(&template.adapter().fetch_all(pool));
+}
+
+macro_rules! instantiate {
+    ($($db:ty),*) => {$(
+        let _: fn(IdQuery, &'static Pool<$db>) = adapter_futures::<$db, IdQuery, (i64,)>;
+        let _: fn(IdQuery, &'static Pool<$db>) = counted_pagination::<$db, IdQuery, (i64,)>;
+        let _: fn(IdQuery, &'static mut <$db as Database>::Connection, &'static ReadWriteAdapter<$db>) =
+            adapter_executors::<$db, IdQuery>;
+        let _: fn(IdQuery, &mut <$db as Database>::Connection, &Pool<$db>) =
+            connections::<$db, IdQuery>;
+    )*};
+}
+
+#[test]
+fn public_futures_are_send() {
+    instantiate!(Postgres, MySql, Sqlite, Any);
+    let _: fn(IdQuery, &'static AnyPool) = any_pool::<IdQuery>;
+}
@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use sqlx::SqlitePool;
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx_askama_template::{Error, SqlTemplate, UsageError};
+
+#[derive(SqlTemplate)]
+#[template(source = "select {{e(id)}} as id")]
+struct IdQuery {
+    id: i64,
+}
+
+async fn single_connection_pool() -> SqlitePool {
+    SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap()
+}
+
+async fn cache_size(pool: &SqlitePool) -> i64 {
+    sqlx::query_scalar("PRAGMA cache_size")
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn session_setup_does_not_leak_to_next_acquire() {
+    let pool = single_connection_pool().await;
+    let default = cache_size(&pool).await;
+
+    let rows = IdQuery { id: 7 }
+        .adapter()
+        .with_session_setup(["PRAGMA cache_size = 123"])
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 1);
+
+    assert_eq!(cache_size(&pool).await, default);
+}
+
+#[tokio::test]
+async fn db_timeout_does_not_leak_to_next_acquire() {
+    let pool = single_connection_pool().await;
+    let busy_timeout = || async {
+        sqlx::query_scalar::<_, i64>("PRAGMA busy_timeout")
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+    };
+    let default = busy_timeout().await;
+
+    let rows = IdQuery { id: 7 }
+        .adapter()
+        .with_db_timeout(Duration::from_millis(1234))
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 1);
+
+    assert_eq!(busy_timeout().await, default);
+}
+
+#[tokio::test]
+async fn local_setting_needs_postgres() {
+    let pool = single_connection_pool().await;
+    let err = IdQuery { id: 7 }
+        .adapter()
+        .with_local_setting("app.current_user_id", 42_i64)
+        .fetch_all(&pool)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Dialect(_)), "{err:?}");
+    assert!(
+        matches!(err.usage(), Some(UsageError::Unsupported(_))),
+        "{err:?}"
+    );
+}
@@ -0,0 +1 @@
+select {{e(id)}} as id
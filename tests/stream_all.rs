@@ -0,0 +1,61 @@
+use futures_util::TryStreamExt;
+use sqlx::{SqlitePool, sqlite::SqlitePoolOptions};
+use sqlx_askama_template::{SortSpec, SqlTemplate};
+
+#[derive(SqlTemplate, Clone)]
+#[template(source = "select id from items where id > {{ e(min_id) }} order by id")]
+#[sql_template(order_by = "id")]
+struct ItemsQuery {
+    min_id: i64,
+}
+
+async fn items_pool() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    sqlx::query("create table items (id integer primary key)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("insert into items (id) values (1), (2), (3), (4), (5)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    pool
+}
+
+#[tokio::test]
+async fn sorted_stream_follows_the_last_row() {
+    let pool = items_pool().await;
+    let mut ids = ItemsQuery { min_id: 0 }
+        .adapter()
+        .with_sort([SortSpec::asc("id")])
+        .stream_all_as::<_, (i64,)>(2, &pool);
+    let mut seen = Vec::new();
+    while let Some((id,)) = ids.try_next().await.unwrap() {
+        seen.push(id);
+        if id == 2 {
+            // an offset for the second pagination would now skip id 3
+            sqlx::query("delete from items where id = 1")
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+    }
+    assert_eq!(seen, [1, 2, 3, 4, 5]);
+}
+
+#[tokio::test]
+async fn stream_paginations_are_cancellable() {
+    let pool = items_pool().await;
+    let err = ItemsQuery { min_id: 0 }
+        .adapter()
+        .with_cancellation(async {})
+        .stream_all_as::<_, (i64,)>(2, &pool)
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("query cancelled"), "{err}");
+}
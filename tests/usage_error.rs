@@ -0,0 +1,58 @@
+use sqlx::{Sqlite, SqlitePool};
+use sqlx_askama_template::{Error, SortSpec, SqlTemplate, StatementKind, UsageError};
+
+#[derive(SqlTemplate)]
+#[template(source = "select {{e(id)}} as id")]
+struct IdQuery {
+    id: i64,
+}
+
+async fn pool() -> SqlitePool {
+    SqlitePool::connect("sqlite::memory:").await.unwrap()
+}
+
+#[tokio::test]
+async fn wrong_statement_kind() {
+    let pool = pool().await;
+    let err = IdQuery { id: 1 }
+        .adapter()
+        .expect_statement(StatementKind::Update)
+        .fetch_all(&pool)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Render(_)), "{err:?}");
+    assert_eq!(
+        err.usage(),
+        Some(&UsageError::StatementKind {
+            expected: StatementKind::Update,
+            found: Some(StatementKind::Select),
+        })
+    );
+}
+
+#[tokio::test]
+async fn sort_column_outside_order_by() {
+    let pool = pool().await;
+    let err = IdQuery { id: 1 }
+        .adapter()
+        .with_sort([SortSpec::asc("password")])
+        .fetch_all(&pool)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.usage(),
+        Some(&UsageError::SortColumn("password".to_string()))
+    );
+}
+
+#[test]
+fn missing_count_block() {
+    let err = SqlTemplate::<Sqlite>::render_count_sql(&IdQuery { id: 1 }).unwrap_err();
+    assert_eq!(err.usage(), Some(&UsageError::MissingCountBlock));
+}
+
+#[test]
+fn database_errors_are_not_usage_errors() {
+    let err = Error::from(sqlx::Error::RowNotFound);
+    assert_eq!(err.usage(), None);
+}